@@ -0,0 +1,687 @@
+use crate::registry::ToolOutput;
+use crate::secret;
+use anyhow::{anyhow, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::fs::{File, OpenOptions};
+use std::future::Future;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex, OnceLock, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const DEFAULT_MAX_BYTES: u64 = 10 * 1024 * 1024;
+pub const DEFAULT_KEEP_FILES: usize = 5;
+
+static GLOBAL_OPTIONS: OnceLock<AuditOptions> = OnceLock::new();
+/// Channel to the thread that writes the log, started by the first entry.
+static WRITER: OnceLock<mpsc::Sender<Message>> = OnceLock::new();
+/// Held for writing while the logs are rotated, and for reading by `search`,
+/// so that a search does not see a file twice or miss one.
+static ROTATION: RwLock<()> = RwLock::new(());
+
+/// How long `flush` waits for pending entries to be written.
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+/// Bytes read at a time while a log file is searched from its end.
+const SEARCH_BLOCK: usize = 64 * 1024;
+/// Arguments that carry record contents or text derived from them.
+const CONTENT_ARGUMENTS: [&str; 4] = ["documents", "metadatas", "where_document", "query_texts"];
+
+tokio::task_local! {
+    static CLIENT: ClientIdentity;
+}
+
+/// How document contents, metadata, document filters and query texts in
+/// tool arguments are written to the audit log.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum DocumentLogging {
+    /// Write contents as sent
+    Keep,
+    /// Replace each value with its SHA-256 digest
+    #[default]
+    Hash,
+    /// Replace each value with a placeholder
+    Redact,
+}
+
+#[derive(Debug, Clone)]
+pub struct AuditOptions {
+    /// Log file; no log is written when unset
+    pub path: Option<PathBuf>,
+    pub documents: DocumentLogging,
+    /// Size at which the log is rotated
+    pub max_bytes: u64,
+    /// Rotated files kept as `<path>.1` (newest) to `<path>.N`
+    pub keep_files: usize,
+}
+
+impl Default for AuditOptions {
+    fn default() -> Self {
+        Self {
+            path: None,
+            documents: DocumentLogging::default(),
+            max_bytes: DEFAULT_MAX_BYTES,
+            keep_files: DEFAULT_KEEP_FILES,
+        }
+    }
+}
+
+pub fn configure(options: AuditOptions) {
+    if GLOBAL_OPTIONS.set(options).is_err() {
+        tracing::warn!("Audit log is already configured");
+    }
+}
+
+fn options() -> &'static AuditOptions {
+    GLOBAL_OPTIONS.get_or_init(AuditOptions::default)
+}
+
+/// Whether tool calls are being logged.
+pub fn enabled() -> bool {
+    options().path.is_some()
+}
+
+enum Message {
+    Entry(Box<AuditEntry>),
+    Flush(mpsc::Sender<()>),
+}
+
+/// Who made a call: the transport and session it came through, and the
+/// client named in the session's `initialize` request.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClientIdentity {
+    pub transport: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+/// Identity shared by the requests of one session.
+pub type SharedClient = Arc<Mutex<ClientIdentity>>;
+
+impl ClientIdentity {
+    pub fn new(transport: &str, session: Option<String>) -> Self {
+        Self {
+            transport: transport.to_string(),
+            session,
+            name: None,
+            version: None,
+        }
+    }
+
+    pub fn shared(transport: &str, session: Option<String>) -> SharedClient {
+        Arc::new(Mutex::new(Self::new(transport, session)))
+    }
+
+    /// Takes the client's name and version from the params of an
+    /// `initialize` request.
+    pub fn initialize(&mut self, params: Option<&Value>) {
+        let info = params.and_then(|params| params.get("clientInfo"));
+        let field = |name| info.and_then(|info| info.get(name)).and_then(Value::as_str).map(str::to_string);
+        self.name = field("name");
+        self.version = field("version");
+    }
+}
+
+/// Runs `future` with `client` as the identity its tool calls are logged
+/// under.
+pub async fn with_client<F: Future>(client: ClientIdentity, future: F) -> F::Output {
+    CLIENT.scope(client, future).await
+}
+
+/// Identity of the client making the current call; the default outside
+/// `with_client`.
+pub fn current_client() -> ClientIdentity {
+    CLIENT.try_with(Clone::clone).unwrap_or_default()
+}
+
+/// How a logged call ended; `rejected` calls named a tool that does not
+/// exist or is hidden by policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    Ok,
+    Error,
+    Cancelled,
+    Rejected,
+}
+
+impl AuditOutcome {
+    fn as_str(&self) -> &'static str {
+        match self {
+            AuditOutcome::Ok => "ok",
+            AuditOutcome::Error => "error",
+            AuditOutcome::Cancelled => "cancelled",
+            AuditOutcome::Rejected => "rejected",
+        }
+    }
+}
+
+/// One tool call, as written to the audit log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Unix time the call finished, in seconds
+    pub timestamp: u64,
+    pub tool: String,
+    /// Collections the call read or changed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub collections: Vec<String>,
+    pub arguments: Value,
+    pub outcome: AuditOutcome,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Ids of the records the call changed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub affected_ids: Vec<String>,
+    pub duration_ms: u64,
+    pub client: ClientIdentity,
+}
+
+impl ToolOutput for AuditEntry {
+    fn summary(&self) -> String {
+        let mut summary = format!("{} {} {}", self.timestamp, self.tool, self.outcome.as_str());
+        if !self.collections.is_empty() {
+            summary.push_str(&format!(" on '{}'", self.collections.join("', '")));
+        }
+        if !self.affected_ids.is_empty() {
+            summary.push_str(&format!(", {} ids affected", self.affected_ids.len()));
+        }
+        if let Some(client) = &self.client.name {
+            summary.push_str(&format!(" by {}", client));
+        }
+        if let Some(error) = &self.error {
+            summary.push_str(&format!(": {}", error));
+        }
+        summary
+    }
+}
+
+/// Appends a finished call of `tool` on `collections` to the log. `result` is
+/// the call's structured output or its error.
+pub fn record(
+    tool: &str,
+    collections: Vec<String>,
+    arguments: &Value,
+    result: Result<&Value, &anyhow::Error>,
+    duration: Duration,
+) {
+    let (outcome, error, affected_ids) = match result {
+        Ok(output) => (AuditOutcome::Ok, None, affected_ids(output)),
+        Err(e) => {
+            let outcome = match crate::cancel::is_cancellation(e) {
+                true => AuditOutcome::Cancelled,
+                false => AuditOutcome::Error,
+            };
+            (outcome, Some(secret::scrub(&format!("{:#}", e))), Vec::new())
+        }
+    };
+    write(tool, collections, arguments, outcome, error, affected_ids, duration);
+}
+
+/// Appends a call of a tool that could not be run to the log.
+pub fn record_rejected(tool: &str, collections: Vec<String>, arguments: &Value, reason: &str) {
+    let reason = Some(secret::scrub(reason));
+    write(tool, collections, arguments, AuditOutcome::Rejected, reason, Vec::new(), Duration::ZERO);
+}
+
+fn write(
+    tool: &str,
+    collections: Vec<String>,
+    arguments: &Value,
+    outcome: AuditOutcome,
+    error: Option<String>,
+    affected_ids: Vec<String>,
+    duration: Duration,
+) {
+    let Some(path) = &options().path else {
+        return;
+    };
+    let entry = AuditEntry {
+        timestamp: unix_now(),
+        tool: tool.to_string(),
+        collections,
+        arguments: redact(arguments, options().documents),
+        outcome,
+        error,
+        affected_ids,
+        duration_ms: duration.as_millis() as u64,
+        client: current_client(),
+    };
+    if writer().send(Message::Entry(Box::new(entry))).is_err() {
+        tracing::warn!("Cannot write audit log '{}': the writer has stopped", path.display());
+    }
+}
+
+/// Entries are written by a dedicated thread, so that tool calls never wait
+/// on the log's file I/O.
+fn writer() -> &'static mpsc::Sender<Message> {
+    WRITER.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel();
+        if let Err(e) = std::thread::Builder::new()
+            .name("audit-log".to_string())
+            .spawn(move || write_entries(receiver))
+        {
+            tracing::warn!("Cannot start the audit log writer: {}", secret::scrub(&e.to_string()));
+        }
+        sender
+    })
+}
+
+fn write_entries(receiver: mpsc::Receiver<Message>) {
+    let Some(path) = &options().path else {
+        return;
+    };
+    for message in receiver {
+        match message {
+            Message::Entry(entry) => {
+                if let Err(e) = append(options(), path, &entry) {
+                    tracing::warn!("Cannot write audit log '{}': {}", path.display(), secret::scrub(&e.to_string()));
+                }
+            }
+            Message::Flush(done) => {
+                let _ = done.send(());
+            }
+        }
+    }
+}
+
+/// Waits until the entries recorded so far are written, for use before the
+/// server exits.
+pub fn flush() {
+    let Some(writer) = WRITER.get() else {
+        return;
+    };
+    let (done, written) = mpsc::channel();
+    if writer.send(Message::Flush(done)).is_ok() && written.recv_timeout(FLUSH_TIMEOUT).is_err() {
+        tracing::warn!("Timed out writing the audit log");
+    }
+}
+
+/// Ids a call reports as written or deleted; previews and dry runs change
+/// nothing.
+fn affected_ids(output: &Value) -> Vec<String> {
+    if output.get("dry_run") == Some(&Value::Bool(true)) || output.get("confirmation_token").is_some() {
+        return Vec::new();
+    }
+    output
+        .get("ids")
+        .and_then(Value::as_array)
+        .map(|ids| ids.iter().filter_map(Value::as_str).map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// Applies `logging` to the content arguments of a call. Lists are handled
+/// per item, so that the log still shows how many were sent.
+fn redact(arguments: &Value, logging: DocumentLogging) -> Value {
+    let mut arguments = arguments.clone();
+    if logging == DocumentLogging::Keep {
+        return arguments;
+    }
+    for key in CONTENT_ARGUMENTS {
+        match arguments.get_mut(key) {
+            Some(Value::Array(items)) => items.iter_mut().for_each(|item| redact_value(item, logging)),
+            Some(value) => redact_value(value, logging),
+            None => {}
+        }
+    }
+    arguments
+}
+
+fn redact_value(value: &mut Value, logging: DocumentLogging) {
+    if value.is_null() {
+        return;
+    }
+    match logging {
+        DocumentLogging::Keep => {}
+        DocumentLogging::Hash => {
+            let bytes = match &*value {
+                Value::String(text) => text.as_bytes().to_vec(),
+                other => other.to_string().into_bytes(),
+            };
+            *value = json!(digest(&bytes));
+        }
+        DocumentLogging::Redact => *value = json!("[redacted]"),
+    }
+}
+
+fn digest(bytes: &[u8]) -> String {
+    let hex: String = Sha256::digest(bytes).iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("sha256:{}", hex)
+}
+
+fn append(options: &AuditOptions, path: &Path, entry: &AuditEntry) -> Result<()> {
+    let mut line = serde_json::to_vec(entry)?;
+    line.push(b'\n');
+
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    if let Ok(metadata) = std::fs::metadata(path)
+        && metadata.len() > 0
+        && metadata.len() + line.len() as u64 > options.max_bytes
+    {
+        let _rotation = ROTATION.write().unwrap();
+        rotate(path, options.keep_files)?;
+    }
+    OpenOptions::new().create(true).append(true).open(path)?.write_all(&line)?;
+    Ok(())
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", index));
+    PathBuf::from(name)
+}
+
+/// Shifts `<path>.N` to `<path>.N+1`, dropping the oldest, and moves the
+/// current log to `<path>.1`.
+fn rotate(path: &Path, keep: usize) -> Result<()> {
+    if keep == 0 {
+        return Ok(std::fs::remove_file(path)?);
+    }
+    remove_if_exists(&rotated_path(path, keep))?;
+    for index in (1..keep).rev() {
+        let from = rotated_path(path, index);
+        if from.exists() {
+            std::fs::rename(&from, rotated_path(path, index + 1))?;
+        }
+    }
+    std::fs::rename(path, rotated_path(path, 1))?;
+    tracing::info!("Rotated audit log '{}'", path.display());
+    Ok(())
+}
+
+fn remove_if_exists(path: &Path) -> Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Conditions an entry must meet to be returned by `search`.
+#[derive(Debug, Default)]
+pub struct AuditFilter {
+    pub tool: Option<String>,
+    pub collection_name: Option<String>,
+    pub outcome: Option<AuditOutcome>,
+    pub client_name: Option<String>,
+    /// Only calls that affected this record id
+    pub id: Option<String>,
+    /// Only calls at or after this Unix time, in seconds
+    pub since: Option<u64>,
+}
+
+impl AuditFilter {
+    fn matches(&self, entry: &AuditEntry) -> bool {
+        self.tool.as_ref().is_none_or(|tool| *tool == entry.tool)
+            && self.collection_name.as_ref().is_none_or(|name| entry.collections.contains(name))
+            && self.outcome.is_none_or(|outcome| outcome == entry.outcome)
+            && self.client_name.as_ref().is_none_or(|name| Some(name) == entry.client.name.as_ref())
+            && self.id.as_ref().is_none_or(|id| entry.affected_ids.contains(id))
+            && self.since.is_none_or(|since| entry.timestamp >= since)
+    }
+}
+
+/// Returns up to `limit` entries matching `filter` and `visible`, newest
+/// first, reading the current log and then the rotated ones. Entries recorded
+/// before the call are written first; later ones are appended while the
+/// search runs, so a line that is not complete yet is
+/// skipped like a malformed one.
+pub fn search(filter: &AuditFilter, visible: impl Fn(&AuditEntry) -> bool, limit: usize) -> Result<Vec<AuditEntry>> {
+    let path = options()
+        .path
+        .as_ref()
+        .ok_or_else(|| anyhow!("The audit log is disabled; start the server with --audit-log to enable it"))?;
+
+    flush();
+    search_files(path, options().keep_files, filter, visible, limit)
+}
+
+/// Searches the log at `path` and its `keep` rotated files. The files are
+/// opened under the rotation lock and read after it is released, from the
+/// end, so that the search stops once `limit` entries are found.
+fn search_files(
+    path: &Path,
+    keep: usize,
+    filter: &AuditFilter,
+    visible: impl Fn(&AuditEntry) -> bool,
+    limit: usize,
+) -> Result<Vec<AuditEntry>> {
+    let files = {
+        let _rotation = ROTATION.read().unwrap();
+        let mut files = Vec::new();
+        for file in std::iter::once(path.to_path_buf()).chain((1..=keep).map(|index| rotated_path(path, index))) {
+            match File::open(&file) {
+                Ok(opened) => files.push((file, opened)),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(anyhow!("Cannot read audit log '{}': {}", file.display(), e)),
+            }
+        }
+        files
+    };
+
+    let mut found = Vec::new();
+    for (path, file) in files {
+        let lines = ReverseLines::new(file).map_err(|e| anyhow!("Cannot read audit log '{}': {}", path.display(), e))?;
+        for line in lines {
+            let Ok(line) = line else {
+                break;
+            };
+            let Ok(entry) = serde_json::from_slice::<AuditEntry>(&line) else {
+                continue;
+            };
+            if filter.matches(&entry) && visible(&entry) {
+                found.push(entry);
+                if found.len() >= limit {
+                    return Ok(found);
+                }
+            }
+        }
+    }
+    Ok(found)
+}
+
+/// Lines of a file from last to first, read in blocks from its end as it
+/// was when opened.
+struct ReverseLines {
+    file: File,
+    /// Start of the part of the file not read yet
+    position: u64,
+    /// Start of the earliest block read, which may be the end of a line
+    partial: Vec<u8>,
+    /// Complete lines of the blocks read, last on top
+    lines: Vec<Vec<u8>>,
+}
+
+impl ReverseLines {
+    fn new(file: File) -> std::io::Result<Self> {
+        let position = file.metadata()?.len();
+        Ok(Self {
+            file,
+            position,
+            partial: Vec::new(),
+            lines: Vec::new(),
+        })
+    }
+
+    fn read_block(&mut self) -> std::io::Result<()> {
+        let size = SEARCH_BLOCK.min(self.position as usize);
+        self.position -= size as u64;
+        let mut block = vec![0; size];
+        self.file.seek(SeekFrom::Start(self.position))?;
+        self.file.read_exact(&mut block)?;
+        block.append(&mut self.partial);
+
+        let mut lines = block.split(|byte| *byte == b'\n');
+        self.partial = lines.next().unwrap_or_default().to_vec();
+        self.lines = lines.map(<[u8]>::to_vec).collect();
+        Ok(())
+    }
+}
+
+impl Iterator for ReverseLines {
+    type Item = std::io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(line) = self.lines.pop() {
+                return Some(Ok(line));
+            }
+            if self.position == 0 {
+                return (!self.partial.is_empty()).then(|| Ok(std::mem::take(&mut self.partial)));
+            }
+            if let Err(e) = self.read_block() {
+                self.position = 0;
+                self.partial.clear();
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh log path under the system temp dir, for the test `name`.
+    fn log_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mcp-chroma-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join("audit.jsonl")
+    }
+
+    fn entry(tool: &str, collections: &[&str], outcome: AuditOutcome) -> AuditEntry {
+        AuditEntry {
+            timestamp: 1,
+            tool: tool.to_string(),
+            collections: collections.iter().map(|name| name.to_string()).collect(),
+            arguments: json!({}),
+            outcome,
+            error: None,
+            affected_ids: Vec::new(),
+            duration_ms: 0,
+            client: ClientIdentity::new("stdio", None),
+        }
+    }
+
+    fn tools(entries: &[AuditEntry]) -> Vec<&str> {
+        entries.iter().map(|entry| entry.tool.as_str()).collect()
+    }
+
+    #[test]
+    fn rotation_keeps_the_newest_files() {
+        let path = log_path("audit-rotation");
+        let line = serde_json::to_vec(&entry("tool0", &["notes"], AuditOutcome::Ok)).unwrap().len() as u64 + 1;
+        let options = AuditOptions {
+            path: Some(path.clone()),
+            max_bytes: 2 * line,
+            keep_files: 2,
+            ..AuditOptions::default()
+        };
+        for index in 0..10 {
+            append(&options, &path, &entry(&format!("tool{}", index), &["notes"], AuditOutcome::Ok)).unwrap();
+        }
+
+        assert!(rotated_path(&path, 2).exists());
+        assert!(!rotated_path(&path, 3).exists());
+        let found = search_files(&path, 2, &AuditFilter::default(), |_| true, 100).unwrap();
+        assert_eq!(tools(&found), ["tool9", "tool8", "tool7", "tool6", "tool5", "tool4"]);
+    }
+
+    #[test]
+    fn search_filters_entries_and_stops_at_limit() {
+        let path = log_path("audit-search");
+        let options = AuditOptions {
+            path: Some(path.clone()),
+            ..AuditOptions::default()
+        };
+        for entry in [
+            entry("chroma_clone_collection", &["notes", "hidden_copy"], AuditOutcome::Ok),
+            entry("chroma_add_documents", &["notes"], AuditOutcome::Error),
+            entry("chroma_restore", &["notes"], AuditOutcome::Ok),
+            entry("chroma_list_collections", &[], AuditOutcome::Ok),
+        ] {
+            append(&options, &path, &entry).unwrap();
+        }
+        let search = |filter: &AuditFilter, limit| {
+            let visible = |entry: &AuditEntry| entry.collections.iter().all(|name| !name.starts_with("hidden_"));
+            search_files(&path, 0, filter, visible, limit).unwrap()
+        };
+
+        let all = search(&AuditFilter::default(), 100);
+        assert_eq!(tools(&all), ["chroma_list_collections", "chroma_restore", "chroma_add_documents"]);
+        let notes = AuditFilter {
+            collection_name: Some("notes".to_string()),
+            ..AuditFilter::default()
+        };
+        assert_eq!(tools(&search(&notes, 1)), ["chroma_restore"]);
+        let errors = AuditFilter {
+            outcome: Some(AuditOutcome::Error),
+            ..AuditFilter::default()
+        };
+        assert_eq!(tools(&search(&errors, 100)), ["chroma_add_documents"]);
+        let copy = AuditFilter {
+            collection_name: Some("hidden_copy".to_string()),
+            ..AuditFilter::default()
+        };
+        assert!(search(&copy, 100).is_empty());
+    }
+
+    #[test]
+    fn lines_are_read_backwards_across_blocks() {
+        let path = log_path("audit-reverse");
+        let lines: Vec<String> = ["a", "b", "c"].iter().map(|fill| fill.repeat(SEARCH_BLOCK + 10)).collect();
+        std::fs::write(&path, format!("{}\n{}\n{}\npartial", lines[0], lines[1], lines[2])).unwrap();
+
+        let read: Vec<Vec<u8>> = ReverseLines::new(File::open(&path).unwrap())
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        let expected: Vec<&[u8]> = vec![b"partial", lines[2].as_bytes(), lines[1].as_bytes(), lines[0].as_bytes()];
+        assert_eq!(read, expected);
+    }
+
+    #[test]
+    fn contents_metadata_and_document_filters_are_redacted() {
+        let arguments = json!({
+            "collection_name": "notes",
+            "documents": ["secret text"],
+            "metadatas": [{"owner": "alice"}, null],
+            "where_document": {"$contains": "secret"},
+            "query_texts": ["secret"],
+            "where": {"owner": "alice"},
+        });
+
+        let redacted = redact(&arguments, DocumentLogging::Redact);
+        assert_eq!(
+            redacted,
+            json!({
+                "collection_name": "notes",
+                "documents": ["[redacted]"],
+                "metadatas": ["[redacted]", null],
+                "where_document": "[redacted]",
+                "query_texts": ["[redacted]"],
+                "where": {"owner": "alice"},
+            })
+        );
+
+        let hashed = redact(&arguments, DocumentLogging::Hash);
+        assert_eq!(hashed["documents"][0], json!(digest(b"secret text")));
+        assert_eq!(hashed["metadatas"][0], json!(digest(br#"{"owner":"alice"}"#)));
+        assert_eq!(hashed["where_document"], json!(digest(br#"{"$contains":"secret"}"#)));
+        assert_eq!(redact(&arguments, DocumentLogging::Keep), arguments);
+    }
+}
@@ -0,0 +1,280 @@
+use crate::dump::Record;
+use crate::pagination::Cursor;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+pub const DEFAULT_MAX_OUTPUT_CHARS: usize = 40_000;
+pub const DEFAULT_MAX_DOCUMENT_CHARS: usize = 4_000;
+pub const APPROX_CHARS_PER_TOKEN: usize = 4;
+pub const TRUNCATION_MARKER: &str = "… [truncated]";
+
+/// Characters of a document always returned when a single record exceeds the
+/// whole output budget, so that paging through it still makes progress. Also
+/// the lowest `max_document_chars` accepted.
+const MIN_DOCUMENT_CHARS: usize = 256;
+
+static GLOBAL_BUDGET: OnceLock<OutputBudget> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputBudget {
+    pub max_output_chars: usize,
+    pub max_document_chars: usize,
+}
+
+impl Default for OutputBudget {
+    fn default() -> Self {
+        Self {
+            max_output_chars: DEFAULT_MAX_OUTPUT_CHARS,
+            max_document_chars: DEFAULT_MAX_DOCUMENT_CHARS,
+        }
+    }
+}
+
+impl OutputBudget {
+    pub fn new(max_output_chars: Option<usize>, max_output_tokens: Option<usize>, max_document_chars: Option<usize>) -> Self {
+        let defaults = Self::default();
+        Self {
+            max_output_chars: min_limit(max_output_chars, tokens_to_chars(max_output_tokens))
+                .unwrap_or(defaults.max_output_chars),
+            max_document_chars: max_document_chars
+                .unwrap_or(defaults.max_document_chars)
+                .max(MIN_DOCUMENT_CHARS),
+        }
+    }
+
+    /// Narrows the global budget with the limits of a single request. A request
+    /// can lower the limits but never raise them above the global ones.
+    pub fn for_request(
+        max_output_chars: Option<usize>,
+        max_output_tokens: Option<usize>,
+        max_document_chars: Option<usize>,
+    ) -> Self {
+        let global = global_budget();
+        Self {
+            max_output_chars: min_limit(max_output_chars, tokens_to_chars(max_output_tokens))
+                .map_or(global.max_output_chars, |limit| limit.min(global.max_output_chars)),
+            max_document_chars: max_document_chars
+                .map_or(global.max_document_chars, |limit| limit.min(global.max_document_chars))
+                .max(MIN_DOCUMENT_CHARS),
+        }
+    }
+}
+
+pub fn configure(budget: OutputBudget) {
+    if GLOBAL_BUDGET.set(budget).is_err() {
+        tracing::warn!("Output budget is already configured");
+    }
+}
+
+pub fn global_budget() -> OutputBudget {
+    GLOBAL_BUDGET.get().copied().unwrap_or_default()
+}
+
+fn tokens_to_chars(tokens: Option<usize>) -> Option<usize> {
+    tokens.map(|tokens| tokens.saturating_mul(APPROX_CHARS_PER_TOKEN))
+}
+
+fn min_limit(a: Option<usize>, b: Option<usize>) -> Option<usize> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Truncation {
+    pub truncated: bool,
+    /// Records fetched from the collection before truncation
+    pub original_count: usize,
+    /// Approximate size of the fetched records in characters before truncation
+    pub original_chars: usize,
+    /// Approximate size of the returned records in characters
+    pub returned_chars: usize,
+    /// Character offset the first returned document starts at when resuming
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub document_offset: Option<usize>,
+    /// Records whose documents were cut to `max_document_chars` while the page
+    /// went on past them; get one by id to page through the rest of it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cut_ids: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+/// Fits `records`, a page fetched starting at `start`, into `budget`.
+///
+/// A document longer than `max_document_chars` is cut and the page goes on
+/// with the records after it; when it is the last record fetched, the cursor
+/// resumes inside it instead. The page stops at the first record that does
+/// not fit the remaining budget, and the cursor resumes there. Otherwise the
+/// cursor points past the page when `has_more` says more records follow it.
+pub fn truncate_records(records: Vec<Record>, start: &Cursor, budget: OutputBudget, has_more: bool) -> (Vec<Record>, Truncation) {
+    let count = records.len();
+    let mut truncation = Truncation {
+        original_count: count,
+        document_offset: (start.document_offset > 0).then_some(start.document_offset),
+        ..Truncation::default()
+    };
+    let mut returned: Vec<Record> = Vec::new();
+    let mut remaining = budget.max_output_chars;
+    let mut resume = None;
+
+    for (index, mut record) in records.into_iter().enumerate() {
+        let document_offset = if index == 0 { start.document_offset } else { 0 };
+        if document_offset > 0 {
+            record.document = record
+                .document
+                .map(|document| document.chars().skip(document_offset).collect());
+        }
+        let size = record_chars(&record);
+        truncation.original_chars += size;
+        if resume.is_some() {
+            continue;
+        }
+
+        let document = document_chars(&record);
+        let overhead = size.saturating_sub(document);
+        let previous_key = returned.last().map(|record| record.id.clone());
+        if overhead + document.min(budget.max_document_chars) <= remaining {
+            if cut_document(&mut record, budget.max_document_chars) {
+                if index + 1 == count {
+                    resume = Some(Cursor {
+                        document_offset: document_offset + budget.max_document_chars,
+                        ..start.advance(index, previous_key.as_deref())
+                    });
+                } else {
+                    truncation.cut_ids.push(record.id.clone());
+                }
+            }
+            let size = record_chars(&record);
+            remaining = remaining.saturating_sub(size);
+            truncation.returned_chars += size;
+            returned.push(record);
+            continue;
+        }
+
+        let mut document_limit = budget.max_document_chars.min(remaining.saturating_sub(overhead));
+        if returned.is_empty() {
+            document_limit = document_limit.max(MIN_DOCUMENT_CHARS);
+        }
+        resume = Some(if returned.is_empty() || document_limit >= MIN_DOCUMENT_CHARS.min(document) {
+            let cut = cut_document(&mut record, document_limit);
+            let id = record.id.clone();
+            truncation.returned_chars += record_chars(&record);
+            returned.push(record);
+            if cut {
+                Cursor {
+                    document_offset: document_offset + document_limit,
+                    ..start.advance(index, previous_key.as_deref())
+                }
+            } else {
+                start.advance(index + 1, Some(&id))
+            }
+        } else {
+            start.advance(index, previous_key.as_deref())
+        });
+    }
+
+    truncation.truncated = resume.is_some() || !truncation.cut_ids.is_empty();
+    if resume.is_none() && has_more {
+        resume = Some(start.advance(returned.len(), returned.last().map(|record| record.id.as_str())));
+    }
+    truncation.next_cursor = resume.map(|cursor| cursor.encode());
+    (returned, truncation)
+}
+
+/// Cuts the record's document to `limit` characters followed by the
+/// truncation marker; returns whether it was cut.
+fn cut_document(record: &mut Record, limit: usize) -> bool {
+    match record.document.as_mut() {
+        Some(document) if document.chars().count() > limit => {
+            *document = document.chars().take(limit).collect::<String>() + TRUNCATION_MARKER;
+            true
+        }
+        _ => false,
+    }
+}
+
+fn document_chars(record: &Record) -> usize {
+    record.document.as_deref().map_or(0, |document| document.chars().count())
+}
+
+fn record_chars(record: &Record) -> usize {
+    serde_json::to_string(record).map_or(0, |json| json.chars().count())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(id: &str, document_chars: usize) -> Record {
+        Record {
+            id: id.to_string(),
+            document: Some((0..document_chars).map(|i| (b'a' + (i % 26) as u8) as char).collect()),
+            metadata: None,
+            embedding: None,
+        }
+    }
+
+    fn budget(max_output_chars: usize, max_document_chars: usize) -> OutputBudget {
+        OutputBudget {
+            max_output_chars,
+            max_document_chars,
+        }
+    }
+
+    #[test]
+    fn zero_document_limit_is_raised_to_minimum() {
+        assert_eq!(OutputBudget::new(None, None, Some(0)).max_document_chars, MIN_DOCUMENT_CHARS);
+        assert_eq!(OutputBudget::for_request(None, None, Some(0)).max_document_chars, MIN_DOCUMENT_CHARS);
+    }
+
+    #[test]
+    fn page_goes_on_after_a_cut_document() {
+        let records = vec![record("a", 10), record("b", 1_000), record("c", 10)];
+        let (returned, truncation) = truncate_records(records, &Cursor::offset(0), budget(10_000, 300), false);
+
+        let ids: Vec<&str> = returned.iter().map(|record| record.id.as_str()).collect();
+        assert_eq!(ids, ["a", "b", "c"]);
+        assert_eq!(document_chars(&returned[1]), 300 + TRUNCATION_MARKER.chars().count());
+        assert_eq!(truncation.cut_ids, ["b"]);
+        assert!(truncation.truncated);
+        assert_eq!(truncation.next_cursor, None);
+    }
+
+    #[test]
+    fn last_cut_document_is_paged_through() {
+        let original = record("a", 1_000).document.unwrap();
+        let mut start = Cursor::offset(0);
+        let mut text = String::new();
+        loop {
+            let (returned, truncation) = truncate_records(vec![record("a", 1_000)], &start, budget(10_000, 300), false);
+            let document = returned[0].document.clone().unwrap();
+            text.push_str(document.strip_suffix(TRUNCATION_MARKER).unwrap_or(&document));
+            match truncation.next_cursor {
+                Some(cursor) => start = Cursor::decode(&cursor).unwrap(),
+                None => break,
+            }
+        }
+        assert_eq!(text, original);
+    }
+
+    #[test]
+    fn page_stops_at_a_record_over_budget() {
+        let records = vec![record("a", 200), record("b", 200), record("c", 200)];
+        let (returned, truncation) = truncate_records(records, &Cursor::offset(5), budget(550, 4_000), false);
+
+        assert_eq!(returned.len(), 2);
+        assert!(truncation.truncated);
+        assert_eq!(Cursor::decode(&truncation.next_cursor.unwrap()).unwrap(), Cursor::offset(7));
+    }
+
+    #[test]
+    fn record_over_whole_budget_still_makes_progress() {
+        let (returned, truncation) = truncate_records(vec![record("a", 1_000)], &Cursor::offset(0), budget(10, 4_000), false);
+
+        assert_eq!(document_chars(&returned[0]), MIN_DOCUMENT_CHARS + TRUNCATION_MARKER.chars().count());
+        let next = Cursor::decode(&truncation.next_cursor.unwrap()).unwrap();
+        assert_eq!(next.document_offset, MIN_DOCUMENT_CHARS);
+    }
+}
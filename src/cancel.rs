@@ -0,0 +1,204 @@
+use crate::progress;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// How long a cancelled request waits for blocking work to reach its next
+/// [`check`] and report what it committed.
+const CANCEL_GRACE: Duration = Duration::from_secs(2);
+
+tokio::task_local! {
+    static TOKEN: CancellationToken;
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Request was cancelled by the client")]
+pub struct Cancelled;
+
+/// Runs `future` with `token` as the cancellation token of the request it
+/// serves.
+pub async fn with_token<F: Future>(token: CancellationToken, future: F) -> F::Output {
+    TOKEN.scope(token, future).await
+}
+
+/// Runs `future` until it completes or the current request is cancelled.
+/// Backend calls are synchronous and run to completion once started; only
+/// the [`check`] calls between them stop a request in the middle of its work.
+/// On cancellation, work running in [`blocking`] gets `CANCEL_GRACE` to stop
+/// at such a check and return its partial-result error; after that, or at
+/// any other await point, the future is dropped.
+pub async fn run<T, F>(future: F) -> Result<T>
+where
+    F: Future<Output = Result<T>>,
+{
+    let Ok(token) = TOKEN.try_with(Clone::clone) else {
+        return future.await;
+    };
+    let mut future = std::pin::pin!(future);
+    tokio::select! {
+        biased;
+        _ = token.cancelled() => {}
+        result = &mut future => return result,
+    }
+    tokio::time::timeout(CANCEL_GRACE, future)
+        .await
+        .unwrap_or_else(|_| Err(Cancelled.into()))
+}
+
+/// Runs the synchronous `work` on the blocking thread pool, so that file and
+/// backend I/O does not hold up the runtime's workers. The request's
+/// cancellation token and progress reporting go with it.
+pub async fn blocking<T, F>(work: F) -> Result<T>
+where
+    F: FnOnce() -> Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    let token = TOKEN.try_with(Clone::clone).ok();
+    let work = progress::carry(work);
+    tokio::task::spawn_blocking(move || match token {
+        Some(token) => TOKEN.sync_scope(token, work),
+        None => work(),
+    })
+    .await?
+}
+
+/// Fails with [`Cancelled`] once the current request has been cancelled.
+/// Batched loops call this between batches.
+pub fn check() -> Result<()> {
+    match TOKEN.try_with(CancellationToken::is_cancelled) {
+        Ok(true) => Err(Cancelled.into()),
+        _ => Ok(()),
+    }
+}
+
+pub fn is_cancellation(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<Cancelled>().is_some()
+}
+
+/// Describes the work a cancelled request already committed, using
+/// `committed`; other errors pass through unchanged.
+pub fn partial(error: anyhow::Error, committed: impl FnOnce() -> String) -> anyhow::Error {
+    if is_cancellation(&error) {
+        error.context(committed())
+    } else {
+        error
+    }
+}
+
+/// Requests of one session that are still running, by JSON-RPC id.
+#[derive(Clone, Default)]
+pub struct InFlight {
+    requests: Arc<Mutex<HashMap<u64, CancellationToken>>>,
+}
+
+impl InFlight {
+    pub fn start(&self, id: u64) -> CancellationToken {
+        let token = CancellationToken::new();
+        self.requests.lock().unwrap().insert(id, token.clone());
+        token
+    }
+
+    pub fn finish(&self, id: u64) {
+        self.requests.lock().unwrap().remove(&id);
+    }
+
+    /// Cancels request `id`; returns whether it was still running.
+    pub fn cancel(&self, id: u64) -> bool {
+        match self.requests.lock().unwrap().remove(&id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.requests.lock().unwrap().is_empty()
+    }
+
+    pub fn cancel_all(&self) {
+        for (_, token) in self.requests.lock().unwrap().drain() {
+            token.cancel();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+
+    /// Commits numbered batches on a blocking thread, checking for
+    /// cancellation before each one. Each committed batch is sent on
+    /// `committed`, and the next waits until `proceed` allows it.
+    async fn batches(committed: UnboundedSender<usize>, proceed: mpsc::Receiver<()>) -> Result<usize> {
+        run(blocking(move || {
+            let mut done = 0;
+            for batch in 0..10 {
+                check().map_err(|e| partial(e, || format!("Cancelled after committing {} of 10 batches", done)))?;
+                done = batch + 1;
+                committed.send(done).unwrap();
+                proceed.recv().unwrap();
+            }
+            Ok(done)
+        }))
+        .await
+    }
+
+    #[tokio::test]
+    async fn cancelled_batches_report_what_they_committed() {
+        let in_flight = InFlight::default();
+        let token = in_flight.start(7);
+        let (committed, mut progress) = unbounded_channel();
+        let (proceed, wait) = mpsc::channel();
+        let call = tokio::spawn(with_token(token, batches(committed, wait)));
+
+        assert_eq!(progress.recv().await, Some(1));
+        proceed.send(()).unwrap();
+        assert_eq!(progress.recv().await, Some(2));
+        assert!(in_flight.cancel(7));
+        proceed.send(()).unwrap();
+
+        let error = call.await.unwrap().unwrap_err();
+        assert!(is_cancellation(&error));
+        assert_eq!(error.to_string(), "Cancelled after committing 2 of 10 batches");
+        assert!(in_flight.is_empty());
+    }
+
+    #[tokio::test]
+    async fn requests_without_a_token_run_to_completion() {
+        let (committed, _progress) = unbounded_channel();
+        let (proceed, wait) = mpsc::channel();
+        for _ in 0..10 {
+            proceed.send(()).unwrap();
+        }
+        assert_eq!(batches(committed, wait).await.unwrap(), 10);
+    }
+
+    #[test]
+    fn other_errors_pass_through_partial_unchanged() {
+        let error = partial(anyhow::anyhow!("Collection 'notes' not found"), || unreachable!());
+        assert!(!is_cancellation(&error));
+        assert_eq!(error.to_string(), "Collection 'notes' not found");
+    }
+
+    #[test]
+    fn only_running_requests_can_be_cancelled() {
+        let in_flight = InFlight::default();
+        let first = in_flight.start(1);
+        let second = in_flight.start(2);
+        in_flight.finish(1);
+        assert!(!in_flight.cancel(1));
+        assert!(!in_flight.cancel(3));
+        assert!(!first.is_cancelled());
+
+        in_flight.cancel_all();
+        assert!(second.is_cancelled());
+        assert!(in_flight.is_empty());
+    }
+}
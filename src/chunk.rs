@@ -0,0 +1,643 @@
+use std::ops::Range;
+use std::path::Path;
+
+#[derive(Debug, Clone)]
+pub struct Chunk {
+    pub text: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub symbol: Option<Symbol>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: &'static str,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    Rust,
+    TypeScript,
+    JavaScript,
+}
+
+impl Language {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "rs" => Some(Language::Rust),
+            "ts" | "tsx" | "mts" | "cts" => Some(Language::TypeScript),
+            "js" | "jsx" | "mjs" | "cjs" => Some(Language::JavaScript),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Language::Rust => "rust",
+            Language::TypeScript => "typescript",
+            Language::JavaScript => "javascript",
+        }
+    }
+
+    /// TypeScript and JavaScript share one parser.
+    fn is_ecmascript(&self) -> bool {
+        matches!(self, Language::TypeScript | Language::JavaScript)
+    }
+}
+
+pub fn chunk_text(text: &str, chunk_size: usize, chunk_overlap: usize) -> Vec<Chunk> {
+    let chunk_size = chunk_size.max(1);
+    let chunk_overlap = chunk_overlap.min(chunk_size / 2);
+
+    let mut chunks = Vec::new();
+    let mut current: Vec<(usize, &str)> = Vec::new();
+    let mut current_len = 0;
+
+    for (index, line) in text.split_inclusive('\n').enumerate() {
+        let line_number = index + 1;
+
+        if line.chars().count() > chunk_size {
+            flush(&mut chunks, &mut current, &mut current_len, 0);
+            for piece in split_long_line(line, chunk_size) {
+                chunks.push(Chunk {
+                    text: piece,
+                    start_line: line_number,
+                    end_line: line_number,
+                    symbol: None,
+                });
+            }
+            continue;
+        }
+
+        let line_len = line.chars().count();
+        if current_len + line_len > chunk_size {
+            flush(&mut chunks, &mut current, &mut current_len, chunk_overlap);
+        }
+        current.push((line_number, line));
+        current_len += line_len;
+    }
+    flush(&mut chunks, &mut current, &mut current_len, 0);
+
+    chunks.retain(|chunk| !chunk.text.trim().is_empty());
+    chunks
+}
+
+fn flush(
+    chunks: &mut Vec<Chunk>,
+    current: &mut Vec<(usize, &str)>,
+    current_len: &mut usize,
+    overlap: usize,
+) {
+    if current.is_empty() {
+        return;
+    }
+
+    chunks.push(Chunk {
+        text: current.iter().map(|(_, line)| *line).collect(),
+        start_line: current[0].0,
+        end_line: current[current.len() - 1].0,
+        symbol: None,
+    });
+
+    let mut kept = 0;
+    let mut kept_len = 0;
+    for (_, line) in current.iter().skip(1).rev() {
+        let line_len = line.chars().count();
+        if kept_len + line_len > overlap {
+            break;
+        }
+        kept += 1;
+        kept_len += line_len;
+    }
+
+    current.drain(..current.len() - kept);
+    *current_len = kept_len;
+}
+
+fn split_long_line(line: &str, chunk_size: usize) -> Vec<String> {
+    let chars: Vec<char> = line.chars().collect();
+    chars
+        .chunks(chunk_size)
+        .map(|piece| piece.iter().collect())
+        .collect()
+}
+
+struct Item {
+    start: usize,
+    end: usize,
+    body: Option<Range<usize>>,
+    symbol: Symbol,
+    qualifier: String,
+}
+
+pub fn chunk_code(text: &str, language: Language, chunk_size: usize, chunk_overlap: usize) -> Vec<Chunk> {
+    let lines: Vec<&str> = text.split_inclusive('\n').collect();
+    let mut chunks = Vec::new();
+    let mut chunker = CodeChunker {
+        lines: &lines,
+        language,
+        chunk_size: chunk_size.max(1),
+        chunk_overlap,
+        chunks: &mut chunks,
+    };
+    chunker.emit_items(0..lines.len(), None);
+
+    chunks.retain(|chunk| !chunk.text.trim().is_empty());
+    chunks
+}
+
+struct CodeChunker<'a> {
+    lines: &'a [&'a str],
+    language: Language,
+    chunk_size: usize,
+    chunk_overlap: usize,
+    chunks: &'a mut Vec<Chunk>,
+}
+
+impl CodeChunker<'_> {
+    fn emit_items(&mut self, range: Range<usize>, parent: Option<(&Symbol, &str)>) {
+        let items = self.parse_items(range.clone(), parent.map(|(_, qualifier)| qualifier));
+        let parent_symbol = parent.map(|(symbol, _)| symbol);
+
+        let mut cursor = range.start;
+        for item in items {
+            self.emit_lines(cursor..item.start, parent_symbol);
+
+            let size: usize = self.lines[item.start..=item.end]
+                .iter()
+                .map(|line| line.chars().count())
+                .sum();
+            match item.body.clone() {
+                Some(body) if size > self.chunk_size && is_container(item.symbol.kind) => {
+                    self.emit_lines(item.start..body.start, Some(&item.symbol));
+                    self.emit_items(body.clone(), Some((&item.symbol, &item.qualifier)));
+                    self.emit_lines(body.end..item.end + 1, Some(&item.symbol));
+                }
+                _ => self.emit_lines(item.start..item.end + 1, Some(&item.symbol)),
+            }
+            cursor = item.end + 1;
+        }
+        self.emit_lines(cursor..range.end, parent_symbol);
+    }
+
+    fn emit_lines(&mut self, range: Range<usize>, symbol: Option<&Symbol>) {
+        if range.is_empty() {
+            return;
+        }
+        let text: String = self.lines[range.clone()].concat();
+        for chunk in chunk_text(&text, self.chunk_size, self.chunk_overlap) {
+            self.chunks.push(Chunk {
+                start_line: range.start + chunk.start_line,
+                end_line: range.start + chunk.end_line,
+                symbol: symbol.cloned(),
+                ..chunk
+            });
+        }
+    }
+
+    fn parse_items(&self, range: Range<usize>, qualifier: Option<&str>) -> Vec<Item> {
+        let mut items = Vec::new();
+        let mut leading: Option<usize> = None;
+        let mut index = range.start;
+
+        while index < range.end {
+            let line = self.lines[index].trim();
+            if is_attribute_or_doc(line, self.language) {
+                leading.get_or_insert(index);
+                index += 1;
+                continue;
+            }
+
+            let declaration = match self.language {
+                Language::Rust => rust_declaration(line),
+                _ if qualifier.is_some() => typescript_member(line),
+                _ => typescript_declaration(line),
+            };
+            let Some((mut symbol, own_qualifier)) = declaration else {
+                leading = None;
+                index += 1;
+                continue;
+            };
+
+            let statement = self.language.is_ecmascript() && is_binding(line);
+            let (end, body) = self.find_end(index, range.end, statement);
+            if let Some(qualifier) = qualifier {
+                let separator = match self.language {
+                    Language::Rust => "::",
+                    _ => ".",
+                };
+                symbol.name = format!("{}{}{}", qualifier, separator, symbol.name);
+            }
+            items.push(Item {
+                start: leading.take().unwrap_or(index),
+                end,
+                body,
+                qualifier: own_qualifier.unwrap_or_else(|| symbol.name.clone()),
+                symbol,
+            });
+            index = end + 1;
+        }
+        items
+    }
+
+    /// Finds the last line of the item starting at `start` and the lines of
+    /// its braced body. A `statement` (a JavaScript or TypeScript binding)
+    /// without a body may end at a line break, as automatic semicolon
+    /// insertion allows.
+    fn find_end(&self, start: usize, limit: usize, statement: bool) -> (usize, Option<Range<usize>>) {
+        let mut depth = 0i32;
+        let mut body_start = None;
+        let mut counter = BraceCounter::new(self.language);
+
+        for index in start..limit {
+            let line = self.lines[index];
+            let (delta, opened) = counter.count(line);
+            depth += delta;
+            if opened && body_start.is_none() {
+                body_start = Some(index + 1);
+            }
+
+            match body_start {
+                Some(body_start) if depth <= 0 => {
+                    let body = (body_start <= index).then_some(body_start..index);
+                    return (index, body);
+                }
+                None if depth <= 0 => {
+                    let line = line.trim_end();
+                    let next = self.lines.get(index + 1).map(|next| next.trim()).filter(|next| !next.is_empty());
+                    let ended = match next {
+                        None => true,
+                        Some(_) if line.ends_with(';') => true,
+                        Some(next) => statement && !statement_continues(line, next),
+                    };
+                    if ended {
+                        return (index, None);
+                    }
+                }
+                _ => {}
+            }
+        }
+        (limit - 1, None)
+    }
+}
+
+fn is_container(kind: &str) -> bool {
+    matches!(kind, "impl" | "trait" | "mod" | "class" | "module")
+}
+
+fn is_attribute_or_doc(line: &str, language: Language) -> bool {
+    match language {
+        Language::Rust => line.starts_with("#[") || line.starts_with("///"),
+        Language::TypeScript | Language::JavaScript => {
+            line.starts_with('@') || line.starts_with("/**") || line.starts_with("* ") || line == "*" || line.starts_with("*/")
+        }
+    }
+}
+
+fn strip_modifiers<'a>(mut line: &'a str, modifiers: &[&str]) -> &'a str {
+    loop {
+        let before = line;
+        if let Some(rest) = line.strip_prefix("pub(")
+            && let Some(close) = rest.find(')')
+        {
+            line = rest[close + 1..].trim_start();
+        }
+        for modifier in modifiers {
+            if let Some(rest) = line.strip_prefix(modifier)
+                && rest.starts_with(char::is_whitespace)
+            {
+                line = rest.trim_start();
+            }
+        }
+        if line == before {
+            return line;
+        }
+    }
+}
+
+fn identifier(text: &str) -> Option<String> {
+    let name: String = text
+        .trim_start()
+        .chars()
+        .take_while(|c| c.is_alphanumeric() || *c == '_' || *c == '$')
+        .collect();
+    (!name.is_empty()).then_some(name)
+}
+
+fn rust_declaration(line: &str) -> Option<(Symbol, Option<String>)> {
+    let rest = strip_modifiers(line, &["pub", "async", "unsafe", "default", "extern \"C\""]);
+    let rest = match rest.strip_prefix("const ") {
+        Some(after) if after.starts_with("fn ") || after.starts_with("unsafe ") => {
+            strip_modifiers(after, &["unsafe"])
+        }
+        _ => rest,
+    };
+
+    if let Some(after) = rest.strip_prefix("impl") {
+        if !after.starts_with(['<', ' ']) {
+            return None;
+        }
+        let header = skip_generics(after.trim_start());
+        let header = header
+            .split(" where")
+            .next()
+            .unwrap_or(header)
+            .trim_end_matches(['{', ' ', '\n'])
+            .trim();
+        let target = header.rsplit(" for ").next().unwrap_or(header);
+        let target = target.split('<').next().unwrap_or(target).trim().to_string();
+        let symbol = Symbol {
+            name: header.to_string(),
+            kind: "impl",
+        };
+        return Some((symbol, Some(target)));
+    }
+
+    if let Some(after) = rest.strip_prefix("macro_rules!") {
+        return Some((Symbol { name: identifier(after)?, kind: "macro" }, None));
+    }
+
+    let (keyword, after) = rest.split_once(char::is_whitespace)?;
+    let kind = match keyword {
+        "fn" => "fn",
+        "struct" => "struct",
+        "enum" => "enum",
+        "union" => "union",
+        "trait" => "trait",
+        "mod" => "mod",
+        "type" => "type",
+        "const" => "const",
+        "static" => "static",
+        _ => return None,
+    };
+    let after = after.strip_prefix("mut ").unwrap_or(after);
+    Some((Symbol { name: identifier(after)?, kind }, None))
+}
+
+fn skip_generics(text: &str) -> &str {
+    if !text.starts_with('<') {
+        return text;
+    }
+    let mut depth = 0;
+    for (index, c) in text.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => {
+                depth -= 1;
+                if depth == 0 {
+                    return text[index + 1..].trim_start();
+                }
+            }
+            _ => {}
+        }
+    }
+    text
+}
+
+const TYPESCRIPT_MODIFIERS: &[&str] = &[
+    "export", "default", "declare", "async", "abstract", "public", "private", "protected",
+    "static", "readonly", "override",
+];
+
+fn typescript_declaration(line: &str) -> Option<(Symbol, Option<String>)> {
+    let rest = strip_modifiers(line, TYPESCRIPT_MODIFIERS);
+    let (keyword, after) = rest.split_once(|c: char| c.is_whitespace() || c == '(' || c == '*')?;
+    let kind = match keyword {
+        "function" => "function",
+        "class" => "class",
+        "interface" => "interface",
+        "enum" => "enum",
+        "namespace" | "module" => "module",
+        "type" => "type",
+        "const" | "let" | "var" => {
+            let value = after.split_once('=').map(|(_, value)| value.trim_start()).unwrap_or("");
+            if value.contains("=>") || value.starts_with("function") || value.starts_with("async") {
+                "function"
+            } else {
+                "variable"
+            }
+        }
+        _ => return None,
+    };
+    let name = identifier(after.trim_start_matches('*')).unwrap_or_else(|| "default".to_string());
+    Some((Symbol { name, kind }, None))
+}
+
+fn typescript_member(line: &str) -> Option<(Symbol, Option<String>)> {
+    let rest = strip_modifiers(line, TYPESCRIPT_MODIFIERS);
+    let rest = ["get ", "set "]
+        .iter()
+        .find_map(|accessor| rest.strip_prefix(accessor))
+        .unwrap_or(rest)
+        .trim_start_matches('*');
+    let name = identifier(rest)?;
+    let after = rest[name.len()..].trim_start();
+    let after = after.strip_prefix('?').unwrap_or(after);
+    if !after.starts_with(['(', '<']) {
+        return None;
+    }
+    Some((Symbol { name, kind: "method" }, None))
+}
+
+/// Whether a JavaScript or TypeScript declaration starts with a binding
+/// keyword (`const`, `let`, `var` or `type`) rather than a block.
+fn is_binding(line: &str) -> bool {
+    let rest = strip_modifiers(line, TYPESCRIPT_MODIFIERS);
+    ["const ", "let ", "var ", "type "].iter().any(|keyword| rest.starts_with(keyword))
+}
+
+/// Whether a statement without a `;` goes on past `line`: the line ends in an
+/// operator or the next line starts with one.
+fn statement_continues(line: &str, next: &str) -> bool {
+    const TRAILING: &[&str] = &["=", ",", "=>", "+", "-", "*", "/", "&&", "||", "??", "?", ":", ".", "|", "&"];
+    const LEADING: &[&str] = &[".", "?", ":", "+", "-", "&&", "||", "??", "=>", "|", "&"];
+    TRAILING.iter().any(|token| line.ends_with(token)) || LEADING.iter().any(|token| next.starts_with(token))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scan {
+    Code,
+    /// Inside a string or character literal closed by this quote
+    Quoted(char),
+    /// Inside a Rust raw string closed by `"` and this many `#`
+    Raw(usize),
+    /// Inside a block comment, nested this deep
+    Comment(usize),
+}
+
+/// Counts the braces of an item line by line, skipping those in strings and
+/// comments, including ones that span lines.
+struct BraceCounter {
+    language: Language,
+    scan: Scan,
+}
+
+impl BraceCounter {
+    fn new(language: Language) -> Self {
+        Self {
+            language,
+            scan: Scan::Code,
+        }
+    }
+
+    /// Returns the change in nesting over `line`, counting braces, brackets
+    /// and parentheses, and whether the line opened a brace.
+    fn count(&mut self, line: &str) -> (i32, bool) {
+        let chars: Vec<char> = line.chars().collect();
+        let mut delta = 0;
+        let mut opened = false;
+        let mut index = 0;
+
+        while index < chars.len() {
+            let c = chars[index];
+            let next = chars.get(index + 1).copied();
+            match self.scan {
+                Scan::Comment(depth) => {
+                    if c == '*' && next == Some('/') {
+                        self.scan = if depth > 1 { Scan::Comment(depth - 1) } else { Scan::Code };
+                        index += 1;
+                    } else if c == '/' && next == Some('*') && self.language == Language::Rust {
+                        self.scan = Scan::Comment(depth + 1);
+                        index += 1;
+                    }
+                }
+                Scan::Quoted(quote) => {
+                    if c == '\\' {
+                        index += 1;
+                    } else if c == quote {
+                        self.scan = Scan::Code;
+                    }
+                }
+                Scan::Raw(hashes) => {
+                    if c == '"' && chars[index + 1..].iter().take_while(|c| **c == '#').count() >= hashes {
+                        self.scan = Scan::Code;
+                        index += hashes;
+                    }
+                }
+                Scan::Code => match c {
+                    '/' if next == Some('/') => break,
+                    '/' if next == Some('*') => {
+                        self.scan = Scan::Comment(1);
+                        index += 1;
+                    }
+                    '"' => self.scan = Scan::Quoted('"'),
+                    '`' | '\'' if self.language.is_ecmascript() => self.scan = Scan::Quoted(c),
+                    '\'' => match (next, chars.get(index + 2)) {
+                        (Some('\\'), _) => self.scan = Scan::Quoted('\''),
+                        (Some(_), Some('\'')) => index += 2,
+                        _ => {}
+                    },
+                    'r' if self.language == Language::Rust && starts_raw_string(&chars, index) => {
+                        let hashes = chars[index + 1..].iter().take_while(|c| **c == '#').count();
+                        self.scan = Scan::Raw(hashes);
+                        index += 1 + hashes;
+                    }
+                    '{' => {
+                        delta += 1;
+                        opened = true;
+                    }
+                    '(' | '[' => delta += 1,
+                    '}' | ')' | ']' => delta -= 1,
+                    _ => {}
+                },
+            }
+            index += 1;
+        }
+
+        // Only template literals and Rust strings continue on the next line.
+        if let Scan::Quoted(quote) = self.scan
+            && self.language.is_ecmascript()
+            && quote != '`'
+        {
+            self.scan = Scan::Code;
+        }
+        (delta, opened)
+    }
+}
+
+/// Whether the `r` at `index` starts a raw string literal, `r"`, `r#"` or
+/// `br#"`, rather than ending an identifier.
+fn starts_raw_string(chars: &[char], index: usize) -> bool {
+    let start = if index > 0 && chars[index - 1] == 'b' { index - 1 } else { index };
+    let identifier_before = start > 0 && (chars[start - 1].is_alphanumeric() || chars[start - 1] == '_');
+    let hashes = chars[index + 1..].iter().take_while(|c| **c == '#').count();
+    !identifier_before && chars.get(index + 1 + hashes) == Some(&'"')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Chunks `text` in one piece per item and returns each symbol's name
+    /// with the text of its chunk.
+    fn items(text: &str, language: Language) -> Vec<(String, String)> {
+        chunk_code(text, language, 10_000, 0)
+            .into_iter()
+            .filter_map(|chunk| Some((chunk.symbol?.name, chunk.text)))
+            .collect()
+    }
+
+    #[test]
+    fn javascript_is_not_typescript() {
+        assert_eq!(Language::from_path(Path::new("a.js")), Some(Language::JavaScript));
+        assert_eq!(Language::from_path(Path::new("a.cjs")), Some(Language::JavaScript));
+        assert_eq!(Language::from_path(Path::new("a.tsx")), Some(Language::TypeScript));
+        assert_eq!(Language::JavaScript.as_str(), "javascript");
+
+        let items = items("function a() {\n  return 1;\n}\nfunction b() {}\n", Language::JavaScript);
+        let names: Vec<&str> = items.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["a", "b"]);
+    }
+
+    #[test]
+    fn braces_in_block_comments_are_ignored() {
+        let text = "fn a() {\n    /* }\n    } */\n    1\n}\nfn b() {}\n";
+        let items = items(text, Language::Rust);
+        assert_eq!(items[0], ("a".to_string(), "fn a() {\n    /* }\n    } */\n    1\n}\n".to_string()));
+        assert_eq!(items[1].0, "b");
+    }
+
+    #[test]
+    fn braces_in_raw_strings_are_ignored() {
+        let text = "fn a() {\n    let s = r#\"}\n\"}\"#;\n}\nfn b() {}\n";
+        let items = items(text, Language::Rust);
+        assert_eq!(items[0], ("a".to_string(), "fn a() {\n    let s = r#\"}\n\"}\"#;\n}\n".to_string()));
+        assert_eq!(items[1].0, "b");
+    }
+
+    #[test]
+    fn braces_in_template_literals_are_ignored() {
+        let text = "function a() {\n  return `}\n  }`;\n}\nfunction b() {}\n";
+        let items = items(text, Language::TypeScript);
+        assert_eq!(items[0], ("a".to_string(), "function a() {\n  return `}\n  }`;\n}\n".to_string()));
+        assert_eq!(items[1].0, "b");
+    }
+
+    #[test]
+    fn split_containers_keep_every_line() {
+        let text = "impl Store {\n    fn get(&self) -> u32 {\n        self.value\n    }\n    fn set(&mut self, value: u32) {\n        self.value = value;\n    }\n}\nfn after() {}\n";
+        let chunks = chunk_code(text, Language::Rust, 80, 0);
+
+        let joined: String = chunks.iter().map(|chunk| chunk.text.as_str()).collect();
+        assert_eq!(joined, text);
+        let lines: Vec<(usize, usize)> = chunks.iter().map(|chunk| (chunk.start_line, chunk.end_line)).collect();
+        assert_eq!(lines, [(1, 1), (2, 4), (5, 7), (8, 8), (9, 9)]);
+        let closing = &chunks[3];
+        assert_eq!((closing.text.as_str(), closing.symbol.as_ref().unwrap().name.as_str()), ("}\n", "Store"));
+    }
+
+    #[test]
+    fn const_without_semicolon_ends_at_line_break() {
+        let text = "const a = 1\nconst b = 2\nexport const c =\n  a +\n  b\nconst d = [\n  1,\n]\n";
+        let items = items(text, Language::TypeScript);
+        let expected = [
+            ("a", "const a = 1\n"),
+            ("b", "const b = 2\n"),
+            ("c", "export const c =\n  a +\n  b\n"),
+            ("d", "const d = [\n  1,\n]\n"),
+        ];
+        let items: Vec<(&str, &str)> = items.iter().map(|(name, text)| (name.as_str(), text.as_str())).collect();
+        assert_eq!(items, expected);
+    }
+}
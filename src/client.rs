@@ -1,4 +1,6 @@
-use anyhow::Result;
+use crate::cancel;
+use crate::secret::Secret;
+use anyhow::{anyhow, Result};
 use serde_json::json;
 use std::sync::{Arc, Mutex, MutexGuard};
 
@@ -8,7 +10,7 @@ pub struct ChromaClient {
     host: String,
     port: u16,
     username: Option<String>,
-    password: Option<String>,
+    password: Option<Secret>,
 }
 
 impl ChromaClient {
@@ -22,29 +24,55 @@ impl ChromaClient {
             host: host.to_string(),
             port,
             username: username.map(|s| s.to_string()),
-            password: password.map(|s| s.to_string()),
+            password: password.map(Secret::new),
         }
     }
 
     pub fn list_collections(&self, _limit: Option<usize>, _offset: Option<usize>) -> Result<Vec<String>> {
-        Ok(vec!["test_collection".to_string()])
+        cancel::check()?;
+        let names = vec!["test_collection".to_string()];
+        #[cfg(test)]
+        let names = names
+            .into_iter()
+            .chain(memory::names())
+            .skip(_offset.unwrap_or(0))
+            .take(_limit.unwrap_or(usize::MAX))
+            .collect();
+        Ok(names)
     }
 
-    pub fn create_collection(&self, name: &str, _metadata: Option<serde_json::Value>) -> Result<String> {
+    pub fn create_collection(
+        &self,
+        name: &str,
+        _metadata: Option<serde_json::Value>,
+        _configuration: Option<serde_json::Value>,
+    ) -> Result<String> {
+        cancel::check()?;
+        #[cfg(test)]
+        memory::create(name, _metadata, _configuration);
         Ok(format!("Created collection: {}", name))
     }
 
     pub fn get_collection(&self, name: &str) -> Result<Collection> {
+        cancel::check()?;
         Ok(Collection {
             name: name.to_string(),
         })
     }
 
     pub fn delete_collection(&self, _name: &str) -> Result<()> {
+        cancel::check()?;
+        #[cfg(test)]
+        memory::remove(_name);
         Ok(())
     }
 }
 
+/// The error the backend returns for a collection that does not exist.
+pub fn collection_not_found(name: &str) -> anyhow::Error {
+    anyhow!("Collection [{}] does not exist", name)
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct Collection {
@@ -58,6 +86,28 @@ impl Collection {
         _metadatas: Option<Vec<serde_json::Value>>,
         _ids: Vec<String>,
     ) -> Result<()> {
+        cancel::check()?;
+        #[cfg(test)]
+        if memory::contains(&self.name) {
+            memory::with(&self.name, |stored| stored.add(_documents, _metadatas, _ids));
+        }
+        Ok(())
+    }
+
+    /// Records without a document or metadata are sent with `null` in its
+    /// place.
+    pub fn upsert(
+        &self,
+        _embeddings: Option<Vec<Vec<f32>>>,
+        _documents: Vec<Option<String>>,
+        _metadatas: Option<Vec<Option<serde_json::Value>>>,
+        _ids: Vec<String>,
+    ) -> Result<()> {
+        cancel::check()?;
+        #[cfg(test)]
+        if memory::contains(&self.name) {
+            memory::with(&self.name, |stored| stored.upsert(_embeddings, _documents, _metadatas, _ids));
+        }
         Ok(())
     }
 
@@ -69,6 +119,7 @@ impl Collection {
         _where_document: Option<serde_json::Value>,
         _include: Vec<String>,
     ) -> Result<serde_json::Value> {
+        cancel::check()?;
         Ok(json!({
             "ids": [["doc1", "doc2"]],
             "documents": [["document1", "document2"]],
@@ -86,6 +137,13 @@ impl Collection {
         _limit: Option<usize>,
         _offset: Option<usize>,
     ) -> Result<serde_json::Value> {
+        cancel::check()?;
+        #[cfg(test)]
+        if memory::contains(&self.name) {
+            return Ok(memory::with(&self.name, |stored| {
+                stored.get(_ids, _where_filter, _where_document, _include, _limit, _offset)
+            }));
+        }
         Ok(json!({
             "ids": ["doc1", "doc2"],
             "documents": ["document1", "document2"],
@@ -100,18 +158,57 @@ impl Collection {
         _metadatas: Option<Vec<serde_json::Value>>,
         _documents: Option<Vec<String>>,
     ) -> Result<()> {
+        cancel::check()?;
+        #[cfg(test)]
+        if memory::contains(&self.name) {
+            memory::with(&self.name, |stored| stored.update(_ids, _embeddings, _metadatas, _documents));
+        }
         Ok(())
     }
 
     pub fn delete(&self, _ids: Vec<String>) -> Result<()> {
+        cancel::check()?;
+        #[cfg(test)]
+        if memory::contains(&self.name) {
+            memory::with(&self.name, |stored| stored.delete(_ids));
+        }
         Ok(())
     }
 
+    pub fn metadata(&self) -> Result<Option<serde_json::Value>> {
+        #[cfg(test)]
+        if memory::contains(&self.name) {
+            return Ok(memory::with(&self.name, |stored| stored.metadata()));
+        }
+        Ok(None)
+    }
+
+    pub fn configuration(&self) -> Result<serde_json::Value> {
+        #[cfg(test)]
+        if memory::contains(&self.name) {
+            return Ok(memory::with(&self.name, |stored| stored.configuration()));
+        }
+        Ok(json!({
+            "hnsw": {"space": "l2"}
+        }))
+    }
+
     pub fn count(&self) -> Result<usize> {
+        cancel::check()?;
+        #[cfg(test)]
+        if memory::contains(&self.name) {
+            return Ok(memory::with(&self.name, |stored| stored.count()));
+        }
         Ok(3)
     }
 
     pub fn peek(&self, _limit: usize) -> Result<serde_json::Value> {
+        cancel::check()?;
+        #[cfg(test)]
+        if memory::contains(&self.name) {
+            let include = vec!["documents".to_string(), "metadatas".to_string()];
+            return Ok(memory::with(&self.name, |stored| stored.get(None, None, None, include, Some(_limit), None)));
+        }
         Ok(json!({
             "ids": ["doc1", "doc2"],
             "documents": ["document1", "document2"],
@@ -123,7 +220,16 @@ impl Collection {
         &self,
         _name: Option<String>,
         _metadata: Option<serde_json::Value>,
+        _configuration: Option<serde_json::Value>,
     ) -> Result<()> {
+        cancel::check()?;
+        #[cfg(test)]
+        if memory::contains(&self.name) {
+            memory::with(&self.name, |stored| stored.modify(_metadata, _configuration));
+            if let Some(new_name) = _name {
+                memory::rename(&self.name, &new_name);
+            }
+        }
         Ok(())
     }
 }
@@ -152,6 +258,22 @@ pub fn initialize_client() -> Result<()> {
     Ok(())
 }
 
+pub fn client_for_profile(profile: &str) -> Result<ChromaClient> {
+    let prefix = format!("CHROMA_{}_", profile.to_uppercase().replace('-', "_"));
+    let var = |name: &str| std::env::var(format!("{}{}", prefix, name)).ok();
+
+    let host = var("HOST")
+        .ok_or_else(|| anyhow!("Backend profile '{}' is not configured: set {}HOST", profile, prefix))?;
+    let port = var("PORT").and_then(|port| port.parse().ok()).unwrap_or(8000);
+
+    Ok(ChromaClient::new(
+        &host,
+        port,
+        var("USERNAME").as_deref(),
+        var("PASSWORD").as_deref(),
+    ))
+}
+
 pub fn get_client() -> Arc<ChromaClient> {
     let client_guard: MutexGuard<Option<ChromaClient>> = CLIENT.lock().unwrap();
     
@@ -163,3 +285,242 @@ pub fn get_client() -> Arc<ChromaClient> {
     
     Arc::new(client_guard.as_ref().unwrap().clone())
 }
+
+/// Collections kept in memory for unit tests. A collection created while
+/// testing is stored here and served by [`Collection`]; any other name keeps
+/// the fixed sample records above. Tests share the process, so each uses
+/// collection names of its own.
+#[cfg(test)]
+pub mod memory {
+    use serde_json::{json, Map, Value};
+    use std::collections::BTreeMap;
+    use std::sync::Mutex;
+
+    #[derive(Debug, Clone, Default)]
+    struct StoredRecord {
+        document: Option<String>,
+        metadata: Option<Value>,
+        embedding: Option<Vec<f32>>,
+    }
+
+    #[derive(Debug, Default)]
+    pub struct StoredCollection {
+        metadata: Option<Value>,
+        configuration: Value,
+        records: BTreeMap<String, StoredRecord>,
+    }
+
+    static COLLECTIONS: Mutex<BTreeMap<String, StoredCollection>> = Mutex::new(BTreeMap::new());
+
+    pub fn contains(name: &str) -> bool {
+        COLLECTIONS.lock().unwrap().contains_key(name)
+    }
+
+    pub fn names() -> Vec<String> {
+        COLLECTIONS.lock().unwrap().keys().cloned().collect()
+    }
+
+    pub fn create(name: &str, metadata: Option<Value>, configuration: Option<Value>) {
+        let collection = StoredCollection {
+            metadata,
+            configuration: configuration.unwrap_or_else(|| json!({"hnsw": {"space": "l2"}})),
+            records: BTreeMap::new(),
+        };
+        COLLECTIONS.lock().unwrap().insert(name.to_string(), collection);
+    }
+
+    pub fn remove(name: &str) {
+        COLLECTIONS.lock().unwrap().remove(name);
+    }
+
+    pub fn rename(name: &str, new_name: &str) {
+        let mut collections = COLLECTIONS.lock().unwrap();
+        if let Some(collection) = collections.remove(name) {
+            collections.insert(new_name.to_string(), collection);
+        }
+    }
+
+    /// Runs `f` on the stored collection `name`, which must exist.
+    pub fn with<R>(name: &str, f: impl FnOnce(&mut StoredCollection) -> R) -> R {
+        f(COLLECTIONS.lock().unwrap().get_mut(name).expect("collection is stored in memory"))
+    }
+
+    impl StoredCollection {
+        pub fn metadata(&self) -> Option<Value> {
+            self.metadata.clone()
+        }
+
+        pub fn configuration(&self) -> Value {
+            self.configuration.clone()
+        }
+
+        pub fn count(&self) -> usize {
+            self.records.len()
+        }
+
+        pub fn modify(&mut self, metadata: Option<Value>, configuration: Option<Value>) {
+            if metadata.is_some() {
+                self.metadata = metadata;
+            }
+            if let (Some(Value::Object(changes)), Value::Object(current)) = (configuration, &mut self.configuration) {
+                for (key, value) in changes {
+                    match (current.get_mut(&key), value) {
+                        (Some(Value::Object(section)), Value::Object(value)) => section.extend(value),
+                        (_, value) => {
+                            current.insert(key, value);
+                        }
+                    }
+                }
+            }
+        }
+
+        /// Adds the records whose ids are not stored yet.
+        pub fn add(&mut self, documents: Vec<String>, metadatas: Option<Vec<Value>>, ids: Vec<String>) {
+            let mut metadatas = metadatas.map(Vec::into_iter);
+            for (id, document) in ids.into_iter().zip(documents) {
+                let metadata = metadatas.as_mut().and_then(Iterator::next);
+                self.records.entry(id).or_insert(StoredRecord {
+                    document: Some(document),
+                    metadata,
+                    embedding: None,
+                });
+            }
+        }
+
+        pub fn upsert(
+            &mut self,
+            embeddings: Option<Vec<Vec<f32>>>,
+            documents: Vec<Option<String>>,
+            metadatas: Option<Vec<Option<Value>>>,
+            ids: Vec<String>,
+        ) {
+            let mut embeddings = embeddings.map(Vec::into_iter);
+            let mut metadatas = metadatas.map(Vec::into_iter);
+            for (id, document) in ids.into_iter().zip(documents) {
+                let record = StoredRecord {
+                    document,
+                    metadata: metadatas.as_mut().and_then(Iterator::next).flatten(),
+                    embedding: embeddings.as_mut().and_then(Iterator::next),
+                };
+                self.records.insert(id, record);
+            }
+        }
+
+        /// Updates the stored records among `ids`. Metadata is merged, and
+        /// keys set to `null` are removed.
+        pub fn update(
+            &mut self,
+            ids: Vec<String>,
+            embeddings: Option<Vec<Vec<f32>>>,
+            metadatas: Option<Vec<Value>>,
+            documents: Option<Vec<String>>,
+        ) {
+            let mut embeddings = embeddings.map(Vec::into_iter);
+            let mut metadatas = metadatas.map(Vec::into_iter);
+            let mut documents = documents.map(Vec::into_iter);
+            for id in ids {
+                let embedding = embeddings.as_mut().and_then(Iterator::next);
+                let metadata = metadatas.as_mut().and_then(Iterator::next);
+                let document = documents.as_mut().and_then(Iterator::next);
+                let Some(record) = self.records.get_mut(&id) else {
+                    continue;
+                };
+                if embedding.is_some() {
+                    record.embedding = embedding;
+                }
+                if document.is_some() {
+                    record.document = document;
+                }
+                if let Some(Value::Object(patch)) = metadata {
+                    let mut merged = match record.metadata.take() {
+                        Some(Value::Object(fields)) => fields,
+                        _ => Map::new(),
+                    };
+                    for (key, value) in patch {
+                        match value {
+                            Value::Null => merged.remove(&key),
+                            value => merged.insert(key, value),
+                        };
+                    }
+                    record.metadata = (!merged.is_empty()).then_some(Value::Object(merged));
+                }
+            }
+        }
+
+        pub fn delete(&mut self, ids: Vec<String>) {
+            for id in ids {
+                self.records.remove(&id);
+            }
+        }
+
+        /// Records matching the filters, in id order, in the shape of the
+        /// backend's `get` response with only the `include`d fields.
+        pub fn get(
+            &self,
+            ids: Option<Vec<String>>,
+            where_filter: Option<Value>,
+            where_document: Option<Value>,
+            include: Vec<String>,
+            limit: Option<usize>,
+            offset: Option<usize>,
+        ) -> Value {
+            let records = self
+                .records
+                .iter()
+                .filter(|(id, _)| ids.as_ref().is_none_or(|ids| ids.contains(id)))
+                .filter(|(_, record)| where_filter.as_ref().is_none_or(|filter| matches_where(record.metadata.as_ref(), filter)))
+                .filter(|(_, record)| {
+                    where_document
+                        .as_ref()
+                        .is_none_or(|filter| matches_document(record.document.as_deref().unwrap_or_default(), filter))
+                })
+                .skip(offset.unwrap_or(0))
+                .take(limit.unwrap_or(usize::MAX));
+
+            let mut page = json!({"ids": [], "documents": [], "metadatas": [], "embeddings": []});
+            for (id, record) in records {
+                page["ids"].as_array_mut().unwrap().push(json!(id));
+                page["documents"].as_array_mut().unwrap().push(json!(record.document));
+                page["metadatas"].as_array_mut().unwrap().push(json!(record.metadata));
+                page["embeddings"].as_array_mut().unwrap().push(json!(record.embedding));
+            }
+            for field in ["documents", "metadatas", "embeddings"] {
+                if !include.iter().any(|name| name == field) {
+                    page.as_object_mut().unwrap().remove(field);
+                }
+            }
+            page
+        }
+    }
+
+    fn matches_where(metadata: Option<&Value>, filter: &Value) -> bool {
+        let Some(filter) = filter.as_object() else {
+            return false;
+        };
+        filter.iter().all(|(key, condition)| match key.as_str() {
+            "$and" => condition.as_array().is_some_and(|all| all.iter().all(|filter| matches_where(metadata, filter))),
+            "$or" => condition.as_array().is_some_and(|any| any.iter().any(|filter| matches_where(metadata, filter))),
+            _ => {
+                let value = metadata.and_then(|metadata| metadata.get(key));
+                match condition.as_object().and_then(|condition| condition.iter().next()) {
+                    Some((operator, operand)) => match operator.as_str() {
+                        "$eq" => value == Some(operand),
+                        "$ne" => value != Some(operand),
+                        "$in" => operand.as_array().is_some_and(|values| value.is_some_and(|value| values.contains(value))),
+                        "$nin" => operand.as_array().is_some_and(|values| value.is_none_or(|value| !values.contains(value))),
+                        other => panic!("unsupported where operator {}", other),
+                    },
+                    None => value == Some(condition),
+                }
+            }
+        })
+    }
+
+    fn matches_document(document: &str, filter: &Value) -> bool {
+        match (filter.get("$contains"), filter.get("$not_contains")) {
+            (Some(Value::String(text)), _) => document.contains(text.as_str()),
+            (_, Some(Value::String(text))) => !document.contains(text.as_str()),
+            _ => panic!("unsupported where_document filter {}", filter),
+        }
+    }
+}
@@ -1,5 +1,19 @@
-use clap::Parser;
+use crate::audit::{self, AuditOptions, DocumentLogging};
+use crate::budget::OutputBudget;
+use crate::confirm::{self, ConfirmOptions, ConfirmRule};
+use crate::dump::ConflictPolicy;
+use crate::policy::{CollectionAccess, CollectionPolicy, CollectionRule};
+use crate::registry::{ToolGroup, ToolPolicy};
+use crate::secret::Secret;
+#[cfg(unix)]
+use crate::transport::SocketOptions;
+use crate::transport::{HttpOptions, TransportKind};
+use crate::trash::{self, TrashOptions};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::collections::HashSet;
+use std::net::{IpAddr, SocketAddr};
 use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Debug, Parser, Clone)]
 #[command(author, version, about, long_about = None)]
@@ -17,8 +31,8 @@ pub struct Config {
     #[arg(long, env = "CHROMA_PORT")]
     pub port: Option<u16>,
 
-    #[arg(long, env = "CHROMA_CUSTOM_AUTH_CREDENTIALS")]
-    pub custom_auth_credentials: Option<String>,
+    #[arg(long, env = "CHROMA_CUSTOM_AUTH_CREDENTIALS", hide_env_values = true)]
+    pub custom_auth_credentials: Option<Secret>,
 
     #[arg(long, env = "CHROMA_TENANT")]
     pub tenant: Option<String>,
@@ -26,14 +40,183 @@ pub struct Config {
     #[arg(long, env = "CHROMA_DATABASE")]
     pub database: Option<String>,
 
-    #[arg(long, env = "CHROMA_API_KEY")]
-    pub api_key: Option<String>,
+    #[arg(long, env = "CHROMA_API_KEY", hide_env_values = true)]
+    pub api_key: Option<Secret>,
 
     #[arg(long, env = "CHROMA_SSL", default_value = "true")]
     pub ssl: bool,
 
     #[arg(long, env = "CHROMA_DOTENV_PATH", default_value = ".chroma_env")]
     pub dotenv_path: PathBuf,
+
+    /// Tool groups to expose (defaults to all groups)
+    #[arg(long, env = "CHROMA_ENABLED_TOOL_GROUPS", value_enum, value_delimiter = ',')]
+    pub enable_tool_groups: Vec<ToolGroup>,
+
+    /// Tool groups to hide, applied after --enable-tool-groups
+    #[arg(long, env = "CHROMA_DISABLED_TOOL_GROUPS", value_enum, value_delimiter = ',')]
+    pub disable_tool_groups: Vec<ToolGroup>,
+
+    /// Hide and reject every tool that can modify collections
+    #[arg(long, env = "CHROMA_READ_ONLY")]
+    pub read_only: bool,
+
+    /// Tools to expose by name (defaults to all tools)
+    #[arg(long, env = "CHROMA_ALLOW_TOOLS", value_delimiter = ',')]
+    pub allow_tools: Vec<String>,
+
+    /// Tools to hide by name, applied after --allow-tools
+    #[arg(long, env = "CHROMA_DENY_TOOLS", value_delimiter = ',')]
+    pub deny_tools: Vec<String>,
+
+    /// Access to collections whose name matches a glob, as PATTERN=none|read|write; the first match wins
+    #[arg(long = "collection-policy", env = "CHROMA_COLLECTION_POLICY", value_delimiter = ',')]
+    pub collection_policies: Vec<CollectionRule>,
+
+    /// Access to collections no --collection-policy pattern matches
+    #[arg(long, env = "CHROMA_DEFAULT_COLLECTION_ACCESS", value_enum, default_value = "write")]
+    pub default_collection_access: CollectionAccess,
+
+    /// Directory deleted collections and records are kept in until they expire (default: a per-user data directory)
+    #[arg(long, env = "CHROMA_TRASH_DIR")]
+    pub trash_dir: Option<PathBuf>,
+
+    /// Hours a trash entry is kept before it is purged
+    #[arg(long, env = "CHROMA_TRASH_RETENTION_HOURS", default_value_t = trash::DEFAULT_RETENTION_HOURS)]
+    pub trash_retention_hours: u64,
+
+    /// Let delete tools bypass the trash when called with `permanent`
+    #[arg(long, env = "CHROMA_ALLOW_HARD_DELETE")]
+    pub allow_hard_delete: bool,
+
+    /// Tools that return a preview and confirmation token before running, as TOOL, TOOL=N (only above N items) or TOOL=off
+    #[arg(long, env = "CHROMA_CONFIRM_TOOLS", value_delimiter = ',', default_value = confirm::DEFAULT_CONFIRM_TOOLS)]
+    pub confirm_tools: Vec<ConfirmRule>,
+
+    /// Seconds a confirmation token stays valid
+    #[arg(long, env = "CHROMA_CONFIRM_TTL_SECS", default_value_t = confirm::DEFAULT_TTL_SECS)]
+    pub confirm_ttl_secs: u64,
+
+    /// JSONL file every tool call is appended to; no audit log is written when unset
+    #[arg(long, env = "CHROMA_AUDIT_LOG")]
+    pub audit_log: Option<PathBuf>,
+
+    /// How documents, metadata, document filters and query texts in tool arguments are written to the audit log
+    #[arg(long, env = "CHROMA_AUDIT_DOCUMENTS", value_enum, default_value = "hash")]
+    pub audit_documents: DocumentLogging,
+
+    /// Size in bytes at which the audit log is rotated
+    #[arg(long, env = "CHROMA_AUDIT_MAX_BYTES", default_value_t = audit::DEFAULT_MAX_BYTES)]
+    pub audit_max_bytes: u64,
+
+    /// Number of rotated audit log files kept
+    #[arg(long, env = "CHROMA_AUDIT_KEEP_FILES", default_value_t = audit::DEFAULT_KEEP_FILES)]
+    pub audit_keep_files: usize,
+
+    /// Directories chroma_ingest_path may read from; the tool is disabled when none is given
+    #[arg(long = "ingest-root", env = "CHROMA_INGEST_ROOTS", value_delimiter = ',')]
+    pub ingest_roots: Vec<PathBuf>,
+
+    /// Directory chroma_export_collection may write dumps to; the tool is disabled when none is given
+    #[arg(long, env = "CHROMA_EXPORT_DIR")]
+    pub export_dir: Option<PathBuf>,
+
+    /// Directory chroma_import_collection may read dumps from; the tool is disabled when none is given
+    #[arg(long, env = "CHROMA_IMPORT_DIR")]
+    pub import_dir: Option<PathBuf>,
+
+    /// Maximum size of a tool response in characters
+    #[arg(long, env = "CHROMA_MAX_OUTPUT_CHARS")]
+    pub max_output_chars: Option<usize>,
+
+    /// Maximum size of a tool response in approximate tokens
+    #[arg(long, env = "CHROMA_MAX_OUTPUT_TOKENS")]
+    pub max_output_tokens: Option<usize>,
+
+    /// Maximum characters returned per document before it is truncated
+    #[arg(long, env = "CHROMA_MAX_DOCUMENT_CHARS")]
+    pub max_document_chars: Option<usize>,
+
+    /// Transport the MCP server is served over
+    #[arg(long, env = "CHROMA_MCP_TRANSPORT", value_enum, default_value = "stdio")]
+    pub transport: TransportKind,
+
+    /// Address the sse and http transports listen on
+    #[arg(long, env = "CHROMA_MCP_BIND", default_value = "127.0.0.1")]
+    pub bind: IpAddr,
+
+    /// Port the sse and http transports listen on
+    #[arg(long, env = "CHROMA_MCP_PORT", default_value = "8080")]
+    pub http_port: u16,
+
+    /// Bearer token clients of the sse and http transports must present
+    #[arg(long, env = "CHROMA_MCP_AUTH_TOKEN", hide_env_values = true)]
+    pub auth_token: Option<Secret>,
+
+    /// Origins allowed to call the sse and http transports from a browser ("*" for any)
+    #[arg(long = "cors-origin", env = "CHROMA_MCP_CORS_ORIGINS", value_delimiter = ',')]
+    pub cors_origins: Vec<String>,
+
+    /// Unix socket path to serve on; implies --transport unix
+    #[cfg(unix)]
+    #[arg(long, env = "CHROMA_MCP_SOCKET")]
+    pub socket: Option<PathBuf>,
+
+    /// Octal permission bits of the socket file
+    #[cfg(unix)]
+    #[arg(long, env = "CHROMA_MCP_SOCKET_MODE", default_value = "600", value_parser = parse_mode)]
+    pub socket_mode: u32,
+
+    /// Text content returned next to structured tool results
+    #[arg(long, env = "CHROMA_TOOL_OUTPUT", value_enum, default_value = "json")]
+    pub tool_output: ToolOutputFormat,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand, Clone)]
+pub enum Command {
+    /// Ingest local files into a collection and exit
+    Ingest(IngestArgs),
+    /// Export a collection to a JSONL dump and exit
+    Export(ExportArgs),
+    /// Import a JSONL dump into a collection and exit
+    Import(ImportArgs),
+}
+
+#[derive(Debug, Args, Clone)]
+pub struct IngestArgs {
+    /// File or directory to ingest
+    pub path: PathBuf,
+
+    #[arg(long)]
+    pub collection_name: String,
+
+    /// Glob pattern selecting files to ingest (repeatable)
+    #[arg(long = "glob")]
+    pub globs: Vec<String>,
+
+    #[arg(long)]
+    pub max_file_size: Option<u64>,
+
+    #[arg(long)]
+    pub chunk_size: Option<usize>,
+
+    #[arg(long)]
+    pub chunk_overlap: Option<usize>,
+
+    /// Chunk source code as plain text instead of on syntactic boundaries
+    #[arg(long)]
+    pub no_code_chunking: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ToolOutputFormat {
+    /// Pretty-printed JSON of the structured result
+    Json,
+    /// Compact human-readable summary
+    Summary,
 }
 
 #[derive(Debug, Clone, clap::ValueEnum)]
@@ -45,6 +228,82 @@ pub enum ClientType {
 }
 
 impl Config {
+    pub fn transport(&self) -> TransportKind {
+        #[cfg(unix)]
+        if self.transport == TransportKind::Stdio && self.socket.is_some() {
+            return TransportKind::Unix;
+        }
+        self.transport
+    }
+
+    #[cfg(unix)]
+    pub fn socket_options(&self) -> Option<SocketOptions> {
+        self.socket.clone().map(|path| SocketOptions {
+            path,
+            mode: self.socket_mode,
+        })
+    }
+
+    pub fn http_options(&self) -> HttpOptions {
+        HttpOptions {
+            bind: SocketAddr::new(self.bind, self.http_port),
+            auth_token: self.auth_token.clone(),
+            cors_origins: self.cors_origins.clone(),
+        }
+    }
+
+    pub fn output_budget(&self) -> OutputBudget {
+        OutputBudget::new(self.max_output_chars, self.max_output_tokens, self.max_document_chars)
+    }
+
+    pub fn enabled_tool_groups(&self) -> HashSet<ToolGroup> {
+        let mut groups: HashSet<ToolGroup> = if self.enable_tool_groups.is_empty() {
+            ToolGroup::value_variants().iter().copied().collect()
+        } else {
+            self.enable_tool_groups.iter().copied().collect()
+        };
+        for group in &self.disable_tool_groups {
+            groups.remove(group);
+        }
+        groups
+    }
+
+    pub fn tool_policy(&self) -> ToolPolicy {
+        ToolPolicy {
+            read_only: self.read_only,
+            allow: self.allow_tools.clone(),
+            deny: self.deny_tools.clone(),
+        }
+    }
+
+    pub fn collection_policy(&self) -> CollectionPolicy {
+        CollectionPolicy::new(self.collection_policies.clone(), self.default_collection_access)
+    }
+
+    pub fn trash_options(&self) -> TrashOptions {
+        TrashOptions {
+            dir: self.trash_dir.clone().unwrap_or_else(trash::default_dir),
+            retention: Duration::from_secs(self.trash_retention_hours * 3600),
+            allow_hard_delete: self.allow_hard_delete,
+        }
+    }
+
+    pub fn confirm_options(&self) -> ConfirmOptions {
+        ConfirmOptions {
+            rules: self.confirm_tools.clone(),
+            ttl: Duration::from_secs(self.confirm_ttl_secs),
+        }
+    }
+
+    pub fn audit_options(&self) -> AuditOptions {
+        AuditOptions {
+            path: self.audit_log.clone(),
+            documents: self.audit_documents,
+            max_bytes: self.audit_max_bytes,
+            keep_files: self.audit_keep_files,
+        }
+    }
+
     pub fn validate(&self) -> anyhow::Result<()> {
         match self.client_type {
             ClientType::Http => {
@@ -70,7 +329,66 @@ impl Config {
             }
             ClientType::Ephemeral => {}
         }
+        #[cfg(unix)]
+        match (self.transport(), &self.socket) {
+            (TransportKind::Unix, None) => anyhow::bail!("--socket must be provided for the unix transport"),
+            (TransportKind::Sse | TransportKind::Http, Some(_)) => {
+                anyhow::bail!("--socket cannot be combined with the sse or http transport")
+            }
+            _ => {}
+        }
+        #[cfg(not(unix))]
+        if self.transport() == TransportKind::Unix {
+            anyhow::bail!("The unix transport is only available on Unix platforms");
+        }
         Ok(())
     }
 }
 
+#[cfg(unix)]
+fn parse_mode(value: &str) -> Result<u32, String> {
+    u32::from_str_radix(value.trim_start_matches("0o"), 8)
+        .ok()
+        .filter(|mode| *mode <= 0o777)
+        .ok_or_else(|| format!("'{}' is not an octal permission mode such as 600", value))
+}
+
+
+#[derive(Debug, Args, Clone)]
+pub struct ExportArgs {
+    /// Path of the JSONL file to write
+    pub output_path: PathBuf,
+
+    #[arg(long)]
+    pub collection_name: String,
+
+    #[arg(long)]
+    pub include_embeddings: bool,
+
+    #[arg(long)]
+    pub page_size: Option<usize>,
+
+    /// Replace the output file if it already exists
+    #[arg(long)]
+    pub overwrite: bool,
+}
+
+#[derive(Debug, Args, Clone)]
+pub struct ImportArgs {
+    /// Path of the JSONL dump to read
+    pub input_path: PathBuf,
+
+    /// Target collection name (defaults to the name stored in the dump)
+    #[arg(long)]
+    pub collection_name: Option<String>,
+
+    /// Line number to resume from
+    #[arg(long)]
+    pub start_line: Option<usize>,
+
+    #[arg(long, value_enum, default_value = "skip")]
+    pub on_conflict: ConflictPolicy,
+
+    #[arg(long)]
+    pub batch_size: Option<usize>,
+}
@@ -0,0 +1,355 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Tools that support a confirmation step.
+pub const CONFIRMABLE_TOOLS: &[&str] = &["chroma_delete_collection", "chroma_delete_documents"];
+pub const DEFAULT_CONFIRM_TOOLS: &str = "chroma_delete_collection,chroma_delete_documents=10";
+pub const DEFAULT_TTL_SECS: u64 = 300;
+/// Most confirmations waiting at once; no more are issued beyond this.
+const MAX_PENDING: usize = 256;
+/// Most confirmations waiting for one session; its oldest is dropped beyond
+/// this.
+const MAX_PENDING_PER_SESSION: usize = 16;
+
+static GLOBAL_OPTIONS: OnceLock<ConfirmOptions> = OnceLock::new();
+static PENDING: Mutex<Confirmations> = Mutex::new(Confirmations { pending: Vec::new() });
+
+/// When a tool asks for confirmation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Threshold {
+    Always,
+    /// Only for calls affecting more than this many items
+    Above(usize),
+    Off,
+}
+
+/// One `TOOL[=N|off]` entry of `--confirm-tools`.
+#[derive(Debug, Clone)]
+pub struct ConfirmRule {
+    tool: String,
+    threshold: Threshold,
+}
+
+impl FromStr for ConfirmRule {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (tool, threshold) = match value.split_once('=') {
+            None => (value.trim(), Threshold::Always),
+            Some((tool, "off")) => (tool.trim(), Threshold::Off),
+            Some((tool, count)) => {
+                let count = count
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("'{}' is not of the form TOOL, TOOL=N or TOOL=off", value))?;
+                (tool.trim(), Threshold::Above(count))
+            }
+        };
+        if !CONFIRMABLE_TOOLS.contains(&tool) {
+            return Err(format!(
+                "'{}' does not support confirmation (expected one of {})",
+                tool,
+                CONFIRMABLE_TOOLS.join(", ")
+            ));
+        }
+        Ok(Self {
+            tool: tool.to_string(),
+            threshold,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ConfirmOptions {
+    pub rules: Vec<ConfirmRule>,
+    pub ttl: Duration,
+}
+
+impl Default for ConfirmOptions {
+    fn default() -> Self {
+        Self {
+            rules: DEFAULT_CONFIRM_TOOLS
+                .split(',')
+                .map(|rule| rule.parse().expect("default confirmation rules parse"))
+                .collect(),
+            ttl: Duration::from_secs(DEFAULT_TTL_SECS),
+        }
+    }
+}
+
+pub fn configure(options: ConfirmOptions) {
+    if GLOBAL_OPTIONS.set(options).is_err() {
+        tracing::warn!("Confirmation is already configured");
+    }
+}
+
+fn options() -> &'static ConfirmOptions {
+    GLOBAL_OPTIONS.get_or_init(ConfirmOptions::default)
+}
+
+/// A token handed out with a preview; repeating the call with it runs the
+/// operation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Confirmation {
+    pub confirmation_token: String,
+    pub expires_in_secs: u64,
+}
+
+struct Pending {
+    token: String,
+    /// Session the token was issued to; no other session can redeem it
+    session: String,
+    tool: &'static str,
+    call: String,
+    /// Digest of the ids the call resolved to when the token was issued
+    targets: String,
+    expires: Instant,
+}
+
+/// Decides whether a call to `tool` affecting `items` items may run.
+/// Returns a new confirmation when the call needs one and carried no token;
+/// fails when the token does not match this exact call or has expired.
+/// `request` is the call's arguments; its `confirmation_token` field is
+/// ignored when matching. `targets` are the ids the call resolved to, such as
+/// the documents a filter matched; the token is refused if they changed since
+/// the preview. Tokens only work in the session they were issued to. Tokens
+/// are not checked at all for a tool whose confirmation is off.
+pub fn check(
+    tool: &'static str,
+    items: usize,
+    request: &impl Serialize,
+    targets: &[String],
+    token: Option<&str>,
+) -> Result<Option<Confirmation>> {
+    let threshold = options()
+        .rules
+        .iter()
+        .rev()
+        .find(|rule| rule.tool == tool)
+        .map_or(Threshold::Off, |rule| rule.threshold);
+    let needed = match threshold {
+        Threshold::Always => true,
+        Threshold::Above(count) => items > count,
+        Threshold::Off => return Ok(None),
+    };
+    let call = Call {
+        session: session(),
+        tool,
+        request: fingerprint(request)?,
+        targets: digest(targets),
+    };
+
+    let mut pending = PENDING.lock().unwrap();
+    match token {
+        Some(token) => pending.redeem(token, &call, Instant::now()).map(|()| None),
+        None if needed => pending.issue(call, Instant::now(), options().ttl).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// The session a call came through, as its transport and session id. Calls
+/// outside a session, such as HTTP requests without `Mcp-Session-Id`, share
+/// one per transport.
+fn session() -> String {
+    let client = crate::audit::current_client();
+    match client.session {
+        Some(session) => format!("{}:{}", client.transport, session),
+        None => client.transport,
+    }
+}
+
+/// What a token is issued for and checked against.
+struct Call {
+    session: String,
+    tool: &'static str,
+    request: String,
+    targets: String,
+}
+
+struct Confirmations {
+    pending: Vec<Pending>,
+}
+
+impl Confirmations {
+    fn issue(&mut self, call: Call, now: Instant, ttl: Duration) -> Result<Confirmation> {
+        self.pending.retain(|pending| pending.expires > now);
+        let issued = self.pending.iter().filter(|pending| pending.session == call.session);
+        if issued.count() >= MAX_PENDING_PER_SESSION
+            && let Some(oldest) = self.pending.iter().position(|pending| pending.session == call.session)
+        {
+            self.pending.remove(oldest);
+        }
+        if self.pending.len() >= MAX_PENDING {
+            bail!(
+                "Too many confirmations are pending; confirm or let some expire before previewing {} again",
+                call.tool
+            );
+        }
+
+        let token = uuid::Uuid::new_v4().simple().to_string();
+        self.pending.push(Pending {
+            token: token.clone(),
+            session: call.session,
+            tool: call.tool,
+            call: call.request,
+            targets: call.targets,
+            expires: now + ttl,
+        });
+        Ok(Confirmation {
+            confirmation_token: token,
+            expires_in_secs: ttl.as_secs(),
+        })
+    }
+
+    /// Consumes `token` if it was issued for `call`. A token of another
+    /// session is reported like an unknown one.
+    fn redeem(&mut self, token: &str, call: &Call, now: Instant) -> Result<()> {
+        self.pending.retain(|pending| pending.expires > now);
+        let Some(index) = self
+            .pending
+            .iter()
+            .position(|pending| pending.token == token && pending.session == call.session)
+        else {
+            bail!(
+                "Confirmation token '{}' is unknown or has expired; call {} again without it to get a new one",
+                token,
+                call.tool
+            );
+        };
+        if self.pending[index].tool != call.tool || self.pending[index].call != call.request {
+            bail!(
+                "Confirmation token '{}' was issued for a different call; repeat the previewed call unchanged",
+                token
+            );
+        }
+        let pending = self.pending.remove(index);
+        if pending.targets != call.targets {
+            bail!(
+                "The documents this call applies to changed since confirmation token '{}' was issued; call {} again without it to preview them",
+                token,
+                call.tool
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Digest of `ids` regardless of their order.
+fn digest(ids: &[String]) -> String {
+    let mut ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+    ids.sort_unstable();
+    ids.dedup();
+    let mut hasher = Sha256::new();
+    for id in ids {
+        hasher.update(id.as_bytes());
+        hasher.update([0]);
+    }
+    hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn fingerprint(request: &impl Serialize) -> Result<String> {
+    let mut request = serde_json::to_value(request)?;
+    if let Value::Object(fields) = &mut request {
+        fields.remove("confirmation_token");
+        fields.retain(|_, value| !value.is_null());
+    }
+    Ok(request.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TTL: Duration = Duration::from_secs(60);
+
+    fn call(session: &str, request: &str, targets: &[&str]) -> Call {
+        let targets: Vec<String> = targets.iter().map(|id| id.to_string()).collect();
+        Call {
+            session: session.to_string(),
+            tool: "chroma_delete_documents",
+            request: request.to_string(),
+            targets: digest(&targets),
+        }
+    }
+
+    fn confirmations() -> Confirmations {
+        Confirmations { pending: Vec::new() }
+    }
+
+    #[test]
+    fn tokens_work_once_and_only_for_their_call() {
+        let mut confirmations = confirmations();
+        let now = Instant::now();
+        let token = confirmations.issue(call("http:a", "delete", &["a", "b"]), now, TTL).unwrap().confirmation_token;
+        assert_eq!(token.len(), 32);
+
+        assert!(confirmations.redeem(&token, &call("http:a", "delete other", &["a", "b"]), now).is_err());
+        confirmations.redeem(&token, &call("http:a", "delete", &["b", "a"]), now).unwrap();
+        let reused = confirmations.redeem(&token, &call("http:a", "delete", &["a", "b"]), now).unwrap_err();
+        assert!(reused.to_string().contains("unknown or has expired"));
+    }
+
+    #[test]
+    fn tokens_are_refused_when_the_targets_changed() {
+        let mut confirmations = confirmations();
+        let now = Instant::now();
+        let token = confirmations.issue(call("stdio", "delete", &["a"]), now, TTL).unwrap().confirmation_token;
+
+        let changed = confirmations.redeem(&token, &call("stdio", "delete", &["b"]), now).unwrap_err();
+        assert!(changed.to_string().contains("changed since"));
+        assert!(confirmations.redeem(&token, &call("stdio", "delete", &["a"]), now).is_err());
+    }
+
+    #[test]
+    fn tokens_expire() {
+        let mut confirmations = confirmations();
+        let now = Instant::now();
+        let token = confirmations.issue(call("stdio", "delete", &[]), now, TTL).unwrap().confirmation_token;
+
+        let expired = confirmations.redeem(&token, &call("stdio", "delete", &[]), now + TTL).unwrap_err();
+        assert!(expired.to_string().contains("unknown or has expired"));
+    }
+
+    #[test]
+    fn tokens_belong_to_their_session() {
+        let mut confirmations = confirmations();
+        let now = Instant::now();
+        let token = confirmations.issue(call("http:a", "delete", &[]), now, TTL).unwrap().confirmation_token;
+
+        assert!(confirmations.redeem(&token, &call("http:b", "delete", &[]), now).is_err());
+        confirmations.redeem(&token, &call("http:a", "delete", &[]), now).unwrap();
+    }
+
+    #[test]
+    fn one_session_cannot_evict_the_tokens_of_others() {
+        let mut confirmations = confirmations();
+        let now = Instant::now();
+        let other = confirmations.issue(call("http:a", "delete", &[]), now, TTL).unwrap().confirmation_token;
+        let tokens: Vec<String> = (0..MAX_PENDING_PER_SESSION + 1)
+            .map(|index| {
+                let issued = confirmations.issue(call("http:b", &format!("delete {}", index), &[]), now, TTL);
+                issued.unwrap().confirmation_token
+            })
+            .collect();
+
+        assert!(confirmations.redeem(&tokens[0], &call("http:b", "delete 0", &[]), now).is_err());
+        confirmations.redeem(&tokens[1], &call("http:b", "delete 1", &[]), now).unwrap();
+        confirmations.redeem(&other, &call("http:a", "delete", &[]), now).unwrap();
+    }
+
+    #[test]
+    fn no_tokens_are_issued_beyond_the_limit() {
+        let mut confirmations = confirmations();
+        let now = Instant::now();
+        for index in 0..MAX_PENDING {
+            confirmations.issue(call(&format!("http:{}", index), "delete", &[]), now, TTL).unwrap();
+        }
+        assert!(confirmations.issue(call("http:new", "delete", &[]), now, TTL).is_err());
+        assert!(confirmations.issue(call("http:new", "delete", &[]), now + TTL, TTL).is_ok());
+    }
+}
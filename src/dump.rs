@@ -0,0 +1,816 @@
+use crate::cancel;
+use crate::client::{ChromaClient, Collection};
+use crate::policy;
+use crate::progress;
+use crate::registry::ToolOutput;
+use crate::secret;
+use crate::trash;
+use anyhow::{anyhow, bail, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Instant;
+
+pub const DUMP_FORMAT_VERSION: u32 = 1;
+pub const DEFAULT_PAGE_SIZE: usize = 500;
+pub const DEFAULT_IMPORT_BATCH_SIZE: usize = 100;
+
+static EXPORT_DIR: OnceLock<PathBuf> = OnceLock::new();
+static IMPORT_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DumpLine {
+    Collection(CollectionHeader),
+    Record(Record),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollectionHeader {
+    pub version: u32,
+    pub name: String,
+    pub metadata: Option<Value>,
+    pub configuration: Value,
+    pub count: usize,
+    pub include_embeddings: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Record {
+    pub id: String,
+    pub document: Option<String>,
+    pub metadata: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<Vec<f32>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportReport {
+    pub collection_name: String,
+    pub output_path: PathBuf,
+    pub records: usize,
+    pub pages: usize,
+}
+
+impl ToolOutput for ExportReport {
+    fn summary(&self) -> String {
+        format!(
+            "Exported {} records from '{}' to {} in {} pages",
+            self.records,
+            self.collection_name,
+            self.output_path.display(),
+            self.pages
+        )
+    }
+}
+
+/// Sets the directories `chroma_export_collection` may write to and
+/// `chroma_import_collection` may read from. A directory that does not exist
+/// is dropped with a warning.
+pub fn configure(export_dir: Option<PathBuf>, import_dir: Option<PathBuf>) {
+    configure_dir(&EXPORT_DIR, export_dir, "Export");
+    configure_dir(&IMPORT_DIR, import_dir, "Import");
+}
+
+fn configure_dir(slot: &OnceLock<PathBuf>, dir: Option<PathBuf>, label: &str) {
+    let Some(dir) = dir else {
+        return;
+    };
+    match std::fs::canonicalize(&dir) {
+        Ok(dir) => {
+            if slot.set(dir).is_err() {
+                tracing::warn!("{} directory is already configured", label);
+            }
+        }
+        Err(e) => tracing::warn!(
+            "Ignoring {} directory '{}': {}",
+            label.to_lowercase(),
+            dir.display(),
+            secret::scrub(&e.to_string())
+        ),
+    }
+}
+
+/// Resolves `path` against the `--export-dir` directory and fails unless the
+/// file it names lies inside it, after following symlinks.
+pub fn resolve_export_path(path: &Path) -> Result<PathBuf> {
+    let Some(dir) = EXPORT_DIR.get() else {
+        bail!("Exporting to files on the server is disabled; start the server with --export-dir to allow a directory");
+    };
+    let joined = dir.join(path);
+    let (Some(parent), Some(file_name)) = (joined.parent(), joined.file_name()) else {
+        bail!("Path '{}' does not name a file", path.display());
+    };
+    let parent = std::fs::canonicalize(parent).map_err(|e| anyhow!("Cannot write to '{}': {}", path.display(), e))?;
+    if !parent.starts_with(dir) {
+        bail!("Path '{}' is outside the directory allowed by --export-dir", path.display());
+    }
+    Ok(parent.join(file_name))
+}
+
+/// Resolves `path` against the `--import-dir` directory and fails unless the
+/// file it names lies inside it, after following symlinks.
+pub fn resolve_import_path(path: &Path) -> Result<PathBuf> {
+    let Some(dir) = IMPORT_DIR.get() else {
+        bail!("Importing files on the server is disabled; start the server with --import-dir to allow a directory");
+    };
+    let resolved = std::fs::canonicalize(dir.join(path)).map_err(|e| anyhow!("Cannot open '{}': {}", path.display(), e))?;
+    if !resolved.starts_with(dir) {
+        bail!("Path '{}' is outside the directory allowed by --import-dir", path.display());
+    }
+    if trash::contains(&resolved) {
+        bail!("Path '{}' is in the trash; use chroma_restore instead", path.display());
+    }
+    Ok(resolved)
+}
+
+/// Creates the dump file at `path`. An existing file is only replaced when
+/// `overwrite` is set, and never through a symlink.
+fn create_dump(path: &Path, overwrite: bool) -> Result<File> {
+    let existing = std::fs::symlink_metadata(path).ok();
+    let file = match existing {
+        Some(metadata) if metadata.is_symlink() => bail!("Cannot overwrite '{}': it is a symlink", path.display()),
+        Some(_) if !overwrite => bail!("'{}' already exists; pass overwrite to replace it", path.display()),
+        Some(_) => File::create(path),
+        None => File::create_new(path),
+    };
+    file.map_err(|e| anyhow!("Cannot create '{}': {}", path.display(), e))
+}
+
+pub fn export_collection(
+    collection: &Collection,
+    output_path: &Path,
+    include_embeddings: bool,
+    page_size: usize,
+    overwrite: bool,
+) -> Result<ExportReport> {
+    let mut writer = BufWriter::new(create_dump(output_path, overwrite)?);
+
+    let total = collection.count()?;
+    write_line(&mut writer, &DumpLine::Collection(collection_header(collection, total, include_embeddings)?))?;
+
+    let mut exported = 0;
+    let pages = for_each_page(collection, include_embeddings, page_size, total, |records| {
+        exported += records.len();
+        for record in records {
+            write_line(&mut writer, &DumpLine::Record(record))?;
+        }
+        progress::report("exporting records", exported, Some(total));
+        Ok(())
+    });
+    writer.flush()?;
+    let (records, pages) = pages.map_err(|e| {
+        cancel::partial(e, || {
+            format!(
+                "Cancelled after exporting {} of {} records; '{}' is incomplete",
+                exported,
+                total,
+                output_path.display()
+            )
+        })
+    })?;
+
+    Ok(ExportReport {
+        collection_name: collection.name.clone(),
+        output_path: output_path.to_path_buf(),
+        records,
+        pages,
+    })
+}
+
+/// Writes the records of `collection` with the given ids, embeddings
+/// included, as a dump at `output_path`; returns how many were written.
+pub fn export_records(collection: &Collection, ids: Vec<String>, output_path: &Path) -> Result<usize> {
+    let include = ["documents", "metadatas", "embeddings"].map(str::to_string).to_vec();
+    let records = records_from_page(&collection.get(Some(ids), None, None, include, None, None)?)?;
+
+    let mut writer = BufWriter::new(create_dump(output_path, false)?);
+    write_line(&mut writer, &DumpLine::Collection(collection_header(collection, records.len(), true)?))?;
+    let count = records.len();
+    for record in records {
+        write_line(&mut writer, &DumpLine::Record(record))?;
+    }
+    writer.flush()?;
+    Ok(count)
+}
+
+fn collection_header(collection: &Collection, count: usize, include_embeddings: bool) -> Result<CollectionHeader> {
+    Ok(CollectionHeader {
+        version: DUMP_FORMAT_VERSION,
+        name: collection.name.clone(),
+        metadata: collection.metadata()?,
+        configuration: collection.configuration()?,
+        count,
+        include_embeddings,
+    })
+}
+
+/// Fetches the records of `collection` page by page, stopping at a short
+/// page or once the `total` counted beforehand has been read, whichever
+/// comes first.
+fn for_each_page(
+    collection: &Collection,
+    include_embeddings: bool,
+    page_size: usize,
+    total: usize,
+    mut handle: impl FnMut(Vec<Record>) -> Result<()>,
+) -> Result<(usize, usize)> {
+    let page_size = page_size.max(1);
+    let mut include = vec!["documents".to_string(), "metadatas".to_string()];
+    if include_embeddings {
+        include.push("embeddings".to_string());
+    }
+
+    let mut records = 0;
+    let mut pages = 0;
+    loop {
+        cancel::check()?;
+        let page = collection.get(None, None, None, include.clone(), Some(page_size), Some(records))?;
+        let page = records_from_page(&page)?;
+        let fetched = page.len();
+        handle(page)?;
+
+        records += fetched;
+        pages += 1;
+        if fetched < page_size || records >= total {
+            return Ok((records, pages));
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    #[default]
+    Skip,
+    Overwrite,
+    Fail,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportOptions {
+    pub input_path: PathBuf,
+    pub collection_name: Option<String>,
+    pub start_line: usize,
+    pub on_conflict: ConflictPolicy,
+    pub batch_size: usize,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub collection_name: String,
+    pub input_path: PathBuf,
+    pub created_collection: bool,
+    pub records_read: usize,
+    pub records_written: usize,
+    pub records_skipped: usize,
+    pub batches: usize,
+    pub last_line: usize,
+}
+
+impl ToolOutput for ImportReport {
+    fn summary(&self) -> String {
+        format!(
+            "Imported {} records into {}'{}' from {} ({} skipped, {} batches, through line {})",
+            self.records_written,
+            if self.created_collection { "new collection " } else { "" },
+            self.collection_name,
+            self.input_path.display(),
+            self.records_skipped,
+            self.batches,
+            self.last_line
+        )
+    }
+}
+
+pub fn import_collection(client: &ChromaClient, options: &ImportOptions) -> Result<ImportReport> {
+    let file = File::open(&options.input_path)
+        .map_err(|e| anyhow!("Cannot open '{}': {}", options.input_path.display(), e))?;
+    let mut lines = BufReader::new(file).lines();
+
+    let header = match lines.next() {
+        Some(line) => serde_json::from_str::<DumpLine>(&line?)
+            .map_err(|e| anyhow!("Line 1: {}", e))?,
+        None => bail!("Dump '{}' is empty", options.input_path.display()),
+    };
+    let DumpLine::Collection(header) = header else {
+        bail!("Line 1: expected a collection header");
+    };
+    if header.version > DUMP_FORMAT_VERSION {
+        bail!("Unsupported dump version {} (expected at most {})", header.version, DUMP_FORMAT_VERSION);
+    }
+
+    let collection_name = options.collection_name.clone().unwrap_or(header.name);
+    policy::check_write(&collection_name)?;
+    let created_collection = !client.list_collections(None, None)?.contains(&collection_name);
+    if created_collection {
+        client.create_collection(&collection_name, header.metadata, Some(header.configuration))?;
+    }
+    let collection = client.get_collection(&collection_name)?;
+
+    let total = header.count;
+    let mut report = ImportReport {
+        collection_name,
+        input_path: options.input_path.clone(),
+        created_collection,
+        ..Default::default()
+    };
+    let batch_size = options.batch_size.max(1);
+    let mut batch = Vec::with_capacity(batch_size);
+    let mut batch_end = 0;
+
+    for (index, line) in lines.enumerate() {
+        let line_number = index + 2;
+        let line = line?;
+        if line_number < options.start_line || line.trim().is_empty() {
+            continue;
+        }
+
+        let record = match serde_json::from_str::<DumpLine>(&line) {
+            Ok(DumpLine::Record(record)) => record,
+            Ok(DumpLine::Collection(_)) => {
+                return Err(resumable(
+                    anyhow!("Line {}: unexpected collection header", line_number),
+                    &report,
+                    options.start_line,
+                ));
+            }
+            Err(e) => return Err(resumable(anyhow!("Line {}: {}", line_number, e), &report, options.start_line)),
+        };
+        report.records_read += 1;
+        batch.push(record);
+        batch_end = line_number;
+
+        if batch.len() >= batch_size {
+            write_batch(&collection, &mut batch, options.on_conflict, &mut report)
+                .map_err(|e| resumable(e, &report, options.start_line))?;
+            report.last_line = batch_end;
+            progress::report("importing records", report.records_read, Some(total));
+        }
+    }
+    write_batch(&collection, &mut batch, options.on_conflict, &mut report)
+        .map_err(|e| resumable(e, &report, options.start_line))?;
+    report.last_line = report.last_line.max(batch_end);
+    progress::report("importing records", report.records_read, Some(total.max(report.records_read)));
+
+    Ok(report)
+}
+
+fn write_batch(
+    collection: &Collection,
+    batch: &mut Vec<Record>,
+    on_conflict: ConflictPolicy,
+    report: &mut ImportReport,
+) -> Result<()> {
+    let mut records = std::mem::take(batch);
+    if records.is_empty() {
+        return Ok(());
+    }
+    cancel::check()?;
+
+    if on_conflict != ConflictPolicy::Overwrite {
+        let ids = records.iter().map(|record| record.id.clone()).collect();
+        let existing = collection.get(Some(ids), None, None, vec![], None, None)?;
+        let existing: HashSet<&str> = existing["ids"]
+            .as_array()
+            .map(|ids| ids.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+
+        if on_conflict == ConflictPolicy::Fail
+            && let Some(record) = records.iter().find(|record| existing.contains(record.id.as_str()))
+        {
+            bail!("Record '{}' already exists in collection '{}'", record.id, collection.name);
+        }
+
+        let before = records.len();
+        records.retain(|record| !existing.contains(record.id.as_str()));
+        report.records_skipped += before - records.len();
+    }
+
+    report.records_written += upsert_records(collection, records)?;
+    report.batches += 1;
+
+    tracing::info!(
+        collection = %collection.name,
+        records_written = report.records_written,
+        records_skipped = report.records_skipped,
+        "Imported batch {}",
+        report.batches
+    );
+    Ok(())
+}
+
+fn upsert_records(collection: &Collection, records: Vec<Record>) -> Result<usize> {
+    if records.is_empty() {
+        return Ok(0);
+    }
+
+    let count = records.len();
+    let embeddings = records
+        .iter()
+        .map(|record| record.embedding.clone())
+        .collect::<Option<Vec<_>>>();
+    let mut documents = Vec::with_capacity(count);
+    let mut metadatas = Vec::with_capacity(count);
+    let mut ids = Vec::with_capacity(count);
+    for record in records {
+        documents.push(record.document);
+        metadatas.push(record.metadata);
+        ids.push(record.id);
+    }
+
+    collection.upsert(embeddings, documents, Some(metadatas), ids)?;
+    Ok(count)
+}
+
+/// Adds what was written to an import error, with the line to resume from:
+/// the one after the last committed batch, or `start_line` if no batch was
+/// committed yet.
+fn resumable(error: anyhow::Error, report: &ImportReport, start_line: usize) -> anyhow::Error {
+    let written = format!(
+        "{} records written in {} batches through line {}; resume with start_line {}",
+        report.records_written,
+        report.batches,
+        report.last_line,
+        start_line.max(report.last_line + 1)
+    );
+    if cancel::is_cancellation(&error) {
+        return error.context(format!("Cancelled after {}", written));
+    }
+    anyhow!("{} ({})", error, written)
+}
+
+#[derive(Debug, Clone)]
+pub struct CloneOptions {
+    pub source_collection: String,
+    pub target_collection: String,
+    pub target_profile: Option<String>,
+    pub embedding_function_name: Option<String>,
+    pub batch_size: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CloneReport {
+    pub source_collection: String,
+    pub target_collection: String,
+    pub target_profile: Option<String>,
+    pub re_embedded: bool,
+    pub records_copied: usize,
+    pub batches: usize,
+    pub elapsed_ms: u128,
+}
+
+impl ToolOutput for CloneReport {
+    fn summary(&self) -> String {
+        format!(
+            "Copied {} records from '{}' to '{}'{}{} in {} ms",
+            self.records_copied,
+            self.source_collection,
+            self.target_collection,
+            self.target_profile
+                .as_ref()
+                .map(|profile| format!(" on profile '{}'", profile))
+                .unwrap_or_default(),
+            if self.re_embedded { " (re-embedded)" } else { "" },
+            self.elapsed_ms
+        )
+    }
+}
+
+pub fn clone_collection(
+    source_client: &ChromaClient,
+    target_client: &ChromaClient,
+    options: &CloneOptions,
+) -> Result<CloneReport> {
+    let started = Instant::now();
+    let source = source_client.get_collection(&options.source_collection)?;
+
+    if target_client
+        .list_collections(None, None)?
+        .contains(&options.target_collection)
+    {
+        bail!("Target collection '{}' already exists", options.target_collection);
+    }
+
+    let mut configuration = source.configuration()?;
+    if let (Some(name), Some(configuration)) =
+        (&options.embedding_function_name, configuration.as_object_mut())
+    {
+        configuration.insert("embedding_function".to_string(), json!(name));
+    }
+    target_client.create_collection(&options.target_collection, source.metadata()?, Some(configuration))?;
+    let target = target_client.get_collection(&options.target_collection)?;
+
+    let re_embedded = options.embedding_function_name.is_some();
+    let phase = if re_embedded { "re-embedding records" } else { "copying records" };
+    let total = source.count()?;
+    let mut copied = 0;
+    let mut committed = 0;
+    let (records_copied, batches) = for_each_page(&source, !re_embedded, options.batch_size, total, |records| {
+        copied += upsert_records(&target, records)?;
+        committed += 1;
+        progress::report(phase, copied, Some(total));
+        Ok(())
+    })
+    .map_err(|e| {
+        cancel::partial(e, || {
+            format!(
+                "Cancelled after copying {} of {} records in {} batches; '{}' holds a partial copy",
+                copied, total, committed, options.target_collection
+            )
+        })
+    })?;
+
+    Ok(CloneReport {
+        source_collection: options.source_collection.clone(),
+        target_collection: options.target_collection.clone(),
+        target_profile: options.target_profile.clone(),
+        re_embedded,
+        records_copied,
+        batches,
+        elapsed_ms: started.elapsed().as_millis(),
+    })
+}
+
+pub fn records_from_page(page: &Value) -> Result<Vec<Record>> {
+    let ids = page["ids"]
+        .as_array()
+        .ok_or_else(|| anyhow!("Malformed get response: missing 'ids'"))?;
+
+    ids.iter()
+        .enumerate()
+        .map(|(index, id)| {
+            let id = id
+                .as_str()
+                .ok_or_else(|| anyhow!("Malformed get response: non-string id"))?;
+            let embedding = match page.get("embeddings").and_then(|e| e.get(index)) {
+                Some(Value::Null) | None => None,
+                Some(embedding) => Some(serde_json::from_value(embedding.clone())?),
+            };
+            Ok(Record {
+                id: id.to_string(),
+                document: page["documents"][index].as_str().map(str::to_string),
+                metadata: page["metadatas"].get(index).filter(|m| !m.is_null()).cloned(),
+                embedding,
+            })
+        })
+        .collect()
+}
+
+fn write_line(writer: &mut impl Write, line: &DumpLine) -> Result<()> {
+    serde_json::to_writer(&mut *writer, line)?;
+    writer.write_all(b"\n")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::get_client;
+
+    /// An empty directory under the system temp dir, for the test `name`.
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mcp-chroma-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::canonicalize(dir).unwrap()
+    }
+
+    /// A collection in the in-memory backend with `count` records, each with
+    /// a document, metadata and an embedding.
+    fn collection(name: &str, count: usize) -> Collection {
+        let client = get_client();
+        client.create_collection(name, Some(json!({"owner": "docs"})), Some(json!({"hnsw": {"space": "cosine"}}))).unwrap();
+        let collection = client.get_collection(name).unwrap();
+        let ids: Vec<String> = (0..count).map(|index| format!("id{}", index)).collect();
+        let embeddings = (0..count).map(|index| vec![index as f32, 1.0]).collect();
+        let documents = ids.iter().map(|id| Some(format!("text of {}", id))).collect();
+        let metadatas = (0..count).map(|index| Some(json!({"index": index}))).collect();
+        collection.upsert(Some(embeddings), documents, Some(metadatas), ids).unwrap();
+        collection
+    }
+
+    fn dump_lines(path: &Path) -> Vec<DumpLine> {
+        let dump = std::fs::read_to_string(path).unwrap();
+        dump.lines().map(|line| serde_json::from_str(line).unwrap()).collect()
+    }
+
+    #[test]
+    fn export_writes_a_header_and_every_record_page_by_page() {
+        let dir = scratch_dir("export-pages");
+        let collection = collection("export_pages", 5);
+        let path = dir.join("dump.jsonl");
+
+        let report = export_collection(&collection, &path, true, 2, false).unwrap();
+        assert_eq!((report.records, report.pages), (5, 3));
+
+        let lines = dump_lines(&path);
+        let DumpLine::Collection(header) = &lines[0] else {
+            panic!("expected a collection header first");
+        };
+        assert_eq!((header.name.as_str(), header.count, header.include_embeddings), ("export_pages", 5, true));
+        assert_eq!(header.metadata, Some(json!({"owner": "docs"})));
+        assert_eq!(header.configuration, json!({"hnsw": {"space": "cosine"}}));
+        let records: Vec<&Record> = lines[1..]
+            .iter()
+            .map(|line| match line {
+                DumpLine::Record(record) => record,
+                DumpLine::Collection(_) => panic!("expected only records after the header"),
+            })
+            .collect();
+        assert_eq!(records.len(), 5);
+        assert_eq!(records[4].id, "id4");
+        assert_eq!(records[4].embedding, Some(vec![4.0, 1.0]));
+    }
+
+    #[test]
+    fn export_replaces_files_only_when_asked_and_never_through_symlinks() {
+        let dir = scratch_dir("export-overwrite");
+        let collection = collection("export_overwrite", 1);
+        let path = dir.join("dump.jsonl");
+        std::fs::write(&path, "old").unwrap();
+
+        let refused = export_collection(&collection, &path, false, 10, false).unwrap_err();
+        assert!(refused.to_string().contains("already exists"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "old");
+        export_collection(&collection, &path, false, 10, true).unwrap();
+        assert_eq!(dump_lines(&path).len(), 2);
+
+        let target = dir.join("target");
+        std::fs::write(&target, "kept").unwrap();
+        let link = dir.join("link.jsonl");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        let refused = export_collection(&collection, &link, false, 10, true).unwrap_err();
+        assert!(refused.to_string().contains("is a symlink"));
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "kept");
+    }
+
+    #[test]
+    fn export_paths_stay_inside_the_export_dir() {
+        let dir = EXPORT_DIR.get_or_init(|| scratch_dir("export-dir"));
+        let outside = scratch_dir("export-outside");
+        let _ = std::os::unix::fs::symlink(&outside, dir.join("escape"));
+
+        assert_eq!(resolve_export_path(Path::new("dump.jsonl")).unwrap(), dir.join("dump.jsonl"));
+        for path in ["../dump.jsonl", "escape/dump.jsonl"] {
+            let refused = resolve_export_path(Path::new(path)).unwrap_err();
+            assert!(refused.to_string().contains("outside the directory"), "{}: {}", path, refused);
+        }
+        let absolute = outside.join("dump.jsonl");
+        assert!(resolve_export_path(&absolute).is_err());
+        assert!(resolve_export_path(Path::new("")).is_err());
+    }
+
+    fn import_options(path: &Path, collection_name: &str, on_conflict: ConflictPolicy) -> ImportOptions {
+        ImportOptions {
+            input_path: path.to_path_buf(),
+            collection_name: Some(collection_name.to_string()),
+            start_line: 0,
+            on_conflict,
+            batch_size: 2,
+        }
+    }
+
+    fn stored(collection: &Collection) -> Vec<Record> {
+        let include = ["documents", "metadatas", "embeddings"].map(str::to_string).to_vec();
+        records_from_page(&collection.get(None, None, None, include, None, None).unwrap()).unwrap()
+    }
+
+    fn documents(collection: &Collection) -> Vec<String> {
+        stored(collection).into_iter().filter_map(|record| record.document).collect()
+    }
+
+    #[test]
+    fn export_then_import_round_trips() {
+        let dir = scratch_dir("import-round-trip");
+        let source = collection("round_trip_source", 5);
+        let path = dir.join("dump.jsonl");
+        export_collection(&source, &path, true, 2, false).unwrap();
+
+        let client = get_client();
+        let report = import_collection(&client, &import_options(&path, "round_trip_copy", ConflictPolicy::Skip)).unwrap();
+        assert!(report.created_collection);
+        assert_eq!((report.records_read, report.records_written, report.batches, report.last_line), (5, 5, 3, 6));
+
+        let copy = client.get_collection("round_trip_copy").unwrap();
+        assert_eq!(copy.metadata().unwrap(), Some(json!({"owner": "docs"})));
+        assert_eq!(copy.configuration().unwrap(), json!({"hnsw": {"space": "cosine"}}));
+        assert_eq!(
+            serde_json::to_value(stored(&copy)).unwrap(),
+            serde_json::to_value(stored(&source)).unwrap()
+        );
+    }
+
+    #[test]
+    fn import_conflict_policies() {
+        let dir = scratch_dir("import-conflicts");
+        let path = dir.join("dump.jsonl");
+        export_collection(&collection("conflict_source", 3), &path, false, 10, false).unwrap();
+        let client = get_client();
+        let target = collection("conflict_target", 0);
+        target.add(vec!["local".to_string()], None, vec!["id1".to_string()]).unwrap();
+
+        let failed = import_collection(&client, &import_options(&path, "conflict_target", ConflictPolicy::Fail)).unwrap_err();
+        assert!(failed.to_string().contains("Record 'id1' already exists"));
+        assert_eq!(documents(&target), ["local"]);
+
+        let skipped = import_collection(&client, &import_options(&path, "conflict_target", ConflictPolicy::Skip)).unwrap();
+        assert_eq!((skipped.records_written, skipped.records_skipped), (2, 1));
+        assert_eq!(documents(&target), ["text of id0", "local", "text of id2"]);
+
+        let replaced = import_collection(&client, &import_options(&path, "conflict_target", ConflictPolicy::Overwrite)).unwrap();
+        assert_eq!((replaced.records_written, replaced.records_skipped), (3, 0));
+        assert_eq!(documents(&target), ["text of id0", "text of id1", "text of id2"]);
+    }
+
+    #[test]
+    fn import_reports_and_resumes_from_start_line() {
+        let dir = scratch_dir("import-resume");
+        let path = dir.join("dump.jsonl");
+        export_collection(&collection("resume_source", 4), &path, false, 10, false).unwrap();
+        let mut lines: Vec<String> = std::fs::read_to_string(&path).unwrap().lines().map(str::to_string).collect();
+        lines.insert(4, "not json".to_string());
+        std::fs::write(&path, lines.join("\n")).unwrap();
+        let client = get_client();
+
+        let failed = import_collection(&client, &import_options(&path, "resume_target", ConflictPolicy::Fail)).unwrap_err();
+        let message = failed.to_string();
+        assert!(message.starts_with("Line 5: "), "{}", message);
+        assert!(message.contains("2 records written in 1 batches through line 3; resume with start_line 4"), "{}", message);
+
+        lines[4] = r#"{"type":"record","id":"id9","document":"fixed","metadata":null}"#.to_string();
+        std::fs::write(&path, lines.join("\n")).unwrap();
+        let mut resumed = import_options(&path, "resume_target", ConflictPolicy::Fail);
+        resumed.start_line = 4;
+        let report = import_collection(&client, &resumed).unwrap();
+        assert_eq!((report.records_read, report.last_line), (3, 6));
+        let target = client.get_collection("resume_target").unwrap();
+        assert_eq!(documents(&target), ["text of id0", "text of id1", "text of id2", "text of id3", "fixed"]);
+    }
+
+    #[test]
+    fn import_paths_stay_inside_the_import_dir() {
+        let dir = IMPORT_DIR.get_or_init(|| scratch_dir("import-dir"));
+        std::fs::write(dir.join("dump.jsonl"), "").unwrap();
+        let outside = scratch_dir("import-outside");
+        std::fs::write(outside.join("dump.jsonl"), "").unwrap();
+
+        assert_eq!(resolve_import_path(Path::new("dump.jsonl")).unwrap(), dir.join("dump.jsonl"));
+        let parent = Path::new("..").join(outside.file_name().unwrap()).join("dump.jsonl");
+        for path in [parent, outside.join("dump.jsonl")] {
+            let refused = resolve_import_path(&path).unwrap_err();
+            assert!(refused.to_string().contains("outside the directory"), "{}: {}", path.display(), refused);
+        }
+    }
+
+    fn clone_options(source: &str, target: &str, embedding_function_name: Option<&str>) -> CloneOptions {
+        CloneOptions {
+            source_collection: source.to_string(),
+            target_collection: target.to_string(),
+            target_profile: None,
+            embedding_function_name: embedding_function_name.map(str::to_string),
+            batch_size: 2,
+        }
+    }
+
+    #[test]
+    fn clone_copies_records_in_batches_and_refuses_existing_targets() {
+        let source = collection("clone_source", 5);
+        let client = get_client();
+
+        let report = clone_collection(&client, &client, &clone_options("clone_source", "clone_copy", None)).unwrap();
+        assert_eq!((report.records_copied, report.batches, report.re_embedded), (5, 3, false));
+        let copy = client.get_collection("clone_copy").unwrap();
+        assert_eq!(copy.metadata().unwrap(), Some(json!({"owner": "docs"})));
+        assert_eq!(
+            serde_json::to_value(stored(&copy)).unwrap(),
+            serde_json::to_value(stored(&source)).unwrap()
+        );
+
+        let existing = clone_collection(&client, &client, &clone_options("clone_source", "clone_copy", None)).unwrap_err();
+        assert_eq!(existing.to_string(), "Target collection 'clone_copy' already exists");
+    }
+
+    #[test]
+    fn clone_re_embeds_without_copying_embeddings() {
+        collection("re_embed_source", 3);
+        let client = get_client();
+
+        let options = clone_options("re_embed_source", "re_embed_copy", Some("openai"));
+        let report = clone_collection(&client, &client, &options).unwrap();
+        assert_eq!((report.records_copied, report.re_embedded), (3, true));
+        let copy = client.get_collection("re_embed_copy").unwrap();
+        assert_eq!(
+            copy.configuration().unwrap(),
+            json!({"hnsw": {"space": "cosine"}, "embedding_function": "openai"})
+        );
+        let records = stored(&copy);
+        assert_eq!(records.len(), 3);
+        assert!(records.iter().all(|record| record.embedding.is_none() && record.document.is_some()));
+    }
+}
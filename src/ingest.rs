@@ -0,0 +1,531 @@
+use crate::cancel;
+use crate::chunk::{chunk_code, chunk_text, Language};
+use crate::client::Collection;
+use crate::progress;
+use crate::registry::ToolOutput;
+use crate::secret;
+use anyhow::{anyhow, bail, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::UNIX_EPOCH;
+
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 1024 * 1024;
+pub const DEFAULT_CHUNK_SIZE: usize = 1000;
+pub const DEFAULT_CHUNK_OVERLAP: usize = 200;
+const UPSERT_BATCH_SIZE: usize = 100;
+
+static ALLOWED_ROOTS: OnceLock<Vec<PathBuf>> = OnceLock::new();
+
+const DEFAULT_GLOBS: &[&str] = &[
+    "*.txt", "*.md", "*.markdown", "*.rst", "*.json", "*.jsonl", "*.csv", "*.tsv", "*.toml",
+    "*.yaml", "*.yml", "*.rs", "*.ts", "*.tsx", "*.js", "*.jsx", "*.py", "*.go", "*.java",
+    "*.c", "*.h", "*.cpp", "*.hpp", "*.rb", "*.sh", "*.sql", "*.html", "*.css",
+];
+
+#[derive(Debug, Clone)]
+pub struct IngestOptions {
+    pub path: PathBuf,
+    pub globs: Vec<String>,
+    pub max_file_size: u64,
+    pub chunk_size: usize,
+    pub chunk_overlap: usize,
+    pub code_chunking: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct IngestReport {
+    pub collection_name: String,
+    pub files_scanned: usize,
+    pub files_ingested: usize,
+    pub files_unchanged: usize,
+    pub files_removed: usize,
+    pub chunks_upserted: usize,
+    pub chunks_deleted: usize,
+    pub skipped: Vec<SkippedFile>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SkippedFile {
+    pub path: String,
+    pub reason: String,
+}
+
+struct IndexedFile {
+    content_hash: Option<String>,
+    ids: Vec<String>,
+}
+
+#[derive(Default)]
+struct PendingUpsert {
+    documents: Vec<String>,
+    metadatas: Vec<Value>,
+    ids: Vec<String>,
+}
+
+/// Sets the directories `chroma_ingest_path` may read from. Roots that do
+/// not exist are dropped with a warning.
+pub fn configure(roots: Vec<PathBuf>) {
+    let roots = roots
+        .into_iter()
+        .filter_map(|root| match std::fs::canonicalize(&root) {
+            Ok(root) => Some(root),
+            Err(e) => {
+                tracing::warn!("Ignoring ingest root '{}': {}", root.display(), secret::scrub(&e.to_string()));
+                None
+            }
+        })
+        .collect();
+    if ALLOWED_ROOTS.set(roots).is_err() {
+        tracing::warn!("Ingest roots are already configured");
+    }
+}
+
+/// Fails unless `path` resolves, after following symlinks, inside one of the
+/// `--ingest-root` directories.
+pub fn check_allowed(path: &Path) -> Result<()> {
+    let roots = ALLOWED_ROOTS.get().map(Vec::as_slice).unwrap_or_default();
+    if roots.is_empty() {
+        bail!("Ingesting files from the server is disabled; start the server with --ingest-root to allow a directory");
+    }
+    let resolved = std::fs::canonicalize(path).map_err(|e| anyhow!("Cannot read path '{}': {}", path.display(), e))?;
+    if !roots.iter().any(|root| resolved.starts_with(root)) {
+        bail!("Path '{}' is outside the directories allowed by --ingest-root", path.display());
+    }
+    Ok(())
+}
+
+pub fn ingest_path(collection: &Collection, options: &IngestOptions) -> Result<IngestReport> {
+    let root = std::fs::canonicalize(&options.path)
+        .map_err(|e| anyhow!("Cannot read path '{}': {}", options.path.display(), e))?;
+    let globs = build_globset(&options.globs)?;
+
+    let mut report = IngestReport {
+        collection_name: collection.name.clone(),
+        ..Default::default()
+    };
+    ingest_files(collection, options, &root, &globs, &mut report).map_err(|e| {
+        cancel::partial(e, || {
+            format!(
+                "Cancelled after scanning {} files: {} chunks upserted and {} deleted were committed; \
+                 chunks not yet sent in a batch were discarded and stale chunks of changed files were kept",
+                report.files_scanned, report.chunks_upserted, report.chunks_deleted
+            )
+        })
+    })?;
+    Ok(report)
+}
+
+fn ingest_files(
+    collection: &Collection,
+    options: &IngestOptions,
+    root: &Path,
+    globs: &GlobSet,
+    report: &mut IngestReport,
+) -> Result<()> {
+    let root_key = root.display().to_string();
+    let mut index = load_index(collection, &root_key)?;
+    let mut seen = HashSet::new();
+    // Files and directories that could not be read keep the chunks they
+    // already have, since whether they still exist is unknown.
+    let mut unreadable: Vec<PathBuf> = Vec::new();
+    let mut pending = PendingUpsert::default();
+    // Chunks a changed file no longer has; deleted only once its new chunks
+    // are all written, so a failed or cancelled run never leaves it empty.
+    let mut stale = Vec::new();
+
+    let walker = WalkBuilder::new(root)
+        .standard_filters(true)
+        .require_git(false)
+        .build();
+
+    for entry in walker {
+        cancel::check()?;
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                let path = error_path(&e).unwrap_or(root).to_path_buf();
+                report.skip(&path.display().to_string(), &unreadable_reason(&e));
+                unreadable.push(path);
+                continue;
+            }
+        };
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+
+        let path = entry.path();
+        if !globs.is_match(relative_path(root, path)) {
+            continue;
+        }
+
+        let source_path = path.display().to_string();
+        report.files_scanned += 1;
+
+        let read = entry.metadata().map_err(anyhow::Error::from).and_then(|metadata| {
+            if metadata.len() > options.max_file_size {
+                return Ok(None);
+            }
+            Ok(Some((metadata, std::fs::read(path)?)))
+        });
+        let (metadata, bytes) = match read {
+            Ok(Some(read)) => read,
+            Ok(None) => {
+                report.skip(&source_path, "exceeds max_file_size");
+                continue;
+            }
+            Err(e) => {
+                report.skip(&source_path, &unreadable_reason(&e));
+                unreadable.push(path.to_path_buf());
+                continue;
+            }
+        };
+        if bytes.contains(&0) {
+            report.skip(&source_path, "binary content");
+            continue;
+        }
+        let text = match String::from_utf8(bytes) {
+            Ok(text) => text,
+            Err(_) => {
+                report.skip(&source_path, "not valid UTF-8");
+                continue;
+            }
+        };
+
+        // Only accepted files count as present; chunks of a file that is now
+        // skipped are removed with those of deleted files.
+        seen.insert(source_path.clone());
+
+        let content_hash = hash_content(&text);
+        let previous = index.remove(&source_path);
+        if previous
+            .as_ref()
+            .is_some_and(|file| file.content_hash.as_deref() == Some(content_hash.as_str()))
+        {
+            report.files_unchanged += 1;
+            continue;
+        }
+
+        let mtime = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+
+        let language = Language::from_path(path).filter(|_| options.code_chunking);
+        let chunks = match language {
+            Some(language) => chunk_code(&text, language, options.chunk_size, options.chunk_overlap),
+            None => chunk_text(&text, options.chunk_size, options.chunk_overlap),
+        };
+        let mut chunk_ids = HashSet::new();
+        for (chunk_index, chunk) in chunks.iter().enumerate() {
+            let id = format!("{}#{}", source_path, chunk_index);
+            chunk_ids.insert(id.clone());
+            pending.ids.push(id);
+            pending.documents.push(chunk.text.clone());
+            let mut metadata = json!({
+                "ingest_root": root_key,
+                "source_path": source_path,
+                "mtime": mtime,
+                "content_hash": content_hash,
+                "chunk_index": chunk_index,
+                "chunk_count": chunks.len(),
+                "start_line": chunk.start_line,
+                "end_line": chunk.end_line,
+            });
+            if let Some(language) = language {
+                metadata["language"] = json!(language.as_str());
+            }
+            if let Some(symbol) = &chunk.symbol {
+                metadata["symbol_name"] = json!(symbol.name);
+                metadata["symbol_kind"] = json!(symbol.kind);
+            }
+            pending.metadatas.push(metadata);
+
+            if pending.ids.len() >= UPSERT_BATCH_SIZE {
+                report.chunks_upserted += pending.flush(collection)?;
+                progress::report("ingesting files", report.files_scanned, None);
+            }
+        }
+
+        if let Some(previous) = previous {
+            stale.extend(previous.ids.into_iter().filter(|id| !chunk_ids.contains(id)));
+        }
+        report.files_ingested += 1;
+    }
+    report.chunks_upserted += pending.flush(collection)?;
+    progress::report("ingesting files", report.files_scanned, Some(report.files_scanned));
+
+    cancel::check()?;
+    report.chunks_deleted += delete_ids(collection, stale)?;
+    let removed: Vec<IndexedFile> = index
+        .into_iter()
+        .filter(|(source_path, _)| {
+            !seen.contains(source_path) && !unreadable.iter().any(|path| Path::new(source_path).starts_with(path))
+        })
+        .map(|(_, file)| file)
+        .collect();
+    let total_removed = removed.len();
+    for file in removed {
+        cancel::check()?;
+        report.chunks_deleted += delete_ids(collection, file.ids)?;
+        report.files_removed += 1;
+        progress::report("removing deleted files", report.files_removed, Some(total_removed));
+    }
+
+    Ok(())
+}
+
+impl ToolOutput for IngestReport {
+    fn summary(&self) -> String {
+        format!(
+            "Ingested {} of {} files into '{}' ({} unchanged, {} removed, {} skipped): {} chunks upserted, {} deleted",
+            self.files_ingested,
+            self.files_scanned,
+            self.collection_name,
+            self.files_unchanged,
+            self.files_removed,
+            self.skipped.len(),
+            self.chunks_upserted,
+            self.chunks_deleted
+        )
+    }
+}
+
+impl IngestReport {
+    fn skip(&mut self, path: &str, reason: &str) {
+        self.skipped.push(SkippedFile {
+            path: path.to_string(),
+            reason: reason.to_string(),
+        });
+    }
+}
+
+impl PendingUpsert {
+    fn flush(&mut self, collection: &Collection) -> Result<usize> {
+        if self.ids.is_empty() {
+            return Ok(0);
+        }
+        let count = self.ids.len();
+        collection.upsert(
+            None,
+            std::mem::take(&mut self.documents).into_iter().map(Some).collect(),
+            Some(std::mem::take(&mut self.metadatas).into_iter().map(Some).collect()),
+            std::mem::take(&mut self.ids),
+        )?;
+        Ok(count)
+    }
+}
+
+fn unreadable_reason(error: &dyn std::fmt::Display) -> String {
+    format!("cannot be read: {}", secret::scrub(&error.to_string()))
+}
+
+/// The file or directory a walk error is about, if it names one.
+fn error_path(error: &ignore::Error) -> Option<&Path> {
+    match error {
+        ignore::Error::WithPath { path, .. } => Some(path),
+        ignore::Error::Loop { child, .. } => Some(child),
+        ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => error_path(err),
+        _ => None,
+    }
+}
+
+fn build_globset(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    if patterns.is_empty() {
+        for pattern in DEFAULT_GLOBS {
+            builder.add(Glob::new(pattern)?);
+        }
+    } else {
+        for pattern in patterns {
+            builder.add(Glob::new(pattern).map_err(|e| anyhow!("Invalid glob '{}': {}", pattern, e))?);
+        }
+    }
+    Ok(builder.build()?)
+}
+
+fn relative_path<'a>(root: &Path, path: &'a Path) -> &'a Path {
+    match path.strip_prefix(root) {
+        Ok(relative) if !relative.as_os_str().is_empty() => relative,
+        _ => path.file_name().map(Path::new).unwrap_or(path),
+    }
+}
+
+fn hash_content(text: &str) -> String {
+    Sha256::digest(text.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn load_index(collection: &Collection, root_key: &str) -> Result<HashMap<String, IndexedFile>> {
+    let existing = collection.get(
+        None,
+        Some(json!({"ingest_root": root_key})),
+        None,
+        vec!["metadatas".to_string()],
+        None,
+        None,
+    )?;
+
+    let ids = existing["ids"].as_array().cloned().unwrap_or_default();
+    let metadatas = existing["metadatas"].as_array().cloned().unwrap_or_default();
+
+    let mut index: HashMap<String, IndexedFile> = HashMap::new();
+    for (id, metadata) in ids.iter().zip(metadatas.iter()) {
+        let (Some(id), Some(source_path)) = (id.as_str(), metadata["source_path"].as_str()) else {
+            continue;
+        };
+        let file = index.entry(source_path.to_string()).or_insert(IndexedFile {
+            content_hash: metadata["content_hash"].as_str().map(str::to_string),
+            ids: Vec::new(),
+        });
+        file.ids.push(id.to_string());
+    }
+    Ok(index)
+}
+
+fn delete_ids(collection: &Collection, ids: Vec<String>) -> Result<usize> {
+    if ids.is_empty() {
+        return Ok(0);
+    }
+    let count = ids.len();
+    collection.delete(ids)?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::get_client;
+
+    /// An empty directory under the system temp dir, for the test `name`.
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mcp-chroma-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::canonicalize(dir).unwrap()
+    }
+
+    fn collection(name: &str) -> Collection {
+        let client = get_client();
+        client.create_collection(name, None, None).unwrap();
+        client.get_collection(name).unwrap()
+    }
+
+    fn options(path: &Path, globs: &[&str]) -> IngestOptions {
+        IngestOptions {
+            path: path.to_path_buf(),
+            globs: globs.iter().map(|glob| glob.to_string()).collect(),
+            max_file_size: 64,
+            chunk_size: 8,
+            chunk_overlap: 0,
+            code_chunking: true,
+        }
+    }
+
+    /// Source paths of the stored chunks, relative to `dir`, with their
+    /// chunk counts.
+    fn stored_files(collection: &Collection, dir: &Path) -> Vec<(String, usize)> {
+        let page = collection.get(None, None, None, vec!["metadatas".to_string()], None, None).unwrap();
+        let mut files: Vec<(String, usize)> = Vec::new();
+        for metadata in page["metadatas"].as_array().unwrap() {
+            let path = Path::new(metadata["source_path"].as_str().unwrap());
+            let path = path.strip_prefix(dir).unwrap().display().to_string();
+            match files.iter_mut().find(|(stored, _)| *stored == path) {
+                Some((_, count)) => *count += 1,
+                None => files.push((path, 1)),
+            }
+        }
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn reingest_skips_unchanged_and_cleans_up_changed_and_removed_files() {
+        let dir = scratch_dir("ingest-incremental");
+        std::fs::write(dir.join("a.txt"), "one\ntwo\nthree\nfour\n").unwrap();
+        std::fs::write(dir.join("b.md"), "# b\n").unwrap();
+        let collection = collection("ingest_incremental");
+
+        let first = ingest_path(&collection, &options(&dir, &[])).unwrap();
+        assert_eq!((first.files_ingested, first.chunks_upserted), (2, 4));
+        assert_eq!(stored_files(&collection, &dir), [("a.txt".to_string(), 3), ("b.md".to_string(), 1)]);
+
+        let second = ingest_path(&collection, &options(&dir, &[])).unwrap();
+        assert_eq!((second.files_ingested, second.files_unchanged, second.chunks_upserted), (0, 2, 0));
+
+        std::fs::write(dir.join("a.txt"), "one\n").unwrap();
+        std::fs::remove_file(dir.join("b.md")).unwrap();
+        let third = ingest_path(&collection, &options(&dir, &[])).unwrap();
+        assert_eq!((third.files_ingested, third.files_removed), (1, 1));
+        assert_eq!((third.chunks_upserted, third.chunks_deleted), (1, 3));
+        assert_eq!(stored_files(&collection, &dir), [("a.txt".to_string(), 1)]);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn globs_select_files_relative_to_the_root() {
+        let dir = scratch_dir("ingest-globs");
+        std::fs::create_dir(dir.join("sub")).unwrap();
+        for file in ["a.txt", "a.log", "sub/b.txt", "sub/b.rs"] {
+            std::fs::write(dir.join(file), "text\n").unwrap();
+        }
+
+        let defaults = collection("ingest_globs_default");
+        ingest_path(&defaults, &options(&dir, &[])).unwrap();
+        let files: Vec<String> = stored_files(&defaults, &dir).into_iter().map(|(path, _)| path).collect();
+        assert_eq!(files, ["a.txt", "sub/b.rs", "sub/b.txt"]);
+
+        let selected = collection("ingest_globs_selected");
+        let report = ingest_path(&selected, &options(&dir, &["sub/*.txt"])).unwrap();
+        assert_eq!(report.files_scanned, 1);
+        assert_eq!(stored_files(&selected, &dir), [("sub/b.txt".to_string(), 1)]);
+
+        assert!(ingest_path(&selected, &options(&dir, &["a/[b"])).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn large_binary_and_non_utf8_files_are_skipped() {
+        let dir = scratch_dir("ingest-skips");
+        std::fs::write(dir.join("large.txt"), "x".repeat(65)).unwrap();
+        std::fs::write(dir.join("binary.txt"), b"a\0b").unwrap();
+        std::fs::write(dir.join("latin1.txt"), b"caf\xe9").unwrap();
+        std::fs::write(dir.join("ok.txt"), "ok\n").unwrap();
+        let collection = collection("ingest_skips");
+
+        let report = ingest_path(&collection, &options(&dir, &[])).unwrap();
+        let mut skipped: Vec<(String, &str)> = report
+            .skipped
+            .iter()
+            .map(|file| (Path::new(&file.path).strip_prefix(&dir).unwrap().display().to_string(), file.reason.as_str()))
+            .collect();
+        skipped.sort();
+        assert_eq!(
+            skipped,
+            [
+                ("binary.txt".to_string(), "binary content"),
+                ("large.txt".to_string(), "exceeds max_file_size"),
+                ("latin1.txt".to_string(), "not valid UTF-8"),
+            ]
+        );
+        assert_eq!((report.files_scanned, report.files_ingested), (4, 1));
+
+        // A file that turns binary loses the chunks it had.
+        std::fs::write(dir.join("ok.txt"), b"o\0k").unwrap();
+        let report = ingest_path(&collection, &options(&dir, &[])).unwrap();
+        assert_eq!((report.files_removed, report.chunks_deleted), (1, 1));
+        assert!(stored_files(&collection, &dir).is_empty());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,3 +1,19 @@
+pub mod audit;
+pub mod budget;
+pub mod cancel;
+pub mod chunk;
 pub mod client;
-pub mod tools; 
+pub mod confirm;
+pub mod dump;
+pub mod ingest;
+pub mod pagination;
+pub mod policy;
+pub mod progress;
+pub mod prompts;
+pub mod registry;
+pub mod resources;
+pub mod secret;
+pub mod tools;
+pub mod transport;
+pub mod trash;
 pub mod config;
\ No newline at end of file
@@ -1,89 +1,78 @@
+mod audit;
+mod budget;
+mod cancel;
+mod chunk;
 mod client;
 mod config;
+mod confirm;
+mod dump;
+mod ingest;
+mod pagination;
+mod policy;
+mod progress;
+mod prompts;
+mod registry;
+mod resources;
+mod secret;
 mod tools;
+mod transport;
+mod trash;
 
 use anyhow::Result;
+use audit::ClientIdentity;
 use clap::Parser;
-use config::Config;
-use mcp_server::{router::Router, Server, router::RouterService, ByteTransport};
+use config::{Command, Config, ToolOutputFormat};
+use mcp_server::{router::Router, RouterError};
 use mcp_spec::{
     content::Content,
     handler::{PromptError, ResourceError, ToolError},
     prompt::Prompt,
-    protocol::ServerCapabilities,
-    resource::Resource,
+    protocol::{JsonRpcRequest, JsonRpcResponse, ReadResourceResult, ServerCapabilities},
+    resource::{Resource, ResourceContents},
     tool::Tool,
 };
-use serde::{Deserialize, Serialize};
+use registry::{ToolRegistry, ToolResult, ToolSpec};
 use serde_json::Value;
 use std::future::Future;
 use std::path::Path;
 use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Instant;
 use tokio::io::{stdin, stdout};
 use tracing_subscriber::EnvFilter;
+use transport::TransportKind;
 
 #[derive(Clone)]
-struct ChromaRouter {}
+struct ChromaRouter {
+    registry: Arc<ToolRegistry>,
+    tool_output: ToolOutputFormat,
+}
 
 impl ChromaRouter {
-    fn new(_config: Config) -> Self {
-        Self {}
+    fn new(config: Config) -> Result<Self> {
+        let mut registry = tools::tool_registry();
+        registry.apply_policy(&config.tool_policy())?;
+        registry.retain_groups(&config.enabled_tool_groups());
+        Ok(Self {
+            registry: Arc::new(registry),
+            tool_output: config.tool_output,
+        })
     }
-    
-    async fn call_tool_method<T, R, F, Fut>(&self, args: Value, f: F) -> Result<Value, anyhow::Error> 
-    where
-        T: for<'de> Deserialize<'de>,
-        R: Serialize,
-        F: FnOnce(T) -> Fut,
-        Fut: Future<Output = Result<R>>,
-    {
-        let args = serde_json::from_value(args)?;
-        let result = f(args).await?;
-        serde_json::to_value(result).map_err(Into::into)
+
+    /// Looks up an exposed tool, explaining why a tool hidden by policy
+    /// cannot be called.
+    fn tool(&self, name: &str) -> Result<&ToolSpec, String> {
+        self.registry.get(name).ok_or_else(|| match self.registry.hidden_reason(name) {
+            Some(reason) => format!("Tool '{}' is {}", name, reason),
+            None => format!("Tool '{}' not found", name),
+        })
     }
-    
-    async fn dispatch_method(&self, name: &str, args: Value) -> Result<Value, anyhow::Error> {
-        match name {
-            "chroma_list_collections" => {
-                self.call_tool_method(args, tools::chroma_list_collections).await
-            }
-            "chroma_create_collection" => {
-                self.call_tool_method(args, tools::chroma_create_collection).await
-            }
-            "chroma_peek_collection" => {
-                self.call_tool_method(args, tools::chroma_peek_collection).await
-            }
-            "chroma_get_collection_info" => {
-                self.call_tool_method(args, tools::chroma_get_collection_info).await
-            }
-            "chroma_get_collection_count" => {
-                self.call_tool_method(args, tools::chroma_get_collection_count).await
-            }
-            "chroma_modify_collection" => {
-                self.call_tool_method(args, tools::chroma_modify_collection).await
-            }
-            "chroma_delete_collection" => {
-                self.call_tool_method(args, tools::chroma_delete_collection).await
-            }
-            "chroma_add_documents" => {
-                self.call_tool_method(args, tools::chroma_add_documents).await
-            }
-            "chroma_query_documents" => {
-                self.call_tool_method(args, tools::chroma_query_documents).await
-            }
-            "chroma_get_documents" => {
-                self.call_tool_method(args, tools::chroma_get_documents).await
-            }
-            "chroma_update_documents" => {
-                self.call_tool_method(args, tools::chroma_update_documents).await
-            }
-            "chroma_delete_documents" => {
-                self.call_tool_method(args, tools::chroma_delete_documents).await
-            }
-            "process_thought" => {
-                self.call_tool_method(args, tools::process_thought).await
-            }
-            _ => Err(anyhow::anyhow!("Method not found: {}", name)),
+
+    fn render(&self, result: &ToolResult) -> Result<String, ToolError> {
+        match self.tool_output {
+            ToolOutputFormat::Json => serde_json::to_string_pretty(&result.structured)
+                .map_err(|e| ToolError::ExecutionError(secret::scrub(&e.to_string()))),
+            ToolOutputFormat::Summary => Ok(result.summary.clone()),
         }
     }
 }
@@ -100,11 +89,13 @@ impl Router for ChromaRouter {
     fn capabilities(&self) -> ServerCapabilities {
         mcp_server::router::CapabilitiesBuilder::new()
             .with_tools(true)
+            .with_resources(false, false)
+            .with_prompts(false)
             .build()
     }
 
     fn list_tools(&self) -> Vec<Tool> {
-        tools::get_tool_definitions()
+        self.registry.iter().map(ToolSpec::tool).collect()
     }
 
     fn call_tool(
@@ -112,55 +103,195 @@ impl Router for ChromaRouter {
         tool_name: &str,
         arguments: Value,
     ) -> Pin<Box<dyn Future<Output = Result<Vec<Content>, ToolError>> + Send + 'static>> {
-        let tool_name = tool_name.to_string();
-        
+        let tool = self.tool(tool_name).cloned();
+        let router = self.clone();
+
         Box::pin(async move {
-            let router = ChromaRouter::new(Config::parse());
-            match router.dispatch_method(&tool_name, arguments).await {
-                Ok(value) => {
-                    let json_str = serde_json::to_string_pretty(&value)
-                        .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
-                    Ok(vec![Content::text(json_str)])
-                }
-                Err(err) => Err(ToolError::ExecutionError(err.to_string())),
-            }
+            let tool = tool.map_err(ToolError::NotFound)?;
+            let result = tool
+                .call(arguments)
+                .await
+                .map_err(|err| ToolError::ExecutionError(secret::scrub(&err.to_string())))?;
+            Ok(vec![Content::text(router.render(&result)?)])
         })
     }
 
     fn list_resources(&self) -> Vec<Resource> {
-        vec![]
+        resources::list_collection_resources().unwrap_or_else(|e| {
+            tracing::warn!("Failed to list collection resources: {}", secret::scrub(&e.to_string()));
+            vec![]
+        })
     }
 
     fn read_resource(
         &self,
-        _uri: &str,
+        uri: &str,
     ) -> Pin<Box<dyn Future<Output = Result<String, ResourceError>> + Send + 'static>> {
-        Box::pin(async { Err(ResourceError::NotFound("Resource not found".to_string())) })
+        let uri = uri.to_string();
+        Box::pin(async move { resources::read_resource(&uri).await })
     }
 
     fn list_prompts(&self) -> Vec<Prompt> {
-        vec![]
+        prompts::list_prompts()
     }
 
     fn get_prompt(
         &self,
-        _prompt_name: &str,
+        prompt_name: &str,
     ) -> Pin<Box<dyn Future<Output = Result<String, PromptError>> + Send + 'static>> {
-        Box::pin(async { Err(PromptError::NotFound("Prompt not found".to_string())) })
+        let prompt_name = prompt_name.to_string();
+        Box::pin(async move { prompts::render_prompt_text(&prompt_name).await })
+    }
+
+    async fn handle_tools_list(&self, req: JsonRpcRequest) -> Result<JsonRpcResponse, RouterError> {
+        let tools: Vec<Value> = self.registry.iter().map(ToolSpec::to_json).collect();
+
+        let mut response = self.create_response(req.id);
+        response.result = Some(serde_json::json!({ "tools": tools }));
+        Ok(response)
+    }
+
+    async fn handle_tools_call(&self, req: JsonRpcRequest) -> Result<JsonRpcResponse, RouterError> {
+        let params = req
+            .params
+            .ok_or_else(|| RouterError::InvalidParams("Missing parameters".into()))?;
+        let name = params
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or_else(|| RouterError::InvalidParams("Missing tool name".into()))?;
+        let arguments = params.get("arguments").cloned().unwrap_or(Value::Null);
+        let tool = self.tool(name).map_err(|reason| {
+            audit::record_rejected(name, registry::collection_arguments(&arguments), &arguments, &reason);
+            RouterError::ToolNotFound(reason)
+        })?;
+
+        // Collections are found before the call, which may remove what
+        // they are looked up in.
+        let audited = audit::enabled().then(|| (tool.collections(&arguments), arguments.clone()));
+        let progress_token = progress::progress_token(&params);
+        let started = Instant::now();
+        let call = progress::with_progress(progress_token, tool.call(arguments));
+        let result = cancel::run(call).await;
+        if let Some((collections, arguments)) = audited {
+            audit::record(name, collections, &arguments, result.as_ref().map(|result| &result.structured), started.elapsed());
+        }
+        let result = match result {
+            Ok(result) => {
+                let text = self
+                    .render(&result)
+                    .map_err(|e| RouterError::Internal(secret::scrub(&e.to_string())))?;
+                serde_json::json!({
+                    "content": [Content::text(text)],
+                    "structuredContent": result.structured,
+                    "isError": false,
+                    "_meta": { "elapsed_ms": started.elapsed().as_millis() as u64 },
+                })
+            }
+            Err(err) => serde_json::json!({
+                "content": [Content::text(secret::scrub(&err.to_string()))],
+                "isError": true,
+                "_meta": { "elapsed_ms": started.elapsed().as_millis() as u64 },
+            }),
+        };
+
+        let mut response = self.create_response(req.id);
+        response.result = Some(result);
+        Ok(response)
+    }
+
+    /// Same as the default handler, but labels the contents as JSON rather
+    /// than plain text.
+    async fn handle_resources_read(&self, req: JsonRpcRequest) -> Result<JsonRpcResponse, RouterError> {
+        let params = req
+            .params
+            .ok_or_else(|| RouterError::InvalidParams("Missing parameters".into()))?;
+        let uri = params
+            .get("uri")
+            .and_then(Value::as_str)
+            .ok_or_else(|| RouterError::InvalidParams("Missing resource URI".into()))?;
+
+        let text = resources::read_resource(uri).await.map_err(RouterError::from)?;
+        let result = ReadResourceResult {
+            contents: vec![ResourceContents::TextResourceContents {
+                uri: uri.to_string(),
+                mime_type: Some(resources::MIME_TYPE.to_string()),
+                text,
+            }],
+        };
+
+        let mut response = self.create_response(req.id);
+        response.result = Some(serde_json::to_value(result).map_err(|e| RouterError::Internal(secret::scrub(&e.to_string())))?);
+        Ok(response)
+    }
+
+    async fn handle_prompts_get(&self, req: JsonRpcRequest) -> Result<JsonRpcResponse, RouterError> {
+        let params = req
+            .params
+            .ok_or_else(|| RouterError::InvalidParams("Missing parameters".into()))?;
+        let prompt_name = params
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or_else(|| RouterError::InvalidParams("Missing prompt name".into()))?;
+        let arguments = params
+            .get("arguments")
+            .and_then(Value::as_object)
+            .cloned()
+            .unwrap_or_default();
+
+        let result = prompts::render_prompt(prompt_name, &arguments)
+            .await
+            .map_err(|e| match e {
+                PromptError::NotFound(name) => RouterError::PromptNotFound(format!("Prompt '{}' not found", name)),
+                PromptError::InvalidParameters(msg) => RouterError::InvalidParams(msg),
+                PromptError::InternalError(msg) => RouterError::Internal(secret::scrub(&msg)),
+            })?;
+
+        let mut response = self.create_response(req.id);
+        response.result = Some(
+            serde_json::to_value(result)
+                .map_err(|e| RouterError::Internal(secret::scrub(&format!("JSON serialization error: {}", e))))?,
+        );
+        Ok(response)
     }
 }
 
-async fn run_server(transport: ByteTransport<tokio::io::Stdin, tokio::io::Stdout>, config: Config) -> Result<()> {
-    let router = ChromaRouter::new(config);
-    let router_service = RouterService(router);
-    let server = Server::new(router_service);
-    
-    tracing::info!("Starting MCP server with transport");
-    server.run(transport).await?;
+async fn run_server(config: Config) -> Result<()> {
+    let transport = config.transport();
+    let http_options = config.http_options();
+    #[cfg(unix)]
+    let socket_options = config.socket_options();
+    let router = ChromaRouter::new(config)?;
+
+    match transport {
+        TransportKind::Stdio => {
+            tracing::info!("Starting MCP server with stdio transport");
+            transport::serve_lines(router, stdin(), stdout(), ClientIdentity::shared("stdio", None)).await?;
+        }
+        TransportKind::Sse | TransportKind::Http => {
+            transport::serve(router, transport, http_options).await?;
+        }
+        #[cfg(unix)]
+        TransportKind::Unix => {
+            let options = socket_options.expect("validated: unix transport has a socket path");
+            transport::serve_unix(router, options).await?;
+        }
+        #[cfg(not(unix))]
+        TransportKind::Unix => unreachable!("validated: the unix transport is rejected off Unix"),
+    }
     
     Ok(())
 }
 
+async fn run_command(command: Command) -> Result<()> {
+    let report = match command {
+        Command::Ingest(args) => serde_json::to_value(tools::run_ingest(args.into())?)?,
+        Command::Export(args) => serde_json::to_value(tools::run_export(args.into())?)?,
+        Command::Import(args) => serde_json::to_value(tools::run_import(args.into())?)?,
+    };
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     tracing_subscriber::fmt()
@@ -180,5 +311,19 @@ async fn main() -> Result<()> {
     
     config.validate()?;
     client::initialize_client()?;
-    run_server(ByteTransport::new(stdin(), stdout()), config).await
+    budget::configure(config.output_budget());
+    policy::configure(config.collection_policy());
+    trash::configure(config.trash_options());
+    confirm::configure(config.confirm_options());
+    audit::configure(config.audit_options());
+    ingest::configure(config.ingest_roots.clone());
+    dump::configure(config.export_dir.clone(), config.import_dir.clone());
+
+    if let Some(command) = config.command.clone() {
+        return run_command(command).await;
+    }
+
+    let result = run_server(config).await;
+    audit::flush();
+    result
 }
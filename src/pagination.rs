@@ -0,0 +1,343 @@
+use crate::client::{ChromaClient, Collection};
+use crate::dump::{records_from_page, Record};
+use crate::policy::{self, CollectionPolicy};
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Cursor a client passes to start paging by key rather than by offset.
+pub const START_CURSOR: &str = "start";
+
+/// Where a page starts. `Offset` keeps the backend's own order and is the
+/// default. `After` orders records by id (collections by name) and resumes
+/// after the last key returned, which stays stable under concurrent writes.
+/// The backend cannot filter on `id > key`, so each `After` page lists every
+/// matching key: it buys stability, not speed, and a full scan is quadratic.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Position {
+    Offset(usize),
+    After(Option<String>),
+}
+
+/// Opaque continuation token handed to clients as `next_cursor`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cursor {
+    #[serde(flatten)]
+    pub position: Position,
+    /// Character offset within the first record's document, when a previous
+    /// response cut that document short
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub document_offset: usize,
+}
+
+fn is_zero(value: &usize) -> bool {
+    *value == 0
+}
+
+impl Cursor {
+    pub fn offset(offset: usize) -> Self {
+        Self {
+            position: Position::Offset(offset),
+            document_offset: 0,
+        }
+    }
+
+    pub fn after(key: Option<String>) -> Self {
+        Self {
+            position: Position::After(key),
+            document_offset: 0,
+        }
+    }
+
+    /// Resolves the start of a page from the request's `cursor` and `offset`.
+    /// Pages are offset-based unless the client starts a keyed listing with
+    /// `START_CURSOR` or continues one with its cursor.
+    pub fn resolve(cursor: Option<&str>, offset: Option<usize>) -> Result<Self> {
+        match (cursor, offset) {
+            (Some(_), Some(_)) => Err(anyhow!("Pass either 'cursor' or 'offset', not both")),
+            (Some(START_CURSOR), None) => Ok(Self::after(None)),
+            (Some(cursor), None) => Self::decode(cursor),
+            (None, offset) => Ok(Self::offset(offset.unwrap_or(0))),
+        }
+    }
+
+    /// Cursor for the record `skipped` places after this cursor's start, where
+    /// `previous_key` is the key of the record just before it.
+    pub fn advance(&self, skipped: usize, previous_key: Option<&str>) -> Self {
+        let position = match &self.position {
+            Position::Offset(offset) => Position::Offset(offset + skipped),
+            Position::After(key) => Position::After(previous_key.map(str::to_string).or_else(|| key.clone())),
+        };
+        Self {
+            position,
+            document_offset: 0,
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).expect("cursor serializes"))
+    }
+
+    pub fn decode(token: &str) -> Result<Self> {
+        URL_SAFE_NO_PAD
+            .decode(token.trim())
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .ok_or_else(|| anyhow!("Invalid cursor: {}", token))
+    }
+}
+
+/// Filters applied to every page of a `get` listing.
+pub struct RecordQuery {
+    pub ids: Option<Vec<String>>,
+    pub where_filter: Option<Value>,
+    pub where_document: Option<Value>,
+    pub include: Vec<String>,
+    pub limit: Option<usize>,
+}
+
+/// Fetches one page of records starting at `start`; returns the page and
+/// whether more records follow it.
+pub fn fetch_records(collection: &Collection, query: RecordQuery, start: &Cursor) -> Result<(Vec<Record>, bool)> {
+    match &start.position {
+        Position::Offset(offset) => {
+            let page = collection.get(
+                query.ids,
+                query.where_filter,
+                query.where_document,
+                query.include,
+                query.limit,
+                Some(*offset),
+            )?;
+            let records = records_from_page(&page)?;
+            let has_more = query.limit.is_some_and(|limit| records.len() >= limit);
+            Ok((records, has_more))
+        }
+        Position::After(after) => {
+            let listing = collection.get(
+                query.ids,
+                query.where_filter.clone(),
+                query.where_document.clone(),
+                Vec::new(),
+                None,
+                None,
+            )?;
+            let (ids, has_more) = page_after(page_ids(&listing)?, after.as_deref(), query.limit);
+            if ids.is_empty() {
+                return Ok((Vec::new(), false));
+            }
+
+            let page = collection.get(
+                Some(ids.clone()),
+                query.where_filter,
+                query.where_document,
+                query.include,
+                None,
+                None,
+            )?;
+            let mut records = records_from_page(&page)?;
+            records.retain(|record| ids.binary_search(&record.id).is_ok());
+            records.sort_by(|a, b| a.id.cmp(&b.id));
+            records.dedup_by(|a, b| a.id == b.id);
+            Ok((records, has_more))
+        }
+    }
+}
+
+/// Lists one page of the collection names visible under the collection
+/// policy, starting at `start`, with a cursor for the next page if there is
+/// one.
+pub fn list_collections(client: &ChromaClient, limit: Option<usize>, start: &Cursor) -> Result<(Vec<String>, Option<Cursor>)> {
+    match &start.position {
+        Position::Offset(offset) => {
+            // Hidden collections would shift the backend's offsets, so page
+            // through the visible names here instead.
+            let policy = policy::global_policy();
+            let names = if policy.hides_any() {
+                page_visible(policy, client.list_collections(None, None)?, *offset, limit)
+            } else {
+                client.list_collections(limit, Some(*offset))?
+            };
+            let next = limit
+                .filter(|limit| names.len() >= *limit)
+                .map(|_| start.advance(names.len(), None));
+            Ok((names, next))
+        }
+        Position::After(after) => {
+            let (names, has_more) = page_after(visible(client.list_collections(None, None)?), after.as_deref(), limit);
+            let next = has_more.then(|| start.advance(names.len(), names.last().map(String::as_str)));
+            Ok((names, next))
+        }
+    }
+}
+
+fn visible(names: Vec<String>) -> Vec<String> {
+    policy::global_policy().visible(names)
+}
+
+/// Takes the page at `offset` from the names `policy` leaves visible.
+fn page_visible(policy: &CollectionPolicy, names: Vec<String>, offset: usize, limit: Option<usize>) -> Vec<String> {
+    let names = policy.visible(names).into_iter().skip(offset);
+    match limit {
+        Some(limit) => names.take(limit).collect(),
+        None => names.collect(),
+    }
+}
+
+/// Sorts `keys` and returns up to `limit` of those after `after`, and whether
+/// any remain beyond them.
+fn page_after(mut keys: Vec<String>, after: Option<&str>, limit: Option<usize>) -> (Vec<String>, bool) {
+    keys.sort();
+    keys.dedup();
+    let mut page: Vec<String> = keys
+        .into_iter()
+        .filter(|key| after.is_none_or(|after| key.as_str() > after))
+        .collect();
+    let has_more = limit.is_some_and(|limit| page.len() > limit);
+    if let Some(limit) = limit {
+        page.truncate(limit);
+    }
+    (page, has_more)
+}
+
+fn page_ids(page: &Value) -> Result<Vec<String>> {
+    page["ids"]
+        .as_array()
+        .ok_or_else(|| anyhow!("Malformed get response: missing 'ids'"))?
+        .iter()
+        .map(|id| {
+            id.as_str()
+                .map(str::to_string)
+                .ok_or_else(|| anyhow!("Malformed get response: non-string id"))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(keys: &[&str]) -> Vec<String> {
+        keys.iter().map(|key| key.to_string()).collect()
+    }
+
+    #[test]
+    fn offset_is_the_default() {
+        assert_eq!(Cursor::resolve(None, None).unwrap(), Cursor::offset(0));
+        assert_eq!(Cursor::resolve(None, Some(20)).unwrap(), Cursor::offset(20));
+        assert_eq!(Cursor::resolve(Some(START_CURSOR), None).unwrap(), Cursor::after(None));
+        assert!(Cursor::resolve(Some(START_CURSOR), Some(1)).is_err());
+        assert!(Cursor::resolve(Some("not a cursor"), None).is_err());
+    }
+
+    #[test]
+    fn cursors_round_trip() {
+        let cursors = [
+            Cursor::offset(40),
+            Cursor::after(None),
+            Cursor::after(Some("doc/7".to_string())),
+            Cursor {
+                document_offset: 300,
+                ..Cursor::offset(3)
+            },
+        ];
+        for cursor in cursors {
+            let token = cursor.encode();
+            assert_eq!(Cursor::resolve(Some(&token), None).unwrap(), cursor);
+        }
+    }
+
+    #[test]
+    fn advance_moves_past_the_returned_records() {
+        let offset = Cursor {
+            document_offset: 10,
+            ..Cursor::offset(5)
+        };
+        assert_eq!(offset.advance(3, Some("c")), Cursor::offset(8));
+
+        let keyed = Cursor::after(Some("a".to_string()));
+        assert_eq!(keyed.advance(2, Some("c")), Cursor::after(Some("c".to_string())));
+        assert_eq!(keyed.advance(0, None), keyed);
+    }
+
+    #[test]
+    fn keyed_pages_survive_concurrent_inserts() {
+        let mut stored = keys(&["e", "a", "c", "b", "d"]);
+        let mut start = Cursor::resolve(Some(START_CURSOR), None).unwrap();
+        let mut seen = Vec::new();
+        loop {
+            let Position::After(after) = &start.position else {
+                panic!("keyed cursor became {:?}", start.position);
+            };
+            let (page, has_more) = page_after(stored.clone(), after.as_deref(), Some(2));
+            seen.extend(page.iter().cloned());
+            if !has_more {
+                break;
+            }
+            let token = start.advance(page.len(), page.last().map(String::as_str)).encode();
+            start = Cursor::resolve(Some(&token), None).unwrap();
+            // A record inserted before the cursor must not shift later pages.
+            stored.push("0".to_string());
+        }
+        assert_eq!(seen, keys(&["a", "b", "c", "d", "e"]));
+    }
+
+    #[test]
+    fn keyed_pages_survive_concurrent_inserts_and_deletes() {
+        let mut stored = keys(&["a", "b", "c", "d", "e", "f"]);
+        let mut start = Cursor::after(None);
+        let mut pages = Vec::new();
+        loop {
+            let Position::After(after) = &start.position else {
+                panic!("keyed cursor became {:?}", start.position);
+            };
+            let (page, has_more) = page_after(stored.clone(), after.as_deref(), Some(2));
+            pages.push(page.clone());
+            if !has_more {
+                break;
+            }
+            start = Cursor::decode(&start.advance(page.len(), page.last().map(String::as_str)).encode()).unwrap();
+
+            // Between pages: delete the last key returned and one not reached
+            // yet, and insert one on each side of the cursor.
+            let last = page.last().unwrap().clone();
+            stored.retain(|key| *key != last && key != "e");
+            stored.extend(keys(&["a0", "c0"]));
+        }
+        // Keys deleted before they were reached are gone, keys inserted after
+        // the cursor appear once, and nothing is skipped or repeated.
+        assert_eq!(pages, [keys(&["a", "b"]), keys(&["c", "c0"]), keys(&["d", "f"])]);
+    }
+
+    #[test]
+    fn offset_pages_skip_hidden_collections() {
+        let policy = CollectionPolicy::new(vec!["hidden_*=none".parse().unwrap()], policy::CollectionAccess::Write);
+        let names = keys(&["a", "hidden_1", "b", "c", "hidden_2", "d", "e"]);
+
+        let mut seen = Vec::new();
+        let mut start = Cursor::offset(0);
+        loop {
+            let Position::Offset(offset) = start.position else {
+                panic!("offset cursor became {:?}", start.position);
+            };
+            let page = page_visible(&policy, names.clone(), offset, Some(2));
+            seen.extend(page.iter().cloned());
+            if page.len() < 2 {
+                break;
+            }
+            start = Cursor::decode(&start.advance(page.len(), None).encode()).unwrap();
+        }
+        assert_eq!(seen, keys(&["a", "b", "c", "d", "e"]));
+    }
+
+    #[test]
+    fn page_after_sorts_dedups_and_limits() {
+        let listing = keys(&["b", "a", "b", "c"]);
+        assert_eq!(page_after(listing.clone(), None, None), (keys(&["a", "b", "c"]), false));
+        assert_eq!(page_after(listing.clone(), Some("a"), Some(1)), (keys(&["b"]), true));
+        assert_eq!(page_after(listing, Some("b"), Some(1)), (keys(&["c"]), false));
+    }
+}
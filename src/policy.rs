@@ -0,0 +1,198 @@
+use crate::client;
+use anyhow::{bail, Result};
+use globset::{Glob, GlobMatcher};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+static GLOBAL_POLICY: OnceLock<CollectionPolicy> = OnceLock::new();
+
+/// What a client may do with a collection. Each level includes the ones
+/// before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+pub enum CollectionAccess {
+    /// The collection is hidden and cannot be used
+    None,
+    /// Records can be listed, fetched and queried
+    Read,
+    /// The collection and its records can also be created, changed and deleted
+    Write,
+}
+
+/// One `PATTERN=ACCESS` entry of `--collection-policy`.
+#[derive(Debug, Clone)]
+pub struct CollectionRule {
+    pattern: String,
+    matcher: GlobMatcher,
+    access: CollectionAccess,
+}
+
+impl FromStr for CollectionRule {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (pattern, access) = value
+            .rsplit_once('=')
+            .ok_or_else(|| format!("'{}' is not of the form PATTERN=none|read|write", value))?;
+        let access = <CollectionAccess as clap::ValueEnum>::from_str(access.trim(), true)
+            .map_err(|_| format!("'{}' is not one of none, read or write", access))?;
+        let matcher = Glob::new(pattern.trim())
+            .map_err(|e| format!("Invalid collection pattern '{}': {}", pattern, e))?
+            .compile_matcher();
+        Ok(Self {
+            pattern: pattern.trim().to_string(),
+            matcher,
+            access,
+        })
+    }
+}
+
+/// Access to collections by name: the first rule whose pattern matches
+/// decides, and names no rule matches get the default.
+#[derive(Debug, Clone)]
+pub struct CollectionPolicy {
+    rules: Vec<CollectionRule>,
+    default: CollectionAccess,
+}
+
+impl Default for CollectionPolicy {
+    fn default() -> Self {
+        Self {
+            rules: Vec::new(),
+            default: CollectionAccess::Write,
+        }
+    }
+}
+
+impl CollectionPolicy {
+    pub fn new(rules: Vec<CollectionRule>, default: CollectionAccess) -> Self {
+        Self { rules, default }
+    }
+
+    pub fn access(&self, name: &str) -> CollectionAccess {
+        self.rules
+            .iter()
+            .find(|rule| rule.matcher.is_match(name))
+            .map_or(self.default, |rule| rule.access)
+    }
+
+    /// Whether some collection names are hidden from clients.
+    pub fn hides_any(&self) -> bool {
+        self.default == CollectionAccess::None
+            || self.rules.iter().any(|rule| rule.access == CollectionAccess::None)
+    }
+
+    /// Keeps the names of `names` that are not hidden, in order.
+    pub fn visible(&self, mut names: Vec<String>) -> Vec<String> {
+        names.retain(|name| self.access(name) > CollectionAccess::None);
+        names
+    }
+
+    /// Fails unless `name` has the `needed` access. A hidden collection fails
+    /// exactly like a missing one, so that clients cannot tell it exists.
+    fn check(&self, name: &str, needed: CollectionAccess) -> Result<()> {
+        let access = self.access(name);
+        if access >= needed {
+            return Ok(());
+        }
+        match (access, self.rules.iter().find(|rule| rule.matcher.is_match(name))) {
+            (CollectionAccess::None, _) => Err(client::collection_not_found(name)),
+            (_, Some(rule)) => bail!("Collection '{}' is read-only (policy '{}')", name, rule.pattern),
+            (_, None) => bail!("Collection '{}' is read-only", name),
+        }
+    }
+}
+
+pub fn configure(policy: CollectionPolicy) {
+    if GLOBAL_POLICY.set(policy).is_err() {
+        tracing::warn!("Collection policy is already configured");
+    }
+}
+
+pub fn global_policy() -> &'static CollectionPolicy {
+    GLOBAL_POLICY.get_or_init(unconfigured)
+}
+
+#[cfg(not(test))]
+fn unconfigured() -> CollectionPolicy {
+    CollectionPolicy::default()
+}
+
+/// Unit tests share one process and so one policy: collections named
+/// `hidden_*` are hidden and `readonly_*` ones are read-only.
+#[cfg(test)]
+fn unconfigured() -> CollectionPolicy {
+    let rules = ["hidden_*=none", "readonly_*=read"].iter().map(|rule| rule.parse().unwrap()).collect();
+    CollectionPolicy::new(rules, CollectionAccess::Write)
+}
+
+/// Fails unless the collection `name` may be read.
+pub fn check_read(name: &str) -> Result<()> {
+    global_policy().check(name, CollectionAccess::Read)
+}
+
+/// Fails unless the collection `name` may be written.
+pub fn check_write(name: &str) -> Result<()> {
+    global_policy().check(name, CollectionAccess::Write)
+}
+
+/// Whether the collection `name` is visible to clients.
+pub fn is_visible(name: &str) -> bool {
+    global_policy().access(name) > CollectionAccess::None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(rules: &[&str], default: CollectionAccess) -> CollectionPolicy {
+        let rules = rules.iter().map(|rule| rule.parse().unwrap()).collect();
+        CollectionPolicy::new(rules, default)
+    }
+
+    #[test]
+    fn first_matching_rule_decides() {
+        let narrow_first = policy(&["secret_*=none", "s*=read"], CollectionAccess::Write);
+        assert_eq!(narrow_first.access("secret_keys"), CollectionAccess::None);
+        assert_eq!(narrow_first.access("stats"), CollectionAccess::Read);
+        assert_eq!(narrow_first.access("docs"), CollectionAccess::Write);
+
+        let broad_first = policy(&["s*=read", "secret_*=none"], CollectionAccess::Write);
+        assert_eq!(broad_first.access("secret_keys"), CollectionAccess::Read);
+    }
+
+    #[test]
+    fn rules_parse_on_the_last_equals_sign() {
+        let rule: CollectionRule = "a=b=write".parse().unwrap();
+        assert_eq!(rule.pattern, "a=b");
+        assert_eq!(rule.access, CollectionAccess::Write);
+        assert!("docs".parse::<CollectionRule>().is_err());
+        assert!("docs=admin".parse::<CollectionRule>().is_err());
+    }
+
+    #[test]
+    fn hides_any_when_a_rule_or_the_default_is_none() {
+        assert!(!CollectionPolicy::default().hides_any());
+        assert!(!policy(&["logs_*=read"], CollectionAccess::Write).hides_any());
+        assert!(policy(&["secret_*=none"], CollectionAccess::Write).hides_any());
+        assert!(policy(&["public_*=read"], CollectionAccess::None).hides_any());
+    }
+
+    #[test]
+    fn hidden_collections_look_missing() {
+        let policy = policy(&["secret_*=none", "logs_*=read"], CollectionAccess::Write);
+
+        let error = policy.check("secret_keys", CollectionAccess::Read).unwrap_err();
+        assert_eq!(error.to_string(), client::collection_not_found("secret_keys").to_string());
+
+        assert!(policy.check("logs_2024", CollectionAccess::Read).is_ok());
+        let error = policy.check("logs_2024", CollectionAccess::Write).unwrap_err();
+        assert_eq!(error.to_string(), "Collection 'logs_2024' is read-only (policy 'logs_*')");
+    }
+
+    #[test]
+    fn visible_keeps_order() {
+        let policy = policy(&["secret_*=none"], CollectionAccess::Read);
+        let names = ["b", "secret_a", "a"].map(str::to_string).to_vec();
+        assert_eq!(policy.visible(names), ["b", "a"]);
+    }
+}
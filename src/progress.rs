@@ -0,0 +1,134 @@
+use mcp_spec::protocol::{JsonRpcMessage, JsonRpcNotification};
+use serde_json::{json, Value};
+use std::future::Future;
+use tokio::sync::mpsc;
+
+/// Channel a transport session writes outgoing messages through, so that
+/// notifications sent while a request runs reach the same client.
+pub type Notifier = mpsc::UnboundedSender<JsonRpcMessage>;
+
+tokio::task_local! {
+    static NOTIFIER: Notifier;
+    static REPORTER: ProgressReporter;
+}
+
+#[derive(Clone)]
+struct ProgressReporter {
+    token: Value,
+    notifier: Notifier,
+}
+
+/// Runs `future` with `notifier` as the session's outgoing channel.
+pub async fn with_notifier<F: Future>(notifier: Notifier, future: F) -> F::Output {
+    NOTIFIER.scope(notifier, future).await
+}
+
+/// Runs `future` reporting progress under `token`, when the request carried
+/// one and the transport can deliver notifications.
+pub async fn with_progress<F: Future>(token: Option<Value>, future: F) -> F::Output {
+    let notifier = NOTIFIER.try_with(Clone::clone).ok();
+    match (token, notifier) {
+        (Some(token), Some(notifier)) => REPORTER.scope(ProgressReporter { token, notifier }, future).await,
+        _ => future.await,
+    }
+}
+
+/// Wraps the synchronous `work` so that it reports progress for the current
+/// request wherever it runs, e.g. on a blocking thread.
+pub fn carry<T, F>(work: F) -> impl FnOnce() -> T + Send
+where
+    F: FnOnce() -> T + Send,
+{
+    let reporter = REPORTER.try_with(Clone::clone).ok();
+    move || match reporter {
+        Some(reporter) => REPORTER.sync_scope(reporter, work),
+        None => work(),
+    }
+}
+
+/// Extracts `_meta.progressToken` from the params of a request.
+pub fn progress_token(params: &Value) -> Option<Value> {
+    params
+        .pointer("/_meta/progressToken")
+        .filter(|token| token.is_string() || token.is_number())
+        .cloned()
+}
+
+/// Sends a `notifications/progress` for the running tool call, if its request
+/// asked for progress. `phase` names the step, e.g. "upserting".
+pub fn report(phase: &str, processed: usize, total: Option<usize>) {
+    let _ = REPORTER.try_with(|reporter| {
+        let mut params = json!({
+            "progressToken": reporter.token,
+            "progress": processed,
+            "message": phase,
+        });
+        if let Some(total) = total {
+            params["total"] = json!(total);
+        }
+        let _ = reporter.notifier.send(JsonRpcMessage::Notification(JsonRpcNotification {
+            jsonrpc: "2.0".to_string(),
+            method: "notifications/progress".to_string(),
+            params: Some(params),
+        }));
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs `work` on a blocking thread under a session notifier and
+    /// `token`, and returns the params of the progress notifications it sent.
+    async fn reported(token: Option<Value>, work: impl FnOnce() + Send + 'static) -> Vec<Value> {
+        let (notifier, mut outgoing) = mpsc::unbounded_channel();
+        let call = with_progress(token, async move {
+            let work = carry(work);
+            tokio::task::spawn_blocking(work).await.unwrap();
+        });
+        with_notifier(notifier, call).await;
+
+        let mut sent = Vec::new();
+        while let Ok(message) = outgoing.try_recv() {
+            match message {
+                JsonRpcMessage::Notification(notification) => {
+                    assert_eq!(notification.method, "notifications/progress");
+                    sent.push(notification.params.unwrap());
+                }
+                other => panic!("expected a notification, got {:?}", other),
+            }
+        }
+        sent
+    }
+
+    #[tokio::test]
+    async fn reports_carry_the_token_count_and_phase() {
+        let sent = reported(Some(json!("upload-1")), || {
+            report("adding documents", 100, Some(250));
+            report("scanning", 3, None);
+        })
+        .await;
+        assert_eq!(
+            sent,
+            [
+                json!({"progressToken": "upload-1", "progress": 100, "total": 250, "message": "adding documents"}),
+                json!({"progressToken": "upload-1", "progress": 3, "message": "scanning"}),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn nothing_is_sent_without_a_progress_token() {
+        let sent = reported(None, || report("adding documents", 100, Some(250))).await;
+        assert!(sent.is_empty());
+    }
+
+    #[test]
+    fn progress_tokens_are_strings_or_numbers() {
+        assert_eq!(progress_token(&json!({"_meta": {"progressToken": "abc"}})), Some(json!("abc")));
+        assert_eq!(progress_token(&json!({"_meta": {"progressToken": 7}})), Some(json!(7)));
+        assert_eq!(progress_token(&json!({"_meta": {"progressToken": null}})), None);
+        assert_eq!(progress_token(&json!({"_meta": {"progressToken": {"id": 1}}})), None);
+        assert_eq!(progress_token(&json!({"name": "chroma_add_documents"})), None);
+    }
+}
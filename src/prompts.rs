@@ -0,0 +1,274 @@
+use crate::client::get_client;
+use crate::dump::records_from_page;
+use crate::policy;
+use crate::secret;
+use mcp_spec::handler::PromptError;
+use mcp_spec::prompt::{Prompt, PromptArgument, PromptMessage, PromptMessageContent, PromptMessageRole};
+use mcp_spec::protocol::GetPromptResult;
+use serde_json::{Map, Value};
+use std::fmt::Write;
+
+const USAGE_GUIDANCE: &str = include_str!("../PROMPT.md");
+
+const CHROMA_USAGE: &str = "chroma_usage";
+const ANSWER_WITH_CITATIONS: &str = "chroma_answer_with_citations";
+const SUMMARIZE_COLLECTION: &str = "chroma_summarize_collection";
+
+const DEFAULT_N_RESULTS: usize = 5;
+const DEFAULT_SAMPLE_SIZE: usize = 20;
+
+pub fn list_prompts() -> Vec<Prompt> {
+    vec![
+        Prompt::new(
+            CHROMA_USAGE,
+            Some("Guidance on when and how to use the ChromaDB tools"),
+            None,
+        ),
+        Prompt::new(
+            ANSWER_WITH_CITATIONS,
+            Some("Answer a question from the documents of a collection, citing the records used"),
+            Some(vec![
+                argument("collection_name", "Collection to retrieve context from", true),
+                argument("question", "Question to answer", true),
+                argument("n_results", "Number of records to retrieve (default 5)", false),
+            ]),
+        ),
+        Prompt::new(
+            SUMMARIZE_COLLECTION,
+            Some("Summarize the contents of a collection from a sample of its records"),
+            Some(vec![
+                argument("collection_name", "Collection to summarize", true),
+                argument("focus", "Aspect the summary should concentrate on", false),
+                argument("sample_size", "Number of records to sample (default 20)", false),
+            ]),
+        ),
+    ]
+}
+
+/// Renders the prompt `name` without arguments, as text, for callers that
+/// cannot pass any; prompts with required arguments fail like in
+/// `render_prompt`.
+pub async fn render_prompt_text(name: &str) -> Result<String, PromptError> {
+    let rendered = render_prompt(name, &Map::new()).await?;
+    let texts: Vec<String> = rendered
+        .messages
+        .into_iter()
+        .filter_map(|message| match message.content {
+            PromptMessageContent::Text { text } => Some(text),
+            _ => None,
+        })
+        .collect();
+    Ok(texts.join("\n\n"))
+}
+
+pub async fn render_prompt(name: &str, arguments: &Map<String, Value>) -> Result<GetPromptResult, PromptError> {
+    let prompt = list_prompts()
+        .into_iter()
+        .find(|prompt| prompt.name == name)
+        .ok_or_else(|| PromptError::NotFound(name.to_string()))?;
+
+    for required in prompt.arguments.iter().flatten().filter(|arg| arg.required == Some(true)) {
+        if string_argument(arguments, &required.name).is_none() {
+            return Err(PromptError::InvalidParameters(format!(
+                "Missing required argument: '{}'",
+                required.name
+            )));
+        }
+    }
+
+    let text = match name {
+        CHROMA_USAGE => USAGE_GUIDANCE.to_string(),
+        ANSWER_WITH_CITATIONS => render_answer_with_citations(arguments)?,
+        SUMMARIZE_COLLECTION => render_summarize_collection(arguments)?,
+        _ => return Err(PromptError::NotFound(name.to_string())),
+    };
+
+    Ok(GetPromptResult {
+        description: prompt.description,
+        messages: vec![PromptMessage::new_text(PromptMessageRole::User, text)],
+    })
+}
+
+fn render_answer_with_citations(arguments: &Map<String, Value>) -> Result<String, PromptError> {
+    let collection_name = string_argument(arguments, "collection_name").unwrap_or_default();
+    let question = string_argument(arguments, "question").unwrap_or_default();
+    let n_results = usize_argument(arguments, "n_results")?.unwrap_or(DEFAULT_N_RESULTS);
+
+    policy::check_read(collection_name).map_err(internal)?;
+    let client = get_client();
+    let collection = client.get_collection(collection_name).map_err(internal)?;
+    let results = collection
+        .query(
+            vec![question.to_string()],
+            n_results,
+            None,
+            None,
+            vec!["documents".to_string(), "metadatas".to_string(), "distances".to_string()],
+        )
+        .map_err(internal)?;
+
+    let ids = results["ids"][0].as_array().cloned().unwrap_or_default();
+    let mut context = String::new();
+    for (index, id) in ids.iter().enumerate() {
+        let document = results["documents"][0][index].as_str().unwrap_or_default();
+        let metadata = &results["metadatas"][0][index];
+        let _ = write!(context, "[{}] id: {}", index + 1, id.as_str().unwrap_or_default());
+        if metadata.is_object() {
+            let _ = write!(context, ", metadata: {}", metadata);
+        }
+        let _ = writeln!(context, "\n{}\n", document.trim());
+    }
+    if context.is_empty() {
+        context.push_str("(no matching records were found)\n");
+    }
+
+    Ok(format!(
+        "Answer the question below using only the context retrieved from the ChromaDB collection '{collection_name}'.\n\
+         Cite every statement with the bracketed number of the record it comes from, e.g. [1]. \
+         If the context does not contain the answer, say so instead of guessing.\n\n\
+         Question: {question}\n\n\
+         Context:\n{context}"
+    ))
+}
+
+fn render_summarize_collection(arguments: &Map<String, Value>) -> Result<String, PromptError> {
+    let collection_name = string_argument(arguments, "collection_name").unwrap_or_default();
+    let focus = string_argument(arguments, "focus");
+    let sample_size = usize_argument(arguments, "sample_size")?.unwrap_or(DEFAULT_SAMPLE_SIZE);
+
+    policy::check_read(collection_name).map_err(internal)?;
+    let client = get_client();
+    let collection = client.get_collection(collection_name).map_err(internal)?;
+    let count = collection.count().map_err(internal)?;
+    let sample = records_from_page(&collection.peek(sample_size).map_err(internal)?).map_err(internal)?;
+
+    let mut text = format!(
+        "Summarize the contents of the ChromaDB collection '{}', which holds {} records. \
+         Describe the main topics, the kinds of documents and any notable metadata fields.",
+        collection_name, count
+    );
+    if let Some(focus) = focus {
+        let _ = write!(text, " Concentrate on: {}.", focus);
+    }
+    let _ = writeln!(text, "\n\nSample of {} records:", sample.len());
+    for record in sample {
+        let _ = write!(text, "\n- id: {}", record.id);
+        if let Some(metadata) = record.metadata {
+            let _ = write!(text, ", metadata: {}", metadata);
+        }
+        let _ = writeln!(text, "\n  {}", record.document.unwrap_or_default().trim());
+    }
+    Ok(text)
+}
+
+fn argument(name: &str, description: &str, required: bool) -> PromptArgument {
+    PromptArgument {
+        name: name.to_string(),
+        description: Some(description.to_string()),
+        required: Some(required),
+    }
+}
+
+fn string_argument<'a>(arguments: &'a Map<String, Value>, name: &str) -> Option<&'a str> {
+    arguments
+        .get(name)
+        .and_then(Value::as_str)
+        .filter(|value| !value.trim().is_empty())
+}
+
+fn usize_argument(arguments: &Map<String, Value>, name: &str) -> Result<Option<usize>, PromptError> {
+    match arguments.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Number(number)) => number
+            .as_u64()
+            .map(|n| Some(n as usize))
+            .ok_or_else(|| PromptError::InvalidParameters(format!("'{}' must be a positive integer", name))),
+        Some(Value::String(value)) => value
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| PromptError::InvalidParameters(format!("'{}' must be a positive integer", name))),
+        Some(_) => Err(PromptError::InvalidParameters(format!("'{}' must be a positive integer", name))),
+    }
+}
+
+fn internal(error: anyhow::Error) -> PromptError {
+    PromptError::InternalError(secret::scrub(&error.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client;
+    use serde_json::json;
+
+    fn arguments(value: Value) -> Map<String, Value> {
+        value.as_object().cloned().unwrap()
+    }
+
+    #[tokio::test]
+    async fn hidden_collections_look_missing() {
+        for (name, arguments) in [
+            (ANSWER_WITH_CITATIONS, arguments(json!({"collection_name": "hidden_notes", "question": "why?"}))),
+            (SUMMARIZE_COLLECTION, arguments(json!({"collection_name": "hidden_notes"}))),
+        ] {
+            match render_prompt(name, &arguments).await {
+                Err(PromptError::InternalError(message)) => {
+                    assert_eq!(message, client::collection_not_found("hidden_notes").to_string())
+                }
+                other => panic!("{} rendered a hidden collection: {:?}", name, other),
+            }
+        }
+    }
+
+    fn text(result: GetPromptResult) -> String {
+        match result.messages.into_iter().next().map(|message| message.content) {
+            Some(PromptMessageContent::Text { text }) => text,
+            other => panic!("expected a text message, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn answer_prompts_number_the_retrieved_records() {
+        let arguments = arguments(json!({"collection_name": "notes", "question": "What is in doc1?", "n_results": "2"}));
+        let text = text(render_prompt(ANSWER_WITH_CITATIONS, &arguments).await.unwrap());
+
+        assert!(text.contains("collection 'notes'"), "{}", text);
+        assert!(text.contains("Question: What is in doc1?"), "{}", text);
+        assert!(text.contains("[1] id: doc1") && text.contains("[2] id: doc2"), "{}", text);
+    }
+
+    #[tokio::test]
+    async fn summary_prompts_sample_the_collection() {
+        let client = client::get_client();
+        client.create_collection("prompt_summary", None, None).unwrap();
+        let collection = client.get_collection("prompt_summary").unwrap();
+        let ids: Vec<String> = ["a", "b", "c"].map(str::to_string).to_vec();
+        let documents = ids.iter().map(|id| format!("text of {}", id)).collect();
+        collection.add(documents, Some(vec![json!({"kind": "note"}); 3]), ids).unwrap();
+
+        let arguments = arguments(json!({"collection_name": "prompt_summary", "focus": "kinds", "sample_size": 2}));
+        let text = text(render_prompt(SUMMARIZE_COLLECTION, &arguments).await.unwrap());
+        assert!(text.contains("'prompt_summary', which holds 3 records"), "{}", text);
+        assert!(text.contains("Concentrate on: kinds."), "{}", text);
+        assert!(text.contains("Sample of 2 records:"), "{}", text);
+        assert!(text.contains("- id: a, metadata: {\"kind\":\"note\"}\n  text of a"), "{}", text);
+    }
+
+    #[tokio::test]
+    async fn missing_required_arguments_are_invalid() {
+        let missing = render_prompt(ANSWER_WITH_CITATIONS, &arguments(json!({"collection_name": "notes", "question": " "}))).await;
+        assert!(matches!(missing, Err(PromptError::InvalidParameters(message)) if message == "Missing required argument: 'question'"));
+
+        let bad_count = arguments(json!({"collection_name": "notes", "sample_size": -1}));
+        assert!(matches!(render_prompt(SUMMARIZE_COLLECTION, &bad_count).await, Err(PromptError::InvalidParameters(_))));
+        assert!(matches!(render_prompt("chroma_unknown", &Map::new()).await, Err(PromptError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn prompt_text_is_rendered_rather_than_a_template() {
+        assert_eq!(render_prompt_text(CHROMA_USAGE).await.unwrap(), USAGE_GUIDANCE);
+        let missing = render_prompt_text(SUMMARIZE_COLLECTION).await;
+        assert!(matches!(missing, Err(PromptError::InvalidParameters(message)) if message.contains("'collection_name'")));
+    }
+}
@@ -0,0 +1,332 @@
+use anyhow::{bail, Result};
+use mcp_spec::tool::Tool;
+use schemars::r#gen::SchemaSettings;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+pub type ToolFuture = Pin<Box<dyn Future<Output = Result<ToolResult>> + Send>>;
+type ToolHandler = Arc<dyn Fn(Value) -> ToolFuture + Send + Sync>;
+/// Names of the collections a call reads or changes, taken from its arguments.
+pub type CollectionsOf = fn(&Value) -> Vec<String>;
+
+/// Arguments that name a collection in most tools.
+const COLLECTION_ARGUMENTS: [&str; 4] = ["collection_name", "new_name", "source_collection", "target_collection"];
+
+pub trait ToolOutput: Serialize {
+    fn summary(&self) -> String;
+}
+
+#[derive(Debug, Clone)]
+pub struct ToolResult {
+    pub structured: Value,
+    pub summary: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolGroup {
+    Collections,
+    Documents,
+    Ingest,
+    Backup,
+    Trash,
+    Audit,
+    Thoughts,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolAnnotations {
+    pub read_only_hint: bool,
+    pub destructive_hint: bool,
+    pub idempotent_hint: bool,
+    pub open_world_hint: bool,
+}
+
+impl ToolAnnotations {
+    pub const READ_ONLY: Self = Self {
+        read_only_hint: true,
+        destructive_hint: false,
+        idempotent_hint: true,
+        open_world_hint: false,
+    };
+
+    pub const ADDITIVE: Self = Self {
+        read_only_hint: false,
+        destructive_hint: false,
+        idempotent_hint: false,
+        open_world_hint: false,
+    };
+
+    pub const DESTRUCTIVE: Self = Self {
+        read_only_hint: false,
+        destructive_hint: true,
+        idempotent_hint: false,
+        open_world_hint: false,
+    };
+
+    /// Destructive, but repeating a call with the same arguments changes
+    /// nothing more, as with overwriting a value.
+    pub const DESTRUCTIVE_IDEMPOTENT: Self = Self {
+        read_only_hint: false,
+        destructive_hint: true,
+        idempotent_hint: true,
+        open_world_hint: false,
+    };
+}
+
+#[derive(Clone)]
+pub struct ToolSpec {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub group: ToolGroup,
+    pub annotations: ToolAnnotations,
+    pub input_schema: Value,
+    handler: ToolHandler,
+    collections: CollectionsOf,
+}
+
+impl ToolSpec {
+    pub fn new<T, R, F, Fut>(
+        name: &'static str,
+        description: &'static str,
+        group: ToolGroup,
+        annotations: ToolAnnotations,
+        handler: F,
+    ) -> Self
+    where
+        T: DeserializeOwned + JsonSchema,
+        R: ToolOutput,
+        F: Fn(T) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<R>> + Send + 'static,
+    {
+        let handler: ToolHandler = Arc::new(move |arguments| match serde_json::from_value::<T>(arguments) {
+            Ok(request) => {
+                let call = handler(request);
+                Box::pin(async move {
+                    let output = call.await?;
+                    Ok(ToolResult {
+                        structured: serde_json::to_value(&output)?,
+                        summary: output.summary(),
+                    })
+                })
+            }
+            Err(e) => Box::pin(async move { Err(e.into()) }),
+        });
+
+        Self {
+            name,
+            description,
+            group,
+            annotations,
+            input_schema: schema_for::<T>(),
+            handler,
+            collections: collection_arguments,
+        }
+    }
+
+    /// Replaces how the collections of a call are found, for tools that do
+    /// not name them in their arguments.
+    pub fn with_collections(mut self, collections: CollectionsOf) -> Self {
+        self.collections = collections;
+        self
+    }
+
+    /// Collections a call with `arguments` reads or changes.
+    pub fn collections(&self, arguments: &Value) -> Vec<String> {
+        (self.collections)(arguments)
+    }
+
+    pub fn tool(&self) -> Tool {
+        Tool::new(self.name, self.description, self.input_schema.clone())
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "description": self.description,
+            "inputSchema": self.input_schema,
+            "annotations": self.annotations,
+        })
+    }
+
+    pub fn call(&self, arguments: Value) -> ToolFuture {
+        (self.handler)(arguments)
+    }
+}
+
+/// Limits on which registered tools are exposed, on top of tool groups.
+#[derive(Debug, Clone, Default)]
+pub struct ToolPolicy {
+    /// Hide every tool that is not annotated read-only
+    pub read_only: bool,
+    /// Tools to expose; empty exposes all
+    pub allow: Vec<String>,
+    /// Tools to hide, applied after `allow`
+    pub deny: Vec<String>,
+}
+
+impl ToolPolicy {
+    /// Why `spec` is hidden under this policy, if it is.
+    fn exclusion(&self, spec: &ToolSpec) -> Option<&'static str> {
+        if self.read_only && !spec.annotations.read_only_hint {
+            Some("disabled in read-only mode")
+        } else if !self.allow.is_empty() && !self.allow.iter().any(|name| name == spec.name) {
+            Some("not in --allow-tools")
+        } else if self.deny.iter().any(|name| name == spec.name) {
+            Some("listed in --deny-tools")
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct ToolRegistry {
+    tools: Vec<ToolSpec>,
+    hidden: Vec<(&'static str, &'static str)>,
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, spec: ToolSpec) -> &mut Self {
+        assert!(self.get(spec.name).is_none(), "tool '{}' is registered twice", spec.name);
+        self.tools.push(spec);
+        self
+    }
+
+    pub fn retain_groups(&mut self, enabled: &HashSet<ToolGroup>) {
+        self.tools.retain(|spec| enabled.contains(&spec.group));
+    }
+
+    /// Hides the tools `policy` excludes, so that they are neither listed
+    /// nor callable. Fails on names in the policy that match no tool.
+    pub fn apply_policy(&mut self, policy: &ToolPolicy) -> Result<()> {
+        for (flag, names) in [("--allow-tools", &policy.allow), ("--deny-tools", &policy.deny)] {
+            if let Some(name) = names.iter().find(|name| self.get(name).is_none()) {
+                bail!("Unknown tool '{}' in {}", name, flag);
+            }
+        }
+
+        for spec in std::mem::take(&mut self.tools) {
+            match policy.exclusion(&spec) {
+                Some(reason) => self.hidden.push((spec.name, reason)),
+                None => self.tools.push(spec),
+            }
+        }
+        Ok(())
+    }
+
+    /// Why the tool `name` is registered but hidden by policy, if it is.
+    pub fn hidden_reason(&self, name: &str) -> Option<&'static str> {
+        self.hidden
+            .iter()
+            .find(|(hidden, _)| *hidden == name)
+            .map(|(_, reason)| *reason)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ToolSpec> {
+        self.tools.iter().find(|spec| spec.name == name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ToolSpec> {
+        self.tools.iter()
+    }
+}
+
+/// Collections named by the usual collection arguments, in argument order.
+pub fn collection_arguments(arguments: &Value) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for name in COLLECTION_ARGUMENTS.iter().filter_map(|key| arguments.get(key)).filter_map(Value::as_str) {
+        if !names.iter().any(|seen| seen == name) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+pub fn schema_for<T: JsonSchema>() -> Value {
+    let generator = SchemaSettings::draft07()
+        .with(|settings| {
+            settings.inline_subschemas = true;
+            settings.meta_schema = None;
+        })
+        .into_generator();
+    serde_json::to_value(generator.into_root_schema_for::<T>()).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::tool_registry;
+
+    fn names(registry: &ToolRegistry) -> Vec<&'static str> {
+        registry.iter().map(|spec| spec.name).collect()
+    }
+
+    fn filtered(policy: ToolPolicy) -> ToolRegistry {
+        let mut registry = tool_registry();
+        registry.apply_policy(&policy).unwrap();
+        registry
+    }
+
+    #[test]
+    fn read_only_mode_keeps_only_read_only_tools() {
+        let registry = filtered(ToolPolicy {
+            read_only: true,
+            ..ToolPolicy::default()
+        });
+
+        assert!(registry.iter().all(|spec| spec.annotations == ToolAnnotations::READ_ONLY));
+        assert!(names(&registry).contains(&"chroma_query_documents"));
+        for hidden in ["chroma_add_documents", "chroma_delete_collection", "chroma_export_collection", "chroma_restore"] {
+            assert!(registry.get(hidden).is_none(), "{} is listed", hidden);
+            assert_eq!(registry.hidden_reason(hidden), Some("disabled in read-only mode"));
+        }
+    }
+
+    #[test]
+    fn allow_and_deny_lists_pick_tools_by_name() {
+        let registry = filtered(ToolPolicy {
+            allow: vec!["chroma_list_collections".to_string(), "chroma_delete_collection".to_string()],
+            deny: vec!["chroma_delete_collection".to_string()],
+            ..ToolPolicy::default()
+        });
+
+        assert_eq!(names(&registry), ["chroma_list_collections"]);
+        assert_eq!(registry.hidden_reason("chroma_delete_collection"), Some("listed in --deny-tools"));
+        assert_eq!(registry.hidden_reason("chroma_add_documents"), Some("not in --allow-tools"));
+        assert_eq!(registry.hidden_reason("chroma_list_collections"), None);
+    }
+
+    #[test]
+    fn unknown_tool_names_are_rejected() {
+        let error = tool_registry()
+            .apply_policy(&ToolPolicy {
+                deny: vec!["chroma_drop_everything".to_string()],
+                ..ToolPolicy::default()
+            })
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Unknown tool 'chroma_drop_everything' in --deny-tools");
+    }
+
+    #[test]
+    fn only_overwriting_writes_are_idempotent() {
+        let registry = tool_registry();
+        let idempotent: Vec<&str> = registry
+            .iter()
+            .filter(|spec| !spec.annotations.read_only_hint && spec.annotations.idempotent_hint)
+            .map(|spec| spec.name)
+            .collect();
+        assert_eq!(idempotent, ["chroma_update_documents", "chroma_patch_metadata"]);
+    }
+}
@@ -0,0 +1,201 @@
+use crate::client::get_client;
+use crate::dump::records_from_page;
+use crate::policy;
+use crate::secret;
+use anyhow::Result;
+use mcp_spec::handler::ResourceError;
+use mcp_spec::resource::Resource;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde_json::{json, Value};
+
+pub const COLLECTIONS_URI_PREFIX: &str = "chroma://collections/";
+/// Every resource is a JSON document.
+pub const MIME_TYPE: &str = "application/json";
+const SAMPLE_SIZE: usize = 5;
+/// Characters escaped in a URI path segment: all but the unreserved ones.
+const SEGMENT: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'_').remove(b'.').remove(b'~');
+
+enum ResourceUri {
+    Collection(String),
+    Record { collection: String, id: String },
+}
+
+pub fn collection_uri(name: &str) -> String {
+    format!("{}{}", COLLECTIONS_URI_PREFIX, utf8_percent_encode(name, SEGMENT))
+}
+
+pub fn record_uri(collection: &str, id: &str) -> String {
+    format!("{}/records/{}", collection_uri(collection), utf8_percent_encode(id, SEGMENT))
+}
+
+pub fn list_collection_resources() -> Result<Vec<Resource>> {
+    let client = get_client();
+    client
+        .list_collections(None, None)?
+        .into_iter()
+        .filter(|name| policy::is_visible(name))
+        .map(|name| {
+            let mut resource = Resource::new(collection_uri(&name), None, Some(name.clone()))?;
+            // `Resource::new` only accepts the "text" and "blob" mime types.
+            resource.mime_type = MIME_TYPE.to_string();
+            resource.description = Some(format!(
+                "Configuration, record count and sample records of collection '{}'",
+                name
+            ));
+            Ok(resource)
+        })
+        .collect()
+}
+
+pub async fn read_resource(uri: &str) -> Result<String, ResourceError> {
+    let parsed = parse_uri(uri)
+        .ok_or_else(|| ResourceError::NotFound(format!("unknown URI {}", uri)))?;
+
+    let content = match parsed {
+        ResourceUri::Collection(name) => read_collection(&name),
+        ResourceUri::Record { collection, id } => read_record(&collection, &id),
+    }
+    .map_err(|e| ResourceError::ExecutionError(secret::scrub(&e.to_string())))?
+    .ok_or_else(|| ResourceError::NotFound(uri.to_string()))?;
+
+    serde_json::to_string_pretty(&content).map_err(|e| ResourceError::ExecutionError(secret::scrub(&e.to_string())))
+}
+
+fn parse_uri(uri: &str) -> Option<ResourceUri> {
+    let path = uri.strip_prefix(COLLECTIONS_URI_PREFIX)?;
+    let decode = |part: &str| percent_decode_str(part).decode_utf8().ok().map(|s| s.into_owned());
+
+    match path.split_once('/') {
+        None if !path.is_empty() => Some(ResourceUri::Collection(decode(path)?)),
+        Some((collection, rest)) => {
+            let id = rest.strip_prefix("records/").filter(|id| !id.is_empty())?;
+            Some(ResourceUri::Record {
+                collection: decode(collection)?,
+                id: decode(id)?,
+            })
+        }
+        None => None,
+    }
+}
+
+fn read_collection(name: &str) -> Result<Option<Value>> {
+    if !policy::is_visible(name) {
+        return Ok(None);
+    }
+    let client = get_client();
+    if !client.list_collections(None, None)?.iter().any(|c| c == name) {
+        return Ok(None);
+    }
+
+    let collection = client.get_collection(name)?;
+    let sample = records_from_page(&collection.peek(SAMPLE_SIZE)?)?;
+    let sample: Vec<Value> = sample
+        .into_iter()
+        .map(|record| {
+            json!({
+                "uri": record_uri(name, &record.id),
+                "id": record.id,
+                "document": record.document,
+                "metadata": record.metadata,
+            })
+        })
+        .collect();
+
+    Ok(Some(json!({
+        "name": name,
+        "metadata": collection.metadata()?,
+        "configuration": collection.configuration()?,
+        "count": collection.count()?,
+        "sample": sample,
+    })))
+}
+
+fn read_record(collection_name: &str, id: &str) -> Result<Option<Value>> {
+    if !policy::is_visible(collection_name) {
+        return Ok(None);
+    }
+    let client = get_client();
+    let collection = client.get_collection(collection_name)?;
+    let page = collection.get(
+        Some(vec![id.to_string()]),
+        None,
+        None,
+        vec!["documents".to_string(), "metadatas".to_string()],
+        None,
+        None,
+    )?;
+
+    let record = records_from_page(&page)?
+        .into_iter()
+        .find(|record| record.id == id);
+    Ok(record.map(|record| {
+        json!({
+            "collection": collection_name,
+            "id": record.id,
+            "document": record.document,
+            "metadata": record.metadata,
+        })
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stored(name: &str, ids: &[&str]) {
+        let client = get_client();
+        client.create_collection(name, None, None).unwrap();
+        let ids: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+        let documents = ids.iter().map(|id| format!("text of {}", id)).collect();
+        client.get_collection(name).unwrap().add(documents, None, ids).unwrap();
+    }
+
+    async fn read(uri: &str) -> Result<Value, ResourceError> {
+        let content = read_resource(uri).await?;
+        Ok(serde_json::from_str(&content).unwrap())
+    }
+
+    #[test]
+    fn uris_escape_names_and_ids() {
+        assert_eq!(collection_uri("my notes/ü"), "chroma://collections/my%20notes%2F%C3%BC");
+        assert_eq!(record_uri("a-b_c.d~", "x?y#z"), "chroma://collections/a-b_c.d~/records/x%3Fy%23z");
+
+        let Some(ResourceUri::Record { collection, id }) = parse_uri(&record_uri("my notes/ü", "a/b c")) else {
+            panic!("expected a record URI");
+        };
+        assert_eq!((collection.as_str(), id.as_str()), ("my notes/ü", "a/b c"));
+        for uri in [
+            "chroma://collections/",
+            "chroma://collections/notes/records/",
+            "chroma://collections/notes/other/x",
+            "chroma://collections/%FF",
+            "file:///etc/passwd",
+        ] {
+            assert!(parse_uri(uri).is_none(), "{} parsed", uri);
+        }
+    }
+
+    #[tokio::test]
+    async fn collections_and_records_are_read_through_their_uris() {
+        stored("resource notes", &["a/b", "c"]);
+
+        let collection = read(&collection_uri("resource notes")).await.unwrap();
+        assert_eq!(collection["count"], 2);
+        let record = collection["sample"][0]["uri"].as_str().unwrap();
+        assert_eq!(record, "chroma://collections/resource%20notes/records/a%2Fb");
+        assert_eq!(read(record).await.unwrap()["document"], "text of a/b");
+        assert!(matches!(read(&record_uri("resource notes", "missing")).await, Err(ResourceError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn hidden_collections_are_neither_listed_nor_read() {
+        stored("hidden_resource", &["a"]);
+        stored("resource_visible", &["a"]);
+
+        let names: Vec<String> = list_collection_resources().unwrap().into_iter().map(|resource| resource.name).collect();
+        assert!(names.contains(&"resource_visible".to_string()));
+        assert!(!names.contains(&"hidden_resource".to_string()));
+        assert!(matches!(read(&collection_uri("hidden_resource")).await, Err(ResourceError::NotFound(_))));
+        assert!(matches!(read(&record_uri("hidden_resource", "a")).await, Err(ResourceError::NotFound(_))));
+    }
+}
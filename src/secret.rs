@@ -0,0 +1,195 @@
+use std::convert::Infallible;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+/// Headers and query parameters whose values are masked by `scrub`.
+const SENSITIVE_NAMES: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "x-chroma-token",
+    "x-api-key",
+    "api_key",
+    "access_token",
+];
+/// Auth schemes whose credential, the word after them, is masked by `scrub`.
+/// `Basic` is left out, as it is also an ordinary word.
+const SENSITIVE_SCHEMES: &[&str] = &["bearer "];
+const MASK: &str = "***";
+/// Shorter secrets are not masked in free text, where they would match
+/// ordinary words.
+const MIN_SCRUBBED_LEN: usize = 4;
+
+static KNOWN: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// A credential that prints as `***` in `Debug` and `Display` output. Every
+/// secret is remembered so that `scrub` can mask it wherever it appears.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        let value = value.into();
+        if value.len() >= MIN_SCRUBBED_LEN {
+            let mut known = KNOWN.lock().unwrap();
+            if !known.contains(&value) {
+                known.push(value.clone());
+            }
+        }
+        Self(value)
+    }
+
+    /// The credential itself, for sending it to the backend or comparing it.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(MASK)
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(MASK)
+    }
+}
+
+impl FromStr for Secret {
+    type Err = Infallible;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Ok(Self::new(value))
+    }
+}
+
+/// Masks the values of auth headers and query parameters, credentials after
+/// an auth scheme and every known secret in `message`, before it is returned
+/// to a client or logged.
+pub fn scrub(message: &str) -> String {
+    let mut message = scrub_schemes(&scrub_headers(message));
+    for secret in KNOWN.lock().unwrap().iter() {
+        if message.contains(secret.as_str()) {
+            message = message.replace(secret.as_str(), MASK);
+        }
+    }
+    message
+}
+
+/// Replaces the value following each sensitive name, as in
+/// `Authorization: Bearer x`, `"x-chroma-token": "x"` or `?api_key=x`, up to
+/// the end of the line, a quote or a separator. Values assigned with `=` also
+/// end at whitespace, `&` or `)`, as in a URL.
+fn scrub_headers(message: &str) -> String {
+    let lower = message.to_ascii_lowercase();
+    let mut scrubbed = String::with_capacity(message.len());
+    let mut copied = 0;
+    let mut search = 0;
+    while let Some((start, header)) = SENSITIVE_NAMES
+        .iter()
+        .filter_map(|header| lower[search..].find(header).map(|at| (search + at, header)))
+        .min_by_key(|(at, _)| *at)
+    {
+        let after_name = start + header.len();
+        let separator = message[after_name..]
+            .find(|c: char| !matches!(c, '"' | '\'' | ':' | '=' | ' '))
+            .map_or(message.len(), |at| after_name + at);
+        let assignment = &message[after_name..separator];
+        if !assignment.contains([':', '=']) {
+            search = after_name;
+            continue;
+        }
+        let in_url = !assignment.contains(':');
+        let end = message[separator..]
+            .find(|c: char| {
+                matches!(c, '\r' | '\n' | '"' | '\'' | ',' | ';' | '}')
+                    || (in_url && (c.is_whitespace() || matches!(c, '&' | ')')))
+            })
+            .map_or(message.len(), |at| separator + at);
+        if end > separator {
+            scrubbed.push_str(&message[copied..separator]);
+            scrubbed.push_str(MASK);
+            copied = end;
+        }
+        search = end.max(after_name);
+    }
+    scrubbed.push_str(&message[copied..]);
+    scrubbed
+}
+
+/// Replaces the word following each sensitive auth scheme, as in
+/// `Bearer x` outside of a header.
+fn scrub_schemes(message: &str) -> String {
+    let lower = message.to_ascii_lowercase();
+    let mut scrubbed = String::with_capacity(message.len());
+    let mut copied = 0;
+    let mut search = 0;
+    while let Some((start, scheme)) = SENSITIVE_SCHEMES
+        .iter()
+        .filter_map(|scheme| lower[search..].find(scheme).map(|at| (search + at, scheme)))
+        .min_by_key(|(at, _)| *at)
+    {
+        let credential = start + scheme.len();
+        let end = message[credential..]
+            .find(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | ',' | ';' | ')' | '}'))
+            .map_or(message.len(), |at| credential + at);
+        if end > credential && &message[credential..end] != MASK {
+            scrubbed.push_str(&message[copied..credential]);
+            scrubbed.push_str(MASK);
+            copied = end;
+        }
+        search = end;
+    }
+    scrubbed.push_str(&message[copied..]);
+    scrubbed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auth_headers_are_masked() {
+        assert_eq!(scrub("Authorization: Bearer abc.def\nnext"), "Authorization: ***\nnext");
+        assert_eq!(scrub("X-Chroma-Token=tok123; path=/"), "X-Chroma-Token=***; path=/");
+        assert_eq!(scrub("authorization header missing"), "authorization header missing");
+    }
+
+    #[test]
+    fn header_maps_are_masked() {
+        let headers = r#"{"content-type": "application/json", "authorization": "Bearer abc", "x-api-key": "key123"}"#;
+        assert_eq!(
+            scrub(headers),
+            r#"{"content-type": "application/json", "authorization": "***", "x-api-key": "***"}"#
+        );
+    }
+
+    #[test]
+    fn bearer_credentials_are_masked_outside_headers() {
+        assert_eq!(scrub("sent bearer abc.def to the server"), "sent bearer *** to the server");
+        assert_eq!(scrub("token (Bearer abc)"), "token (Bearer ***)");
+        assert_eq!(scrub("basic auth failed"), "basic auth failed");
+    }
+
+    #[test]
+    fn api_keys_in_urls_are_masked() {
+        assert_eq!(
+            scrub("error sending request for url (https://host/api?api_key=s3cr3t&limit=5): timed out"),
+            "error sending request for url (https://host/api?api_key=***&limit=5): timed out"
+        );
+        assert_eq!(scrub("GET /x?access_token=abc def"), "GET /x?access_token=*** def");
+    }
+
+    #[test]
+    fn multi_byte_text_next_to_secrets_is_kept() {
+        assert_eq!(scrub("é authorization: ключ\nü"), "é authorization: ***\nü");
+        assert_eq!(scrub("日本 Bearer токен日本 ok"), "日本 Bearer *** ok");
+        assert_eq!(scrub("ü?api_key=ключ&ü=1"), "ü?api_key=***&ü=1");
+
+        let secret = Secret::new("пароль-multi-byte-secret");
+        assert_eq!(format!("{:?}", secret), MASK);
+        assert_eq!(scrub("до пароль-multi-byte-secretпосле"), "до ***после");
+    }
+}
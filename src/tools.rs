@@ -1,206 +1,989 @@
-use crate::client::get_client;
+use crate::audit::{self, AuditEntry, AuditFilter, AuditOutcome};
+use crate::budget::{self, OutputBudget, Truncation};
+use crate::cancel;
+use crate::client::{client_for_profile, get_client, Collection};
+use crate::config::{ExportArgs, ImportArgs, IngestArgs};
+use crate::confirm::{self, Confirmation};
+use crate::dump::{self, records_from_page, CloneOptions, CloneReport, ConflictPolicy, ExportReport, ImportOptions, ImportReport, Record};
+use crate::ingest::{self, IngestOptions, IngestReport};
+use crate::pagination::{self, Cursor, RecordQuery};
+use crate::policy;
+use crate::progress;
+use crate::secret;
+use crate::trash::{self, PurgeReport, RestoreReport, TrashEntry, TrashList};
 use anyhow::{anyhow, Result};
+use crate::registry::{self, ToolAnnotations, ToolGroup, ToolOutput, ToolRegistry, ToolSpec};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
-use mcp_spec::tool::Tool;
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use std::path::Path;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Space {
+    L2,
+    Ip,
+    Cosine,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Include {
+    Documents,
+    Metadatas,
+    Embeddings,
+    Distances,
+    Uris,
+    Data,
+}
+
+impl Include {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Include::Documents => "documents",
+            Include::Metadatas => "metadatas",
+            Include::Embeddings => "embeddings",
+            Include::Distances => "distances",
+            Include::Uris => "uris",
+            Include::Data => "data",
+        }
+    }
+}
+
+const ADD_BATCH_SIZE: usize = 100;
+
+fn include_names(include: &[Include]) -> Vec<String> {
+    include.iter().map(|field| field.as_str().to_string()).collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Operation {
+    Created,
+    Modified,
+    Deleted,
+    Added,
+    Updated,
+}
+
+impl Operation {
+    fn verb(&self) -> &'static str {
+        match self {
+            Operation::Created => "Created",
+            Operation::Modified => "Modified",
+            Operation::Deleted => "Deleted",
+            Operation::Added => "Added",
+            Operation::Updated => "Updated",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CollectionList {
+    pub collections: Vec<String>,
+    pub count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+impl ToolOutput for CollectionList {
+    fn summary(&self) -> String {
+        let mut summary = format!("{} collections: {}", self.count, self.collections.join(", "));
+        if let Some(cursor) = &self.next_cursor {
+            summary.push_str(&format!("\nMore collections follow; continue with cursor {}", cursor));
+        }
+        summary
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CollectionChange {
+    pub collection_name: String,
+    pub operation: Operation,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub changed: Vec<String>,
+    /// Trash entry holding the deleted collection
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trash_id: Option<String>,
+}
+
+impl ToolOutput for CollectionChange {
+    fn summary(&self) -> String {
+        let mut summary = format!("{} collection '{}'", self.operation.verb(), self.collection_name);
+        if !self.changed.is_empty() {
+            summary.push_str(&format!(": updated {}", self.changed.join(" and ")));
+        }
+        summary.push_str(&trash_note(self.trash_id.as_deref()));
+        summary
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CollectionCount {
+    pub collection_name: String,
+    pub count: usize,
+}
+
+impl ToolOutput for CollectionCount {
+    fn summary(&self) -> String {
+        format!("Collection '{}' holds {} records", self.collection_name, self.count)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CollectionInfo {
+    pub collection_name: String,
+    pub count: usize,
+    pub sample: Vec<Record>,
+}
+
+impl ToolOutput for CollectionInfo {
+    fn summary(&self) -> String {
+        let ids: Vec<&str> = self.sample.iter().map(|record| record.id.as_str()).collect();
+        format!(
+            "Collection '{}' holds {} records; sample ids: {}",
+            self.collection_name,
+            self.count,
+            ids.join(", ")
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecordList {
+    pub collection_name: String,
+    pub count: usize,
+    pub records: Vec<Record>,
+    #[serde(flatten)]
+    pub truncation: Truncation,
+}
+
+impl RecordList {
+    fn new(collection_name: String, records: Vec<Record>, start: &Cursor, budget: OutputBudget, has_more: bool) -> Self {
+        let (records, truncation) = budget::truncate_records(records, start, budget, has_more);
+        Self {
+            collection_name,
+            count: records.len(),
+            records,
+            truncation,
+        }
+    }
+}
+
+impl ToolOutput for RecordList {
+    fn summary(&self) -> String {
+        let mut summary = format!("{} records from '{}'", self.count, self.collection_name);
+        for record in &self.records {
+            summary.push_str(&format!("\n- {}: {}", record.id, preview(record.document.as_deref())));
+        }
+        match &self.truncation.next_cursor {
+            Some(cursor) if self.truncation.truncated => summary.push_str(&format!(
+                "\nTruncated to {} of {} characters ({} records fetched); continue with cursor {}",
+                self.truncation.returned_chars, self.truncation.original_chars, self.truncation.original_count, cursor
+            )),
+            Some(cursor) => summary.push_str(&format!("\nMore records follow; continue with cursor {}", cursor)),
+            None => {}
+        }
+        if !self.truncation.cut_ids.is_empty() {
+            summary.push_str(&format!(
+                "\nDocuments cut short: {}; get one by id to read the rest",
+                self.truncation.cut_ids.join(", ")
+            ));
+        }
+        summary
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DocumentsChange {
+    pub collection_name: String,
+    pub operation: Operation,
+    pub count: usize,
+    pub ids: Vec<String>,
+    /// Trash entry holding the deleted records
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trash_id: Option<String>,
+}
+
+impl ToolOutput for DocumentsChange {
+    fn summary(&self) -> String {
+        format!(
+            "{} {} documents in collection '{}'{}",
+            self.operation.verb(),
+            self.count,
+            self.collection_name,
+            trash_note(self.trash_id.as_deref())
+        )
+    }
+}
+
+/// Result of a mutating call: what it would change for a dry run, a preview
+/// when the call must first be confirmed, or the change once it ran.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Outcome<T> {
+    DryRun(DryRunReport),
+    Preview(DeletePreview),
+    Done(T),
+}
+
+impl<T: ToolOutput> ToolOutput for Outcome<T> {
+    fn summary(&self) -> String {
+        match self {
+            Outcome::DryRun(report) => report.summary(),
+            Outcome::Preview(preview) => preview.summary(),
+            Outcome::Done(output) => output.summary(),
+        }
+    }
+}
+
+/// What a mutating call would change, worked out without writing.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DryRunReport {
+    pub dry_run: bool,
+    pub collection_name: String,
+    pub operation: Operation,
+    /// Number of records that would change
+    pub count: usize,
+    /// Ids of the records that would change
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ids: Vec<String>,
+    /// Ids the call names that already exist, which an add would not write
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub existing_ids: Vec<String>,
+    /// Ids the call names that do not exist, which an update or delete would skip
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub missing_ids: Vec<String>,
+    /// Collection properties that would change
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub changed: Vec<String>,
+    /// Whether a delete would bypass the trash
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub permanent: Option<bool>,
+}
+
+impl DryRunReport {
+    fn new(collection_name: &str, operation: Operation) -> Self {
+        Self {
+            dry_run: true,
+            collection_name: collection_name.to_string(),
+            operation,
+            count: 0,
+            ids: Vec::new(),
+            existing_ids: Vec::new(),
+            missing_ids: Vec::new(),
+            changed: Vec::new(),
+            permanent: None,
+        }
+    }
+}
+
+impl ToolOutput for DryRunReport {
+    fn summary(&self) -> String {
+        let mut summary = if self.changed.is_empty() {
+            format!(
+                "Dry run: would have {} {} records in collection '{}'",
+                self.operation.verb().to_lowercase(),
+                self.count,
+                self.collection_name
+            )
+        } else {
+            format!(
+                "Dry run: would have {} {} of collection '{}'",
+                self.operation.verb().to_lowercase(),
+                self.changed.join(" and "),
+                self.collection_name
+            )
+        };
+        match self.permanent {
+            Some(true) => summary.push_str(" permanently"),
+            Some(false) => summary.push_str(", moving them to the trash"),
+            None => {}
+        }
+        if !self.existing_ids.is_empty() {
+            summary.push_str(&format!("; ids already present: {}", self.existing_ids.join(", ")));
+        }
+        if !self.missing_ids.is_empty() {
+            summary.push_str(&format!("; ids not found: {}", self.missing_ids.join(", ")));
+        }
+        summary.push_str(". Nothing was written");
+        summary
+    }
+}
+
+/// Splits `ids` into those stored in `collection` and those that are not,
+/// keeping their order.
+fn partition_existing(collection: &Collection, ids: &[String]) -> Result<(Vec<String>, Vec<String>)> {
+    let page = collection.get(Some(ids.to_vec()), None, None, Vec::new(), None, None)?;
+    let stored: HashSet<String> = records_from_page(&page)?.into_iter().map(|record| record.id).collect();
+    Ok(ids.iter().cloned().partition(|id| stored.contains(id)))
+}
+
+fn check_unique_ids(ids: &[String]) -> Result<()> {
+    let mut seen = HashSet::new();
+    match ids.iter().find(|id| !seen.insert(id.as_str())) {
+        Some(id) => Err(anyhow!("Duplicate id '{}' in the 'ids' list.", id)),
+        None => Ok(()),
+    }
+}
+
+/// Checks that a metadata object holds only string, number and boolean
+/// values, as the backend requires. With `allow_null`, for updates, a `null`
+/// value is also accepted: the backend merges updated metadata and removes
+/// the keys set to `null`.
+fn validate_metadata(metadata: &Value, label: &str, allow_null: bool) -> Result<()> {
+    let fields = metadata
+        .as_object()
+        .ok_or_else(|| anyhow!("{} must be an object.", label))?;
+    for (key, value) in fields {
+        if key.is_empty() {
+            return Err(anyhow!("{} has an empty key.", label));
+        }
+        let allowed = value.is_string() || value.is_number() || value.is_boolean() || (allow_null && value.is_null());
+        if !allowed {
+            return Err(anyhow!(
+                "{} field '{}' must be a {}.",
+                label,
+                key,
+                if allow_null { "string, number, boolean or null" } else { "string, number or boolean" }
+            ));
+        }
+    }
+    Ok(())
+}
 
+fn validate_metadatas(metadatas: &[Value], allow_null: bool) -> Result<()> {
+    metadatas.iter().enumerate().try_for_each(|(index, metadata)| {
+        validate_metadata(metadata, &format!("Metadata {}", index), allow_null)
+    })
+}
+
+/// What a delete would remove, returned with the token that confirms it.
 #[derive(Debug, Serialize, Deserialize)]
+pub struct DeletePreview {
+    pub collection_name: String,
+    /// Whether the delete bypasses the trash
+    pub permanent: bool,
+    pub count: usize,
+    pub sample: Vec<Record>,
+    #[serde(flatten)]
+    pub confirmation: Confirmation,
+}
+
+impl ToolOutput for DeletePreview {
+    fn summary(&self) -> String {
+        let ids: Vec<&str> = self.sample.iter().map(|record| record.id.as_str()).collect();
+        format!(
+            "Confirmation required: this would delete {} records of '{}'{} (sample ids: {}). \
+             Repeat the call with confirmation_token '{}' within {} seconds to proceed",
+            self.count,
+            self.collection_name,
+            if self.permanent { " permanently" } else { ", moving them to the trash" },
+            ids.join(", "),
+            self.confirmation.confirmation_token,
+            self.confirmation.expires_in_secs
+        )
+    }
+}
+
+const PREVIEW_SAMPLE_SIZE: usize = 5;
+
+fn trash_note(trash_id: Option<&str>) -> String {
+    trash_id
+        .map(|id| format!(" (moved to trash entry {}; undo with chroma_restore)", id))
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QueryHit {
+    pub id: String,
+    pub document: Option<String>,
+    pub metadata: Option<Value>,
+    pub distance: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<Vec<f32>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QueryHits {
+    pub query: String,
+    pub hits: Vec<QueryHit>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QueryResult {
+    pub collection_name: String,
+    pub results: Vec<QueryHits>,
+}
+
+impl ToolOutput for QueryResult {
+    fn summary(&self) -> String {
+        let mut summary = String::new();
+        for result in &self.results {
+            summary.push_str(&format!("Query '{}': {} hits", result.query, result.hits.len()));
+            for hit in &result.hits {
+                let distance = hit.distance.map(|d| format!(" ({:.3})", d)).unwrap_or_default();
+                summary.push_str(&format!("\n- {}{}: {}", hit.id, distance, preview(hit.document.as_deref())));
+            }
+            summary.push('\n');
+        }
+        summary.trim_end().to_string()
+    }
+}
+
+fn preview(document: Option<&str>) -> String {
+    const PREVIEW_CHARS: usize = 80;
+    let document = document.unwrap_or_default().trim().replace('\n', " ");
+    if document.chars().count() > PREVIEW_CHARS {
+        format!("{}…", document.chars().take(PREVIEW_CHARS).collect::<String>())
+    } else {
+        document
+    }
+}
+
+fn query_hits(query_texts: Vec<String>, raw: &Value) -> Result<Vec<QueryHits>> {
+    query_texts
+        .into_iter()
+        .enumerate()
+        .map(|(q, query)| {
+            let ids = raw["ids"][q].as_array().cloned().unwrap_or_default();
+            let hits = ids
+                .iter()
+                .enumerate()
+                .map(|(i, id)| {
+                    let embedding = match &raw["embeddings"][q][i] {
+                        Value::Null => None,
+                        embedding => Some(serde_json::from_value(embedding.clone())?),
+                    };
+                    Ok(QueryHit {
+                        id: id.as_str().unwrap_or_default().to_string(),
+                        document: raw["documents"][q][i].as_str().map(str::to_string),
+                        metadata: Some(raw["metadatas"][q][i].clone()).filter(|m| !m.is_null()),
+                        distance: raw["distances"][q][i].as_f64(),
+                        embedding,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Ok(QueryHits { query, hits })
+        })
+        .collect()
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct ListCollectionsRequest {
+    /// Maximum number of collections to return
+    #[schemars(range(min = 1))]
     pub limit: Option<usize>,
+    /// Offset for pagination
     pub offset: Option<usize>,
+    /// Cursor returned as `next_cursor` by the previous page, or "start" to page by name, stable under concurrent writes but listing every name per page
+    pub cursor: Option<String>,
 }
 
-pub async fn chroma_list_collections(request: ListCollectionsRequest) -> Result<Vec<String>> {
+pub async fn chroma_list_collections(request: ListCollectionsRequest) -> Result<CollectionList> {
     let client = get_client();
-    client.list_collections(request.limit, request.offset)
+    let start = Cursor::resolve(request.cursor.as_deref(), request.offset)?;
+    let (collections, next) = pagination::list_collections(&client, request.limit, &start)?;
+    Ok(CollectionList {
+        count: collections.len(),
+        collections,
+        next_cursor: next.map(|cursor| cursor.encode()),
+    })
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct CreateCollectionRequest {
+    /// Name of the collection to create
     pub collection_name: String,
+    /// Name of the embedding function to use
     pub embedding_function_name: Option<String>,
+    /// Optional metadata for the collection
+    #[schemars(with = "Option<Map<String, Value>>")]
     pub metadata: Option<Value>,
-    pub space: Option<String>,
+    /// Distance function of the HNSW index
+    pub space: Option<Space>,
+    /// Size of the candidate list used while building the HNSW index
+    #[schemars(range(min = 1))]
     pub ef_construction: Option<i32>,
+    /// Size of the candidate list used while searching the HNSW index
+    #[schemars(range(min = 1))]
     pub ef_search: Option<i32>,
+    /// Maximum number of neighbors per node in the HNSW graph
+    #[schemars(range(min = 1))]
     pub max_neighbors: Option<i32>,
+    /// Number of threads used to build the HNSW index
+    #[schemars(range(min = 1))]
     pub num_threads: Option<i32>,
+    /// Number of vectors buffered before they are added to the HNSW index
+    #[schemars(range(min = 2))]
     pub batch_size: Option<i32>,
+    /// Number of vectors buffered before the HNSW index is persisted
+    #[schemars(range(min = 2))]
     pub sync_threshold: Option<i32>,
+    /// Growth factor applied when the HNSW index is resized
+    #[schemars(range(min = 1))]
     pub resize_factor: Option<f32>,
 }
 
-pub async fn chroma_create_collection(request: CreateCollectionRequest) -> Result<String> {
+/// HNSW index settings, as sent in the `hnsw` section of a collection's
+/// configuration; unset fields keep the backend's values.
+#[derive(Debug, Default, PartialEq, Serialize)]
+struct HnswConfiguration {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    space: Option<Space>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ef_construction: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ef_search: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_neighbors: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_threads: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    batch_size: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sync_threshold: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    resize_factor: Option<f32>,
+}
+
+/// The collection configuration to send, or `None` when nothing is set.
+fn collection_configuration(hnsw: HnswConfiguration, embedding_function_name: Option<&str>) -> Result<Option<Value>> {
+    let mut configuration = Map::new();
+    if hnsw != HnswConfiguration::default() {
+        configuration.insert("hnsw".to_string(), serde_json::to_value(hnsw)?);
+    }
+    if let Some(name) = embedding_function_name {
+        configuration.insert("embedding_function".to_string(), Value::String(name.to_string()));
+    }
+    Ok((!configuration.is_empty()).then_some(Value::Object(configuration)))
+}
+
+pub async fn chroma_create_collection(request: CreateCollectionRequest) -> Result<CollectionChange> {
+    policy::check_write(&request.collection_name)?;
+    let hnsw = HnswConfiguration {
+        space: request.space,
+        ef_construction: request.ef_construction,
+        ef_search: request.ef_search,
+        max_neighbors: request.max_neighbors,
+        num_threads: request.num_threads,
+        batch_size: request.batch_size,
+        sync_threshold: request.sync_threshold,
+        resize_factor: request.resize_factor,
+    };
+    let configuration = collection_configuration(hnsw, request.embedding_function_name.as_deref())?;
     let client = get_client();
-    client.create_collection(&request.collection_name, request.metadata)
+    client.create_collection(&request.collection_name, request.metadata, configuration)?;
+    Ok(CollectionChange {
+        collection_name: request.collection_name,
+        operation: Operation::Created,
+        changed: Vec::new(),
+        trash_id: None,
+    })
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct PeekCollectionRequest {
+    /// Name of the collection to peek
     pub collection_name: String,
+    /// Number of documents to return
+    #[schemars(range(min = 1))]
     pub limit: usize,
+    /// Continuation cursor returned by a previous truncated call
+    pub cursor: Option<String>,
+    /// Maximum size of the response in characters
+    #[schemars(range(min = 1))]
+    pub max_output_chars: Option<usize>,
+    /// Maximum size of the response in approximate tokens
+    #[schemars(range(min = 1))]
+    pub max_output_tokens: Option<usize>,
+    /// Maximum characters returned per document before it is truncated
+    #[schemars(range(min = 1))]
+    pub max_document_chars: Option<usize>,
 }
 
-pub async fn chroma_peek_collection(request: PeekCollectionRequest) -> Result<Value> {
+pub async fn chroma_peek_collection(request: PeekCollectionRequest) -> Result<RecordList> {
+    policy::check_read(&request.collection_name)?;
     let client = get_client();
     let collection = client.get_collection(&request.collection_name)?;
-    collection.peek(request.limit)
+    let budget = OutputBudget::for_request(request.max_output_chars, request.max_output_tokens, request.max_document_chars);
+    
+    let (records, start) = match request.cursor.as_deref() {
+        Some(cursor) => {
+            let start = Cursor::decode(cursor)?;
+            let query = RecordQuery {
+                ids: None,
+                where_filter: None,
+                where_document: None,
+                include: include_names(&[Include::Documents, Include::Metadatas]),
+                limit: Some(request.limit),
+            };
+            (pagination::fetch_records(&collection, query, &start)?.0, start)
+        }
+        None => (records_from_page(&collection.peek(request.limit)?)?, Cursor::offset(0)),
+    };
+    
+    Ok(RecordList::new(request.collection_name, records, &start, budget, false))
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct GetCollectionInfoRequest {
+    /// Name of the collection
     pub collection_name: String,
 }
 
-pub async fn chroma_get_collection_info(request: GetCollectionInfoRequest) -> Result<Value> {
+pub async fn chroma_get_collection_info(request: GetCollectionInfoRequest) -> Result<CollectionInfo> {
+    policy::check_read(&request.collection_name)?;
     let client = get_client();
     let collection = client.get_collection(&request.collection_name)?;
     let count = collection.count()?;
-    let sample_documents = collection.peek(3)?;
+    let sample = records_from_page(&collection.peek(3)?)?;
     
-    Ok(serde_json::json!({
-        "name": request.collection_name,
-        "count": count,
-        "sample_documents": sample_documents
-    }))
+    Ok(CollectionInfo {
+        collection_name: request.collection_name,
+        count,
+        sample,
+    })
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct GetCollectionCountRequest {
+    /// Name of the collection
     pub collection_name: String,
 }
 
-pub async fn chroma_get_collection_count(request: GetCollectionCountRequest) -> Result<usize> {
+pub async fn chroma_get_collection_count(request: GetCollectionCountRequest) -> Result<CollectionCount> {
+    policy::check_read(&request.collection_name)?;
     let client = get_client();
     let collection = client.get_collection(&request.collection_name)?;
-    collection.count()
+    Ok(CollectionCount {
+        count: collection.count()?,
+        collection_name: request.collection_name,
+    })
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct ModifyCollectionRequest {
+    /// Name of the collection to modify
     pub collection_name: String,
+    /// New name for the collection
     pub new_name: Option<String>,
+    /// New metadata for the collection
+    #[schemars(with = "Option<Map<String, Value>>")]
     pub new_metadata: Option<Value>,
+    /// Size of the candidate list used while searching the HNSW index
+    #[schemars(range(min = 1))]
     pub ef_search: Option<i32>,
+    /// Number of threads used to build the HNSW index
+    #[schemars(range(min = 1))]
     pub num_threads: Option<i32>,
+    /// Number of vectors buffered before they are added to the HNSW index
+    #[schemars(range(min = 2))]
     pub batch_size: Option<i32>,
+    /// Number of vectors buffered before the HNSW index is persisted
+    #[schemars(range(min = 2))]
     pub sync_threshold: Option<i32>,
+    /// Growth factor applied when the HNSW index is resized
+    #[schemars(range(min = 1))]
     pub resize_factor: Option<f32>,
+    /// Validate and report what would change without writing
+    pub dry_run: Option<bool>,
 }
 
-pub async fn chroma_modify_collection(request: ModifyCollectionRequest) -> Result<String> {
+pub async fn chroma_modify_collection(request: ModifyCollectionRequest) -> Result<Outcome<CollectionChange>> {
+    if let Some(metadata) = &request.new_metadata {
+        validate_metadata(metadata, "New metadata", false)?;
+    }
+    policy::check_write(&request.collection_name)?;
+    if let Some(new_name) = &request.new_name {
+        policy::check_write(new_name)?;
+    }
     let client = get_client();
     let collection = client.get_collection(&request.collection_name)?;
-    collection.modify(request.new_name.clone(), request.new_metadata.clone())?;
+    if let Some(new_name) = &request.new_name
+        && *new_name != request.collection_name
+        && client.list_collections(None, None)?.contains(new_name)
+    {
+        return Err(anyhow!("Collection '{}' already exists.", new_name));
+    }
     
+    let hnsw = HnswConfiguration {
+        ef_search: request.ef_search,
+        num_threads: request.num_threads,
+        batch_size: request.batch_size,
+        sync_threshold: request.sync_threshold,
+        resize_factor: request.resize_factor,
+        ..Default::default()
+    };
+    let configuration = collection_configuration(hnsw, None)?;
+
     let mut modified_aspects = Vec::new();
-    if request.new_name.is_some() { modified_aspects.push("name"); }
-    if request.new_metadata.is_some() { modified_aspects.push("metadata"); }
-    if request.ef_search.is_some() || request.num_threads.is_some() || 
-       request.batch_size.is_some() || request.sync_threshold.is_some() || 
-       request.resize_factor.is_some() { modified_aspects.push("hnsw"); }
+    if request.new_name.is_some() { modified_aspects.push("name".to_string()); }
+    if request.new_metadata.is_some() { modified_aspects.push("metadata".to_string()); }
+    if configuration.is_some() { modified_aspects.push("hnsw".to_string()); }
+
+    if request.dry_run.unwrap_or(false) {
+        let mut report = DryRunReport::new(&request.collection_name, Operation::Modified);
+        report.changed = modified_aspects;
+        return Ok(Outcome::DryRun(report));
+    }
+    collection.modify(request.new_name.clone(), request.new_metadata.clone(), configuration)?;
     
-    Ok(format!("Successfully modified collection {}: updated {}", 
-               request.collection_name, 
-               modified_aspects.join(" and ")))
+    Ok(Outcome::Done(CollectionChange {
+        collection_name: request.collection_name,
+        operation: Operation::Modified,
+        changed: modified_aspects,
+        trash_id: None,
+    }))
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct DeleteCollectionRequest {
+    /// Name of the collection to delete
     pub collection_name: String,
+    /// Delete permanently instead of moving the collection to the trash (requires --allow-hard-delete)
+    pub permanent: Option<bool>,
+    /// Token from a previous call's preview, confirming the delete
+    pub confirmation_token: Option<String>,
+    /// Validate and report what would be deleted without writing
+    pub dry_run: Option<bool>,
 }
 
-pub async fn chroma_delete_collection(request: DeleteCollectionRequest) -> Result<String> {
+pub async fn chroma_delete_collection(request: DeleteCollectionRequest) -> Result<Outcome<CollectionChange>> {
+    let permanent = request.permanent.unwrap_or(false);
+    if permanent {
+        trash::check_hard_delete()?;
+    }
+    policy::check_write(&request.collection_name)?;
     let client = get_client();
-    client.delete_collection(&request.collection_name)?;
-    Ok(format!("Successfully deleted collection {}", request.collection_name))
+    let collection = client.get_collection(&request.collection_name)?;
+    let count = collection.count()?;
+    if request.dry_run.unwrap_or(false) {
+        let mut report = DryRunReport::new(&request.collection_name, Operation::Deleted);
+        report.count = count;
+        report.permanent = Some(permanent);
+        return Ok(Outcome::DryRun(report));
+    }
+    let token = request.confirmation_token.as_deref();
+    if let Some(confirmation) = confirm::check("chroma_delete_collection", count, &request, &[], token)? {
+        return Ok(Outcome::Preview(DeletePreview {
+            collection_name: request.collection_name,
+            permanent,
+            count,
+            sample: records_from_page(&collection.peek(PREVIEW_SAMPLE_SIZE)?)?,
+            confirmation,
+        }));
+    }
+
+    let trash_id = if permanent {
+        client.delete_collection(&request.collection_name)?;
+        None
+    } else {
+        Some(trash::trash_collection(&client, &request.collection_name)?.trash_id)
+    };
+    Ok(Outcome::Done(CollectionChange {
+        collection_name: request.collection_name,
+        operation: Operation::Deleted,
+        changed: Vec::new(),
+        trash_id,
+    }))
 }
 
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct AddDocumentsRequest {
+    /// Name of the collection
     pub collection_name: String,
+    /// List of documents to add
+    #[schemars(length(min = 1))]
     pub documents: Vec<String>,
+    /// List of metadata objects for documents
+    #[schemars(with = "Option<Vec<Map<String, Value>>>")]
     pub metadatas: Option<Vec<Value>>,
+    /// List of IDs for documents
     pub ids: Option<Vec<String>>,
+    /// Validate and report what would be added without writing
+    pub dry_run: Option<bool>,
 }
 
-pub async fn chroma_add_documents(request: AddDocumentsRequest) -> Result<String> {
+pub async fn chroma_add_documents(request: AddDocumentsRequest) -> Result<Outcome<DocumentsChange>> {
     if request.documents.is_empty() {
         return Err(anyhow!("The 'documents' list cannot be empty."));
     }
     
-    let client = get_client();
-    let collection = client.get_collection(&request.collection_name)?;
-    
     let ids = match request.ids {
         Some(ids) => ids,
         None => (0..request.documents.len()).map(|i| i.to_string()).collect(),
     };
     
-    let documents_len = request.documents.len();
-    collection.add(request.documents.clone(), request.metadatas.clone(), ids)?;
+    if ids.len() != request.documents.len() {
+        return Err(anyhow!("The 'ids' list must have one entry per document."));
+    }
+    check_unique_ids(&ids)?;
+    if let Some(metadatas) = &request.metadatas {
+        if metadatas.len() != request.documents.len() {
+            return Err(anyhow!("The 'metadatas' list must have one entry per document."));
+        }
+        validate_metadatas(metadatas, false)?;
+    }
+    
+    policy::check_write(&request.collection_name)?;
+    let client = get_client();
+    let collection = client.get_collection(&request.collection_name)?;
+    
+    if request.dry_run.unwrap_or(false) {
+        let (existing_ids, new_ids) = partition_existing(&collection, &ids)?;
+        let mut report = DryRunReport::new(&request.collection_name, Operation::Added);
+        report.count = new_ids.len();
+        report.ids = new_ids;
+        report.existing_ids = existing_ids;
+        return Ok(Outcome::DryRun(report));
+    }
+    
+    let total = request.documents.len();
+    let mut metadatas = request.metadatas.map(Vec::into_iter);
+    for (batch, documents) in request.documents.chunks(ADD_BATCH_SIZE).enumerate() {
+        let start = batch * ADD_BATCH_SIZE;
+        let batch_ids = ids[start..start + documents.len()].to_vec();
+        let batch_metadatas = metadatas.as_mut().map(|metadatas| metadatas.by_ref().take(documents.len()).collect());
+        cancel::check()
+            .and_then(|_| collection.add(documents.to_vec(), batch_metadatas, batch_ids))
+            .map_err(|e| {
+                cancel::partial(e, || match start {
+                    0 => format!("Cancelled before adding any of {} documents", total),
+                    _ => format!(
+                        "Cancelled after adding {} of {} documents; ids '{}' through '{}' were committed",
+                        start,
+                        total,
+                        ids[0],
+                        ids[start - 1]
+                    ),
+                })
+            })?;
+        progress::report("adding documents", start + documents.len(), Some(total));
+    }
     
-    Ok(format!("Successfully added {} documents to collection {}", 
-               documents_len, 
-               request.collection_name))
+    Ok(Outcome::Done(DocumentsChange {
+        collection_name: request.collection_name,
+        operation: Operation::Added,
+        count: ids.len(),
+        ids,
+        trash_id: None,
+    }))
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct QueryDocumentsRequest {
+    /// Name of the collection
     pub collection_name: String,
+    /// List of query texts
+    #[schemars(length(min = 1))]
     pub query_texts: Vec<String>,
+    /// Number of results to return per query
+    #[schemars(range(min = 1))]
     pub n_results: Option<usize>,
+    /// Filter by metadata
+    #[schemars(with = "Option<Map<String, Value>>")]
     pub where_filter: Option<Value>,
+    /// Filter by document content
+    #[schemars(with = "Option<Map<String, Value>>")]
     pub where_document: Option<Value>,
-    pub include: Option<Vec<String>>,
+    /// Fields to include in the results
+    pub include: Option<Vec<Include>>,
 }
 
-pub async fn chroma_query_documents(request: QueryDocumentsRequest) -> Result<Value> {
+pub async fn chroma_query_documents(request: QueryDocumentsRequest) -> Result<QueryResult> {
     if request.query_texts.is_empty() {
         return Err(anyhow!("The 'query_texts' list cannot be empty."));
     }
     
+    policy::check_read(&request.collection_name)?;
     let client = get_client();
     let collection = client.get_collection(&request.collection_name)?;
     
     let n_results = request.n_results.unwrap_or(5);
-    let include = request.include.unwrap_or_else(|| vec!["documents".to_string(), "metadatas".to_string(), "distances".to_string()]);
+    let include = request.include.unwrap_or_else(|| vec![Include::Documents, Include::Metadatas, Include::Distances]);
     
-    collection.query(request.query_texts, n_results, request.where_filter, request.where_document, include)
+    let raw = collection.query(request.query_texts.clone(), n_results, request.where_filter, request.where_document, include_names(&include))?;
+    
+    Ok(QueryResult {
+        collection_name: request.collection_name,
+        results: query_hits(request.query_texts, &raw)?,
+    })
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct GetDocumentsRequest {
+    /// Name of the collection
     pub collection_name: String,
+    /// List of document IDs to retrieve
     pub ids: Option<Vec<String>>,
+    /// Filter by metadata
+    #[schemars(with = "Option<Map<String, Value>>")]
     pub where_filter: Option<Value>,
+    /// Filter by document content
+    #[schemars(with = "Option<Map<String, Value>>")]
     pub where_document: Option<Value>,
-    pub include: Option<Vec<String>>,
+    /// Fields to include in the results
+    pub include: Option<Vec<Include>>,
+    /// Maximum number of documents to return
+    #[schemars(range(min = 1))]
     pub limit: Option<usize>,
+    /// Offset for pagination
     pub offset: Option<usize>,
+    /// Cursor returned as `next_cursor` by the previous page or truncated call, or "start" to page by id, stable under concurrent writes but listing every matching id per page
+    pub cursor: Option<String>,
+    /// Maximum size of the response in characters
+    #[schemars(range(min = 1))]
+    pub max_output_chars: Option<usize>,
+    /// Maximum size of the response in approximate tokens
+    #[schemars(range(min = 1))]
+    pub max_output_tokens: Option<usize>,
+    /// Maximum characters returned per document before it is truncated
+    #[schemars(range(min = 1))]
+    pub max_document_chars: Option<usize>,
 }
 
-pub async fn chroma_get_documents(request: GetDocumentsRequest) -> Result<Value> {
+pub async fn chroma_get_documents(request: GetDocumentsRequest) -> Result<RecordList> {
+    policy::check_read(&request.collection_name)?;
     let client = get_client();
     let collection = client.get_collection(&request.collection_name)?;
+    let budget = OutputBudget::for_request(request.max_output_chars, request.max_output_tokens, request.max_document_chars);
     
-    let include = request.include.unwrap_or_else(|| vec!["documents".to_string(), "metadatas".to_string()]);
+    let include = request.include.unwrap_or_else(|| vec![Include::Documents, Include::Metadatas]);
+    let start = Cursor::resolve(request.cursor.as_deref(), request.offset)?;
+    let query = RecordQuery {
+        ids: request.ids,
+        where_filter: request.where_filter,
+        where_document: request.where_document,
+        include: include_names(&include),
+        limit: request.limit,
+    };
+    let (records, has_more) = pagination::fetch_records(&collection, query, &start)?;
     
-    collection.get(request.ids, request.where_filter, request.where_document, include, request.limit, request.offset)
+    Ok(RecordList::new(request.collection_name, records, &start, budget, has_more))
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct UpdateDocumentsRequest {
+    /// Name of the collection
     pub collection_name: String,
+    /// List of document IDs to update
+    #[schemars(length(min = 1))]
     pub ids: Vec<String>,
+    /// List of embeddings, one per ID
     pub embeddings: Option<Vec<Vec<f32>>>,
+    /// List of metadata objects, one per ID; keys set to null are removed
+    #[schemars(with = "Option<Vec<Map<String, Value>>>")]
     pub metadatas: Option<Vec<Value>>,
+    /// List of document contents, one per ID
     pub documents: Option<Vec<String>>,
+    /// Validate and report what would be updated without writing
+    pub dry_run: Option<bool>,
 }
 
-pub async fn chroma_update_documents(request: UpdateDocumentsRequest) -> Result<String> {
+pub async fn chroma_update_documents(request: UpdateDocumentsRequest) -> Result<Outcome<DocumentsChange>> {
     if request.ids.is_empty() {
         return Err(anyhow!("The 'ids' list cannot be empty."));
     }
@@ -222,59 +1005,674 @@ pub async fn chroma_update_documents(request: UpdateDocumentsRequest) -> Result<
     
     if let Some(ref metadatas) = request.metadatas {
         check_length("metadatas", metadatas.len())?;
+        validate_metadatas(metadatas, true)?;
     }
     
     if let Some(ref documents) = request.documents {
         check_length("documents", documents.len())?;
     }
+    check_unique_ids(&request.ids)?;
     
+    policy::check_write(&request.collection_name)?;
     let client = get_client();
     let collection = client.get_collection(&request.collection_name)?;
     
+    if request.dry_run.unwrap_or(false) {
+        let (existing_ids, missing_ids) = partition_existing(&collection, &request.ids)?;
+        let mut report = DryRunReport::new(&request.collection_name, Operation::Updated);
+        report.count = existing_ids.len();
+        report.ids = existing_ids;
+        report.missing_ids = missing_ids;
+        return Ok(Outcome::DryRun(report));
+    }
+    
     collection.update(request.ids.clone(), request.embeddings, request.metadatas, request.documents)?;
     
-    Ok(format!(
-        "Successfully updated {} documents in collection '{}'",
-        request.ids.len(),
-        request.collection_name
-    ))
+    Ok(Outcome::Done(DocumentsChange {
+        collection_name: request.collection_name,
+        operation: Operation::Updated,
+        count: request.ids.len(),
+        ids: request.ids,
+        trash_id: None,
+    }))
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct PatchMetadataRequest {
+    /// Name of the collection
+    pub collection_name: String,
+    /// Patch the documents whose metadata matches this filter
+    #[schemars(with = "Option<Map<String, Value>>")]
+    pub where_filter: Option<Value>,
+    /// Patch the documents whose content matches this filter
+    #[schemars(with = "Option<Map<String, Value>>")]
+    pub where_document: Option<Value>,
+    /// Metadata keys to set, merged into each document's metadata
+    pub set: Option<Map<String, Value>>,
+    /// Metadata keys to remove
+    pub unset: Option<Vec<String>>,
+    /// Fail without patching anything when more documents than this match
+    pub max_affected: usize,
+    /// Number of documents updated per batch
+    #[schemars(range(min = 1))]
+    pub batch_size: Option<usize>,
+    /// Validate and report what would be patched without writing
+    pub dry_run: Option<bool>,
+}
+
+/// Whether applying `patch`, where `null` removes a key, would change
+/// `metadata`.
+fn patch_changes(metadata: Option<&Value>, patch: &Map<String, Value>) -> bool {
+    let metadata = metadata.and_then(Value::as_object);
+    patch.iter().any(|(key, value)| {
+        let current = metadata.and_then(|metadata| metadata.get(key));
+        match value {
+            Value::Null => current.is_some(),
+            value => current != Some(value),
+        }
+    })
+}
+
+pub async fn chroma_patch_metadata(request: PatchMetadataRequest) -> Result<Outcome<DocumentsChange>> {
+    check_filter("where_filter", request.where_filter.as_ref())?;
+    check_filter("where_document", request.where_document.as_ref())?;
+    if request.where_filter.is_none() && request.where_document.is_none() {
+        return Err(anyhow!("Pass 'where_filter' or 'where_document' to choose the documents to patch."));
+    }
+    let set = request.set.clone().unwrap_or_default();
+    let unset = request.unset.clone().unwrap_or_default();
+    if set.is_empty() && unset.is_empty() {
+        return Err(anyhow!("The patch is empty; pass keys to 'set' or 'unset'."));
+    }
+    if let Some(key) = unset.iter().find(|key| set.contains_key(*key)) {
+        return Err(anyhow!("Key '{}' cannot be both set and unset.", key));
+    }
+    // The backend merges updated metadata and removes the keys set to null,
+    // so only the patched keys are sent.
+    let mut patch = set;
+    patch.extend(unset.into_iter().map(|key| (key, Value::Null)));
+    validate_metadata(&Value::Object(patch.clone()), "The patch", true)?;
+    
+    policy::check_write(&request.collection_name)?;
+    let client = get_client();
+    let collection = client.get_collection(&request.collection_name)?;
+    
+    let page = collection.get(
+        None,
+        request.where_filter.clone(),
+        request.where_document.clone(),
+        include_names(&[Include::Metadatas]),
+        Some(request.max_affected.saturating_add(1)),
+        None,
+    )?;
+    let records = records_from_page(&page)?;
+    if records.len() > request.max_affected {
+        return Err(anyhow!(
+            "The filter matches more than max_affected ({}) documents; narrow the filter or raise max_affected.",
+            request.max_affected
+        ));
+    }
+    let ids: Vec<String> = records
+        .into_iter()
+        .filter(|record| patch_changes(record.metadata.as_ref(), &patch))
+        .map(|record| record.id)
+        .collect();
+    
+    if request.dry_run.unwrap_or(false) {
+        let mut report = DryRunReport::new(&request.collection_name, Operation::Updated);
+        report.count = ids.len();
+        report.ids = ids;
+        return Ok(Outcome::DryRun(report));
+    }
+    
+    let total = ids.len();
+    let batch_size = request.batch_size.unwrap_or(ADD_BATCH_SIZE).max(1);
+    for (batch, batch_ids) in ids.chunks(batch_size).enumerate() {
+        let start = batch * batch_size;
+        let batch_metadatas = vec![Value::Object(patch.clone()); batch_ids.len()];
+        cancel::check()
+            .and_then(|_| collection.update(batch_ids.to_vec(), None, Some(batch_metadatas), None))
+            .map_err(|e| {
+                cancel::partial(e, || {
+                    format!(
+                        "Cancelled after patching {} of {} documents; the rest keep their old metadata",
+                        start, total
+                    )
+                })
+            })?;
+        progress::report("patching metadata", start + batch_ids.len(), Some(total));
+    }
+    
+    Ok(Outcome::Done(DocumentsChange {
+        collection_name: request.collection_name,
+        operation: Operation::Updated,
+        count: total,
+        ids,
+        trash_id: None,
+    }))
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct DeleteDocumentsRequest {
+    /// Name of the collection
     pub collection_name: String,
-    pub ids: Vec<String>,
+    /// List of document IDs to delete
+    pub ids: Option<Vec<String>>,
+    /// Delete the documents whose metadata matches this filter
+    #[schemars(with = "Option<Map<String, Value>>")]
+    pub where_filter: Option<Value>,
+    /// Delete the documents whose content matches this filter
+    #[schemars(with = "Option<Map<String, Value>>")]
+    pub where_document: Option<Value>,
+    /// Fail instead of deleting more than this many documents; required with a filter
+    pub max_affected: Option<usize>,
+    /// Delete permanently instead of moving the records to the trash (requires --allow-hard-delete)
+    pub permanent: Option<bool>,
+    /// Token from a previous call's preview, confirming the delete
+    pub confirmation_token: Option<String>,
+    /// Validate and report what would be deleted without writing
+    pub dry_run: Option<bool>,
 }
 
-pub async fn chroma_delete_documents(request: DeleteDocumentsRequest) -> Result<String> {
-    if request.ids.is_empty() {
-        return Err(anyhow!("The 'ids' list cannot be empty."));
+impl DeleteDocumentsRequest {
+    fn has_filter(&self) -> bool {
+        self.where_filter.is_some() || self.where_document.is_some()
+    }
+}
+
+fn check_filter(name: &str, filter: Option<&Value>) -> Result<()> {
+    match filter {
+        Some(Value::Object(fields)) if fields.is_empty() => Err(anyhow!("The '{}' filter cannot be empty.", name)),
+        Some(Value::Object(_)) | None => Ok(()),
+        Some(_) => Err(anyhow!("The '{}' filter must be an object.", name)),
+    }
+}
+
+/// Ids of the records a delete applies to. With a filter, they are looked up
+/// and the call fails when more than `max_affected` match.
+fn ids_to_delete(collection: &Collection, request: &DeleteDocumentsRequest) -> Result<Vec<String>> {
+    if !request.has_filter() {
+        let ids = request.ids.clone().unwrap_or_default();
+        if let Some(max_affected) = request.max_affected
+            && ids.len() > max_affected
+        {
+            return Err(anyhow!(
+                "The call names {} documents, more than max_affected ({}).",
+                ids.len(),
+                max_affected
+            ));
+        }
+        return Ok(ids);
     }
     
+    let max_affected = request
+        .max_affected
+        .ok_or_else(|| anyhow!("'max_affected' is required when deleting by 'where_filter' or 'where_document'."))?;
+    let page = collection.get(
+        request.ids.clone(),
+        request.where_filter.clone(),
+        request.where_document.clone(),
+        Vec::new(),
+        Some(max_affected.saturating_add(1)),
+        None,
+    )?;
+    let ids: Vec<String> = records_from_page(&page)?.into_iter().map(|record| record.id).collect();
+    if ids.len() > max_affected {
+        return Err(anyhow!(
+            "The filter matches more than max_affected ({}) documents; narrow the filter or raise max_affected.",
+            max_affected
+        ));
+    }
+    Ok(ids)
+}
+
+pub async fn chroma_delete_documents(request: DeleteDocumentsRequest) -> Result<Outcome<DocumentsChange>> {
+    check_filter("where_filter", request.where_filter.as_ref())?;
+    check_filter("where_document", request.where_document.as_ref())?;
+    if !request.has_filter() && request.ids.as_ref().is_none_or(|ids| ids.is_empty()) {
+        return Err(anyhow!("Pass 'ids', 'where_filter' or 'where_document' to choose the documents to delete."));
+    }
+    if let Some(ids) = &request.ids {
+        check_unique_ids(ids)?;
+    }
+    let permanent = request.permanent.unwrap_or(false);
+    if permanent {
+        trash::check_hard_delete()?;
+    }
+    
+    policy::check_write(&request.collection_name)?;
     let client = get_client();
     let collection = client.get_collection(&request.collection_name)?;
+    let ids = ids_to_delete(&collection, &request)?;
     
-    collection.delete(request.ids.clone())?;
+    if request.dry_run.unwrap_or(false) {
+        let mut report = DryRunReport::new(&request.collection_name, Operation::Deleted);
+        if request.has_filter() {
+            report.ids = ids;
+        } else {
+            (report.ids, report.missing_ids) = partition_existing(&collection, &ids)?;
+        }
+        report.count = report.ids.len();
+        report.permanent = Some(permanent);
+        return Ok(Outcome::DryRun(report));
+    }
+    
+    if ids.is_empty() {
+        return Ok(Outcome::Done(DocumentsChange {
+            collection_name: request.collection_name,
+            operation: Operation::Deleted,
+            count: 0,
+            ids,
+            trash_id: None,
+        }));
+    }
     
-    Ok(format!(
-        "Successfully deleted {} documents from collection '{}'",
-        request.ids.len(),
-        request.collection_name
-    ))
+    let token = request.confirmation_token.as_deref();
+    if let Some(confirmation) = confirm::check("chroma_delete_documents", ids.len(), &request, &ids, token)? {
+        let sample_ids = ids.iter().take(PREVIEW_SAMPLE_SIZE).cloned().collect();
+        let include = include_names(&[Include::Documents, Include::Metadatas]);
+        let sample = collection.get(Some(sample_ids), None, None, include, None, None)?;
+        return Ok(Outcome::Preview(DeletePreview {
+            collection_name: request.collection_name,
+            permanent,
+            count: ids.len(),
+            sample: records_from_page(&sample)?,
+            confirmation,
+        }));
+    }
+    
+    let trash_id = if permanent {
+        collection.delete(ids.clone())?;
+        None
+    } else {
+        Some(trash::trash_documents(&collection, ids.clone())?.trash_id)
+    };
+    
+    Ok(Outcome::Done(DocumentsChange {
+        collection_name: request.collection_name,
+        operation: Operation::Deleted,
+        count: ids.len(),
+        ids,
+        trash_id,
+    }))
+}
+
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct IngestPathRequest {
+    /// Name of the collection
+    pub collection_name: String,
+    /// File or directory to ingest
+    pub path: String,
+    /// Glob patterns selecting files to ingest, relative to the path
+    pub globs: Option<Vec<String>>,
+    /// Files larger than this many bytes are skipped
+    #[schemars(range(min = 1))]
+    pub max_file_size: Option<u64>,
+    /// Maximum number of characters per chunk
+    #[schemars(range(min = 1))]
+    pub chunk_size: Option<usize>,
+    /// Number of characters shared between consecutive chunks
+    pub chunk_overlap: Option<usize>,
+    /// Split Rust, TypeScript and JavaScript sources on functions, impls, classes and modules instead of plain text windows (default true)
+    pub code_chunking: Option<bool>,
+}
+
+impl From<IngestArgs> for IngestPathRequest {
+    fn from(args: IngestArgs) -> Self {
+        Self {
+            collection_name: args.collection_name,
+            path: args.path.display().to_string(),
+            globs: Some(args.globs),
+            max_file_size: args.max_file_size,
+            chunk_size: args.chunk_size,
+            chunk_overlap: args.chunk_overlap,
+            code_chunking: Some(!args.no_code_chunking),
+        }
+    }
+}
+
+pub async fn chroma_ingest_path(request: IngestPathRequest) -> Result<IngestReport> {
+    if request.path.is_empty() {
+        return Err(anyhow!("The 'path' cannot be empty."));
+    }
+    ingest::check_allowed(Path::new(&request.path))?;
+    cancel::blocking(move || run_ingest(request)).await
+}
+
+/// Ingests without the `--ingest-root` check, for the `ingest` subcommand
+/// whose path is named by the operator.
+pub fn run_ingest(request: IngestPathRequest) -> Result<IngestReport> {
+    let options = IngestOptions {
+        path: request.path.into(),
+        globs: request.globs.unwrap_or_default(),
+        max_file_size: request.max_file_size.unwrap_or(ingest::DEFAULT_MAX_FILE_SIZE),
+        chunk_size: request.chunk_size.unwrap_or(ingest::DEFAULT_CHUNK_SIZE),
+        chunk_overlap: request.chunk_overlap.unwrap_or(ingest::DEFAULT_CHUNK_OVERLAP),
+        code_chunking: request.code_chunking.unwrap_or(true),
+    };
+
+    policy::check_write(&request.collection_name)?;
+    let client = get_client();
+    let collection = client.get_collection(&request.collection_name)?;
+    ingest::ingest_path(&collection, &options)
+}
+
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ExportCollectionRequest {
+    /// Name of the collection to export
+    pub collection_name: String,
+    /// Path of the JSONL file to write, relative to the server's export directory
+    pub output_path: String,
+    /// Whether to include embeddings in the dump (default false)
+    pub include_embeddings: Option<bool>,
+    /// Number of records fetched per page
+    #[schemars(range(min = 1))]
+    pub page_size: Option<usize>,
+    /// Whether to replace the file if it already exists (default false)
+    pub overwrite: Option<bool>,
+}
+
+impl From<ExportArgs> for ExportCollectionRequest {
+    fn from(args: ExportArgs) -> Self {
+        Self {
+            collection_name: args.collection_name,
+            output_path: args.output_path.display().to_string(),
+            include_embeddings: Some(args.include_embeddings),
+            page_size: args.page_size,
+            overwrite: Some(args.overwrite),
+        }
+    }
+}
+
+pub async fn chroma_export_collection(mut request: ExportCollectionRequest) -> Result<ExportReport> {
+    if request.output_path.is_empty() {
+        return Err(anyhow!("The 'output_path' cannot be empty."));
+    }
+    request.output_path = dump::resolve_export_path(Path::new(&request.output_path))?
+        .display()
+        .to_string();
+    cancel::blocking(move || run_export(request)).await
+}
+
+/// Exports without the `--export-dir` check, for the `export` subcommand
+/// whose path is named by the operator.
+pub fn run_export(request: ExportCollectionRequest) -> Result<ExportReport> {
+    if request.output_path.is_empty() {
+        return Err(anyhow!("The 'output_path' cannot be empty."));
+    }
+
+    policy::check_read(&request.collection_name)?;
+    let client = get_client();
+    let collection = client.get_collection(&request.collection_name)?;
+    dump::export_collection(
+        &collection,
+        request.output_path.as_ref(),
+        request.include_embeddings.unwrap_or(false),
+        request.page_size.unwrap_or(dump::DEFAULT_PAGE_SIZE),
+        request.overwrite.unwrap_or(false),
+    )
+}
+
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ImportCollectionRequest {
+    /// Path of the JSONL dump to read
+    pub input_path: String,
+    /// Target collection name (defaults to the name stored in the dump)
+    pub collection_name: Option<String>,
+    /// Line number to resume from; earlier records are skipped
+    #[schemars(range(min = 1))]
+    pub start_line: Option<usize>,
+    /// What to do with records whose id already exists (default skip)
+    pub on_conflict: Option<ConflictPolicy>,
+    /// Number of records upserted per batch
+    #[schemars(range(min = 1))]
+    pub batch_size: Option<usize>,
+}
+
+impl From<ImportArgs> for ImportCollectionRequest {
+    fn from(args: ImportArgs) -> Self {
+        Self {
+            input_path: args.input_path.display().to_string(),
+            collection_name: args.collection_name,
+            start_line: args.start_line,
+            on_conflict: Some(args.on_conflict),
+            batch_size: args.batch_size,
+        }
+    }
+}
+
+pub async fn chroma_import_collection(mut request: ImportCollectionRequest) -> Result<ImportReport> {
+    if request.input_path.is_empty() {
+        return Err(anyhow!("The 'input_path' cannot be empty."));
+    }
+    request.input_path = dump::resolve_import_path(Path::new(&request.input_path))?
+        .display()
+        .to_string();
+    cancel::blocking(move || run_import(request)).await
+}
+
+/// Imports without the `--import-dir` check, for the `import` subcommand
+/// whose path is named by the operator.
+pub fn run_import(request: ImportCollectionRequest) -> Result<ImportReport> {
+    if request.input_path.is_empty() {
+        return Err(anyhow!("The 'input_path' cannot be empty."));
+    }
+
+    let options = ImportOptions {
+        input_path: request.input_path.into(),
+        collection_name: request.collection_name,
+        start_line: request.start_line.unwrap_or(1),
+        on_conflict: request.on_conflict.unwrap_or_default(),
+        batch_size: request.batch_size.unwrap_or(dump::DEFAULT_IMPORT_BATCH_SIZE),
+    };
+
+    let client = get_client();
+    dump::import_collection(&client, &options)
+}
+
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct CloneCollectionRequest {
+    /// Name of the collection to copy
+    pub source_collection: String,
+    /// Name of the collection to create
+    pub target_collection: String,
+    /// Backend profile to create the copy on (defaults to the current backend)
+    pub target_profile: Option<String>,
+    /// Re-embed documents with this embedding function instead of copying embeddings
+    pub embedding_function_name: Option<String>,
+    /// Number of records copied per batch
+    #[schemars(range(min = 1))]
+    pub batch_size: Option<usize>,
+}
+
+pub async fn chroma_clone_collection(request: CloneCollectionRequest) -> Result<CloneReport> {
+    if request.target_collection.is_empty() {
+        return Err(anyhow!("The 'target_collection' cannot be empty."));
+    }
+
+    policy::check_read(&request.source_collection)?;
+    policy::check_write(&request.target_collection)?;
+    let source_client = get_client();
+    let target_client = match &request.target_profile {
+        Some(profile) => client_for_profile(profile)?,
+        None => (*source_client).clone(),
+    };
+
+    let options = CloneOptions {
+        source_collection: request.source_collection,
+        target_collection: request.target_collection,
+        target_profile: request.target_profile,
+        embedding_function_name: request.embedding_function_name,
+        batch_size: request.batch_size.unwrap_or(dump::DEFAULT_PAGE_SIZE),
+    };
+    dump::clone_collection(&source_client, &target_client, &options)
+}
+
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ListTrashRequest {
+    /// Only list entries deleted from this collection
+    pub collection_name: Option<String>,
 }
 
+pub async fn chroma_list_trash(request: ListTrashRequest) -> Result<TrashList> {
+    let entries: Vec<TrashEntry> = trash::list()?
+        .into_iter()
+        .filter(|entry| policy::is_visible(&entry.collection_name))
+        .filter(|entry| request.collection_name.as_ref().is_none_or(|name| *name == entry.collection_name))
+        .collect();
+    Ok(TrashList {
+        count: entries.len(),
+        entries,
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct RestoreRequest {
+    /// Trash entry to restore, as returned by the delete or chroma_list_trash
+    pub trash_id: String,
+    /// Collection to restore into (defaults to the collection it was deleted from)
+    pub collection_name: Option<String>,
+    /// What to do with records whose id already exists
+    pub on_conflict: Option<ConflictPolicy>,
+}
+
+/// Collections of the trash entries a restore or purge names, and the one a
+/// restore writes to.
+fn trash_collections(arguments: &Value) -> Vec<String> {
+    let mut names = registry::collection_arguments(arguments);
+    let ids = arguments.get("trash_id").into_iter().chain(
+        arguments
+            .get("trash_ids")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten(),
+    );
+    for entry in ids.filter_map(Value::as_str).filter_map(|id| trash::entry(id).ok()) {
+        if !names.contains(&entry.collection_name) {
+            names.push(entry.collection_name);
+        }
+    }
+    names
+}
+
+pub async fn chroma_restore(request: RestoreRequest) -> Result<RestoreReport> {
+    let entry = trash::entry(&request.trash_id)?;
+    policy::check_read(&entry.collection_name)?;
+    policy::check_write(request.collection_name.as_ref().unwrap_or(&entry.collection_name))?;
+    let client = get_client();
+    trash::restore(&client, &entry, request.collection_name, request.on_conflict.unwrap_or_default())
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct PurgeRequest {
+    /// Trash entries to delete permanently
+    #[schemars(length(min = 1))]
+    pub trash_ids: Vec<String>,
+}
+
+pub async fn chroma_purge(request: PurgeRequest) -> Result<PurgeReport> {
+    if request.trash_ids.is_empty() {
+        return Err(anyhow!("The 'trash_ids' list cannot be empty."));
+    }
+
+    let entries = request
+        .trash_ids
+        .iter()
+        .map(|id| trash::entry(id))
+        .collect::<Result<Vec<_>>>()?;
+    for entry in &entries {
+        policy::check_write(&entry.collection_name)?;
+    }
+    trash::purge(&entries)
+}
+
+const DEFAULT_AUDIT_LIMIT: usize = 50;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct AuditQueryRequest {
+    /// Only calls of this tool
+    pub tool: Option<String>,
+    /// Only calls on this collection
+    pub collection_name: Option<String>,
+    /// Only calls with this outcome
+    pub outcome: Option<AuditOutcome>,
+    /// Only calls from the client with this name, as sent in `initialize`
+    pub client_name: Option<String>,
+    /// Only calls that changed the record with this id
+    pub id: Option<String>,
+    /// Only calls at or after this Unix time, in seconds
+    pub since: Option<u64>,
+    /// Maximum number of entries to return
+    #[schemars(range(min = 1))]
+    pub limit: Option<usize>,
+}
 
 #[derive(Debug, Serialize, Deserialize)]
+pub struct AuditLog {
+    pub entries: Vec<AuditEntry>,
+    pub count: usize,
+}
+
+impl ToolOutput for AuditLog {
+    fn summary(&self) -> String {
+        let mut summary = format!("{} audit entries", self.count);
+        for entry in &self.entries {
+            summary.push('\n');
+            summary.push_str(&entry.summary());
+        }
+        summary
+    }
+}
+
+pub async fn chroma_audit_query(request: AuditQueryRequest) -> Result<AuditLog> {
+    let filter = AuditFilter {
+        tool: request.tool,
+        collection_name: request.collection_name,
+        outcome: request.outcome,
+        client_name: request.client_name,
+        id: request.id,
+        since: request.since,
+    };
+    let visible = |entry: &AuditEntry| entry.collections.iter().all(|name| policy::is_visible(name));
+    let limit = request.limit.unwrap_or(DEFAULT_AUDIT_LIMIT).max(1);
+    let entries = tokio::task::spawn_blocking(move || audit::search(&filter, visible, limit)).await??;
+    Ok(AuditLog {
+        count: entries.len(),
+        entries,
+    })
+}
+
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct ThoughtData {
+    /// Session identifier
     pub session_id: String,
+    /// Content of the current thought
     pub thought: String,
+    /// Number of this thought in the sequence
+    #[schemars(range(min = 1))]
     pub thought_number: usize,
+    /// Total expected thoughts
+    #[schemars(range(min = 1))]
     pub total_thoughts: usize,
+    /// Whether another thought is needed
     pub next_thought_needed: bool,
+    /// Whether this thought revises an earlier one
     pub is_revision: Option<bool>,
+    /// Number of the thought being revised
+    #[schemars(range(min = 1))]
     pub revises_thought: Option<usize>,
+    /// Number of the thought this branch starts from
+    #[schemars(range(min = 1))]
     pub branch_from_thought: Option<usize>,
+    /// Identifier of the branch
     pub branch_id: Option<String>,
+    /// Whether more thoughts are needed than originally estimated
     pub needs_more_thoughts: Option<bool>,
 }
 
@@ -290,6 +1688,21 @@ pub struct ThoughtResponse {
     pub status: Option<String>,
 }
 
+impl ToolOutput for ThoughtResponse {
+    fn summary(&self) -> String {
+        match &self.error {
+            Some(error) => format!("Thought {} of session '{}' failed: {}", self.thought_number, self.session_id, error),
+            None => format!(
+                "Thought {}/{} recorded for session '{}'{}",
+                self.thought_number,
+                self.total_thoughts,
+                self.session_id,
+                if self.next_thought_needed { "; next thought needed" } else { "" }
+            ),
+        }
+    }
+}
+
 fn validate_thought_data(input_data: &ThoughtData) -> Result<()> {
     if input_data.session_id.is_empty() {
         return Err(anyhow!("Invalid sessionId: must be provided"));
@@ -327,217 +1740,593 @@ pub async fn process_thought(input_data: ThoughtData) -> Result<ThoughtResponse>
                 thought_number: input_data.thought_number,
                 total_thoughts: input_data.total_thoughts,
                 next_thought_needed: input_data.next_thought_needed,
-                error: Some(e.to_string()),
+                error: Some(secret::scrub(&e.to_string())),
                 status: Some("failed".to_string()),
             })
         }
     }
 }
 
-pub fn get_tool_definitions() -> Vec<Tool> {
-    let mut tools = Vec::new();
-    
-    let add_tool = |tools: &mut Vec<Tool>, name: &str, description: &str, schema: Value| {
-        tools.push(Tool {
-            name: name.to_string(),
-            description: description.to_string(),
-            input_schema: schema,
-        });
-    };
-    
-    add_tool(
-        &mut tools,
+pub fn tool_registry() -> ToolRegistry {
+    let mut registry = ToolRegistry::new();
+
+    registry.register(ToolSpec::new(
         "chroma_list_collections",
         "Lists all collections in the ChromaDB instance",
-        serde_json::to_value(serde_json::json!({
-            "type": "object", 
-            "properties": {
-                "limit": {"type": "integer", "description": "Maximum number of collections to return"},
-                "offset": {"type": "integer", "description": "Offset for pagination"}
-            }
-        })).unwrap()
-    );
-    
-    add_tool(
-        &mut tools,
+        ToolGroup::Collections,
+        ToolAnnotations::READ_ONLY,
+        chroma_list_collections,
+    ));
+
+    registry.register(ToolSpec::new(
         "chroma_create_collection",
         "Creates a new collection in ChromaDB",
-        serde_json::to_value(serde_json::json!({
-            "type": "object", 
-            "required": ["collection_name"],
-            "properties": {
-                "collection_name": {"type": "string", "description": "Name of the collection to create"},
-                "metadata": {"type": "object", "description": "Optional metadata for the collection"},
-                "embedding_function_name": {"type": "string", "description": "Name of the embedding function to use"}
-            }
-        })).unwrap()
-    );
-    
-    add_tool(
-        &mut tools,
+        ToolGroup::Collections,
+        ToolAnnotations::ADDITIVE,
+        chroma_create_collection,
+    ));
+
+    registry.register(ToolSpec::new(
         "chroma_peek_collection",
         "Shows a sample of documents in a collection",
-        serde_json::to_value(serde_json::json!({
-            "type": "object", 
-            "required": ["collection_name", "limit"],
-            "properties": {
-                "collection_name": {"type": "string", "description": "Name of the collection to peek"},
-                "limit": {"type": "integer", "description": "Number of documents to return"}
-            }
-        })).unwrap()
-    );
-    
-    add_tool(
-        &mut tools,
+        ToolGroup::Collections,
+        ToolAnnotations::READ_ONLY,
+        chroma_peek_collection,
+    ));
+
+    registry.register(ToolSpec::new(
         "chroma_get_collection_info",
         "Gets metadata about a collection",
-        serde_json::to_value(serde_json::json!({
-            "type": "object", 
-            "required": ["collection_name"],
-            "properties": {
-                "collection_name": {"type": "string", "description": "Name of the collection"}
-            }
-        })).unwrap()
-    );
-    
-    add_tool(
-        &mut tools,
+        ToolGroup::Collections,
+        ToolAnnotations::READ_ONLY,
+        chroma_get_collection_info,
+    ));
+
+    registry.register(ToolSpec::new(
         "chroma_get_collection_count",
         "Counts the number of documents in a collection",
-        serde_json::to_value(serde_json::json!({
-            "type": "object", 
-            "required": ["collection_name"],
-            "properties": {
-                "collection_name": {"type": "string", "description": "Name of the collection"}
-            }
-        })).unwrap()
-    );
-    
-    add_tool(
-        &mut tools,
+        ToolGroup::Collections,
+        ToolAnnotations::READ_ONLY,
+        chroma_get_collection_count,
+    ));
+
+    registry.register(ToolSpec::new(
         "chroma_modify_collection",
-        "Modifies collection properties",
-        serde_json::to_value(serde_json::json!({
-            "type": "object", 
-            "required": ["collection_name"],
-            "properties": {
-                "collection_name": {"type": "string", "description": "Name of the collection to modify"},
-                "new_name": {"type": "string", "description": "New name for the collection"},
-                "new_metadata": {"type": "object", "description": "New metadata for the collection"}
-            }
-        })).unwrap()
-    );
-    
-    add_tool(
-        &mut tools,
+        "Renames a collection, replaces its metadata or changes its HNSW search settings. Metadata values must be strings, numbers or booleans",
+        ToolGroup::Collections,
+        ToolAnnotations::DESTRUCTIVE,
+        chroma_modify_collection,
+    ));
+
+    registry.register(ToolSpec::new(
         "chroma_delete_collection",
         "Deletes a collection",
-        serde_json::to_value(serde_json::json!({
-            "type": "object", 
-            "required": ["collection_name"],
-            "properties": {
-                "collection_name": {"type": "string", "description": "Name of the collection to delete"}
-            }
-        })).unwrap()
-    );
-    
-    add_tool(
-        &mut tools,
+        ToolGroup::Collections,
+        ToolAnnotations::DESTRUCTIVE,
+        chroma_delete_collection,
+    ));
+
+    registry.register(ToolSpec::new(
         "chroma_add_documents",
-        "Adds documents to a collection",
-        serde_json::to_value(serde_json::json!({
-            "type": "object", 
-            "required": ["collection_name", "documents"],
-            "properties": {
-                "collection_name": {"type": "string", "description": "Name of the collection"},
-                "documents": {"type": "array", "items": {"type": "string"}, "description": "List of documents to add"},
-                "metadatas": {"type": "array", "items": {"type": "object"}, "description": "List of metadata objects for documents"},
-                "ids": {"type": "array", "items": {"type": "string"}, "description": "List of IDs for documents"}
-            }
-        })).unwrap()
-    );
-    
-    add_tool(
-        &mut tools,
+        "Adds documents to a collection. Ids must be unique within the call and metadata values must be strings, numbers or booleans",
+        ToolGroup::Documents,
+        ToolAnnotations::ADDITIVE,
+        chroma_add_documents,
+    ));
+
+    registry.register(ToolSpec::new(
         "chroma_query_documents",
         "Searches for similar documents in a collection",
-        serde_json::to_value(serde_json::json!({
-            "type": "object", 
-            "required": ["collection_name", "query_texts"],
-            "properties": {
-                "collection_name": {"type": "string", "description": "Name of the collection"},
-                "query_texts": {"type": "array", "items": {"type": "string"}, "description": "List of query texts"},
-                "n_results": {"type": "integer", "description": "Number of results to return per query"},
-                "where_filter": {"type": "object", "description": "Filter by metadata"},
-                "where_document": {"type": "object", "description": "Filter by document content"}
-            }
-        })).unwrap()
-    );
-    
-    add_tool(
-        &mut tools,
+        ToolGroup::Documents,
+        ToolAnnotations::READ_ONLY,
+        chroma_query_documents,
+    ));
+
+    registry.register(ToolSpec::new(
         "chroma_get_documents",
         "Retrieves documents from a collection",
-        serde_json::to_value(serde_json::json!({
-            "type": "object", 
-            "required": ["collection_name"],
-            "properties": {
-                "collection_name": {"type": "string", "description": "Name of the collection"},
-                "ids": {"type": "array", "items": {"type": "string"}, "description": "List of document IDs to retrieve"},
-                "where_filter": {"type": "object", "description": "Filter by metadata"},
-                "where_document": {"type": "object", "description": "Filter by document content"},
-                "limit": {"type": "integer", "description": "Maximum number of documents to return"},
-                "offset": {"type": "integer", "description": "Offset for pagination"}
-            }
-        })).unwrap()
-    );
-    
-    add_tool(
-        &mut tools,
+        ToolGroup::Documents,
+        ToolAnnotations::READ_ONLY,
+        chroma_get_documents,
+    ));
+
+    registry.register(ToolSpec::new(
         "chroma_update_documents",
-        "Updates documents in a collection",
-        serde_json::to_value(serde_json::json!({
-            "type": "object", 
-            "required": ["collection_name", "ids"],
-            "properties": {
-                "collection_name": {"type": "string", "description": "Name of the collection"},
-                "ids": {"type": "array", "items": {"type": "string"}, "description": "List of document IDs to update"},
-                "documents": {"type": "array", "items": {"type": "string"}, "description": "List of document contents"},
-                "metadatas": {"type": "array", "items": {"type": "object"}, "description": "List of metadata objects"}
-            }
-        })).unwrap()
-    );
-    
-    add_tool(
-        &mut tools,
+        "Updates documents in a collection. Ids must be unique within the call and metadata values must be strings, numbers, booleans or null, which removes the key",
+        ToolGroup::Documents,
+        ToolAnnotations::DESTRUCTIVE_IDEMPOTENT,
+        chroma_update_documents,
+    ));
+
+    registry.register(ToolSpec::new(
+        "chroma_patch_metadata",
+        "Sets or removes metadata keys on every document matching a filter, without re-sending documents",
+        ToolGroup::Documents,
+        ToolAnnotations::DESTRUCTIVE_IDEMPOTENT,
+        chroma_patch_metadata,
+    ));
+
+    registry.register(ToolSpec::new(
         "chroma_delete_documents",
-        "Deletes documents from a collection",
-        serde_json::to_value(serde_json::json!({
-            "type": "object", 
-            "required": ["collection_name", "ids"],
-            "properties": {
-                "collection_name": {"type": "string", "description": "Name of the collection"},
-                "ids": {"type": "array", "items": {"type": "string"}, "description": "List of document IDs to delete"}
-            }
-        })).unwrap()
-    );
-    
-    add_tool(
-        &mut tools,
+        "Deletes documents from a collection by id or by filter. Ids must be unique within the call",
+        ToolGroup::Documents,
+        ToolAnnotations::DESTRUCTIVE,
+        chroma_delete_documents,
+    ));
+
+    registry.register(ToolSpec::new(
+        "chroma_ingest_path",
+        "Ingests local files into a collection, skipping unchanged files and removing chunks of deleted files",
+        ToolGroup::Ingest,
+        ToolAnnotations::DESTRUCTIVE,
+        chroma_ingest_path,
+    ));
+
+    registry.register(ToolSpec::new(
+        "chroma_export_collection",
+        "Exports a collection's configuration and records to a newline-delimited JSON file",
+        ToolGroup::Backup,
+        ToolAnnotations::DESTRUCTIVE,
+        chroma_export_collection,
+    ));
+
+    registry.register(ToolSpec::new(
+        "chroma_import_collection",
+        "Imports a JSONL dump produced by chroma_export_collection, creating the collection if needed",
+        ToolGroup::Backup,
+        ToolAnnotations::DESTRUCTIVE,
+        chroma_import_collection,
+    ));
+
+    registry.register(ToolSpec::new(
+        "chroma_clone_collection",
+        "Copies a collection into a new collection, optionally on another backend or with a different embedding function",
+        ToolGroup::Backup,
+        ToolAnnotations::ADDITIVE,
+        chroma_clone_collection,
+    ));
+
+    registry.register(ToolSpec::new(
+        "chroma_list_trash",
+        "Lists deleted collections and records held in the trash until they expire",
+        ToolGroup::Trash,
+        ToolAnnotations::READ_ONLY,
+        chroma_list_trash,
+    ));
+
+    registry.register(ToolSpec::new(
+        "chroma_restore",
+        "Restores a deleted collection or set of records from the trash",
+        ToolGroup::Trash,
+        ToolAnnotations::ADDITIVE,
+        chroma_restore,
+    ).with_collections(trash_collections));
+
+    registry.register(ToolSpec::new(
+        "chroma_purge",
+        "Permanently deletes entries from the trash",
+        ToolGroup::Trash,
+        ToolAnnotations::DESTRUCTIVE,
+        chroma_purge,
+    ).with_collections(trash_collections));
+
+    registry.register(ToolSpec::new(
+        "chroma_audit_query",
+        "Searches recent entries of the audit log of tool calls, newest first",
+        ToolGroup::Audit,
+        ToolAnnotations::READ_ONLY,
+        chroma_audit_query,
+    ));
+
+    registry.register(ToolSpec::new(
         "process_thought",
         "Processes a thought in an ongoing session",
-        serde_json::to_value(serde_json::json!({
-            "type": "object", 
-            "required": ["session_id", "thought", "thought_number", "total_thoughts", "next_thought_needed"],
-            "properties": {
-                "session_id": {"type": "string", "description": "Session identifier"},
-                "thought": {"type": "string", "description": "Content of the current thought"},
-                "thought_number": {"type": "integer", "description": "Number of this thought in the sequence"},
-                "total_thoughts": {"type": "integer", "description": "Total expected thoughts"},
-                "next_thought_needed": {"type": "boolean", "description": "Whether another thought is needed"}
+        ToolGroup::Thoughts,
+        ToolAnnotations::READ_ONLY,
+        process_thought,
+    ));
+
+    registry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::de::DeserializeOwned;
+    use serde_json::json;
+    use std::collections::BTreeSet;
+
+    fn example(schema: &Value) -> Value {
+        if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+            return values[0].clone();
+        }
+        if let Some(variants) = schema.get("anyOf").and_then(Value::as_array) {
+            return variants.iter().map(example).find(|v| !v.is_null()).unwrap_or(Value::Null);
+        }
+
+        let kind = match &schema["type"] {
+            Value::String(kind) => kind.as_str(),
+            Value::Array(kinds) => kinds.iter().filter_map(Value::as_str).find(|k| *k != "null").unwrap_or("null"),
+            _ => "object",
+        };
+        match kind {
+            "string" => json!("example"),
+            "integer" => json!(schema["minimum"].as_f64().unwrap_or(1.0).max(1.0) as u64),
+            "number" => json!(schema["minimum"].as_f64().unwrap_or(1.0)),
+            "boolean" => json!(true),
+            "array" => json!([example(&schema["items"])]),
+            "object" => {
+                let properties = schema["properties"].as_object().cloned().unwrap_or_default();
+                Value::Object(properties.iter().map(|(k, v)| (k.clone(), example(v))).collect())
             }
-        })).unwrap()
-    );
-    
-    tools
+            _ => Value::Null,
+        }
+    }
+
+    fn assert_schema_matches<T: Serialize + DeserializeOwned>(tool_name: &str) {
+        let registry = tool_registry();
+        let tool = registry
+            .get(tool_name)
+            .unwrap_or_else(|| panic!("{}: tool is not defined", tool_name));
+        let schema = &tool.input_schema;
+        let arguments = example(schema);
+
+        let request: T = serde_json::from_value(arguments.clone())
+            .unwrap_or_else(|e| panic!("{}: schema example does not deserialize: {}", tool_name, e));
+        let round_trip = serde_json::to_value(&request).unwrap();
+
+        let declared: BTreeSet<&String> = schema["properties"].as_object().unwrap().keys().collect();
+        let fields: BTreeSet<&String> = round_trip.as_object().unwrap().keys().collect();
+        assert_eq!(declared, fields, "{}: schema properties and struct fields differ", tool_name);
+
+        for (key, value) in arguments.as_object().unwrap() {
+            assert_eq!(&round_trip[key], value, "{}: '{}' was not read from the arguments", tool_name, key);
+
+            let mut partial = arguments.clone();
+            partial.as_object_mut().unwrap().remove(key);
+            let required = schema["required"]
+                .as_array()
+                .is_some_and(|required| required.contains(&json!(key)));
+            assert_eq!(
+                serde_json::from_value::<T>(partial).is_err(),
+                required,
+                "{}: '{}' is {}required in the schema but not in the struct",
+                tool_name,
+                key,
+                if required { "" } else { "not " }
+            );
+        }
+    }
+
+    #[test]
+    fn collection_configuration_sends_only_set_fields() {
+        assert_eq!(collection_configuration(HnswConfiguration::default(), None).unwrap(), None);
+
+        let hnsw = HnswConfiguration {
+            space: Some(Space::Cosine),
+            ef_search: Some(64),
+            ..Default::default()
+        };
+        assert_eq!(
+            collection_configuration(hnsw, Some("openai")).unwrap(),
+            Some(json!({"hnsw": {"space": "cosine", "ef_search": 64}, "embedding_function": "openai"}))
+        );
+    }
+
+    fn request<T: DeserializeOwned>(arguments: Value) -> T {
+        serde_json::from_value(arguments).unwrap()
+    }
+
+    fn dry_run<T: std::fmt::Debug>(outcome: Result<Outcome<T>>) -> DryRunReport {
+        match outcome.unwrap() {
+            Outcome::DryRun(report) => report,
+            other => panic!("expected a dry run report, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn dry_run_add_splits_new_and_existing_ids() {
+        let report = dry_run(
+            chroma_add_documents(request(json!({
+                "collection_name": "notes",
+                "documents": ["a", "b"],
+                "ids": ["doc1", "new"],
+                "dry_run": true,
+            })))
+            .await,
+        );
+        assert_eq!(report.operation, Operation::Added);
+        assert_eq!((report.count, report.ids, report.existing_ids), (1, vec!["new".to_string()], vec!["doc1".to_string()]));
+    }
+
+    #[tokio::test]
+    async fn dry_run_update_lists_missing_ids() {
+        let report = dry_run(
+            chroma_update_documents(request(json!({
+                "collection_name": "notes",
+                "ids": ["doc2", "gone"],
+                "metadatas": [{"topic": null}, {"topic": "x"}],
+                "dry_run": true,
+            })))
+            .await,
+        );
+        assert_eq!(report.operation, Operation::Updated);
+        assert_eq!((report.count, report.ids, report.missing_ids), (1, vec!["doc2".to_string()], vec!["gone".to_string()]));
+    }
+
+    #[tokio::test]
+    async fn dry_run_delete_reports_ids_and_trash_use() {
+        let by_id = dry_run(
+            chroma_delete_documents(request(json!({
+                "collection_name": "notes",
+                "ids": ["doc1", "gone"],
+                "dry_run": true,
+            })))
+            .await,
+        );
+        assert_eq!((by_id.count, by_id.ids, by_id.missing_ids), (1, vec!["doc1".to_string()], vec!["gone".to_string()]));
+        assert_eq!(by_id.permanent, Some(false));
+
+        let by_filter = dry_run(
+            chroma_delete_documents(request(json!({
+                "collection_name": "notes",
+                "where_filter": {"source": "test1"},
+                "max_affected": 5,
+                "dry_run": true,
+            })))
+            .await,
+        );
+        assert_eq!((by_filter.count, by_filter.ids), (2, vec!["doc1".to_string(), "doc2".to_string()]));
+    }
+
+    #[tokio::test]
+    async fn dry_run_modify_lists_the_aspects_it_would_send() {
+        let report = dry_run(
+            chroma_modify_collection(request(json!({
+                "collection_name": "notes",
+                "new_name": "notes_v2",
+                "ef_search": 50,
+                "dry_run": true,
+            })))
+            .await,
+        );
+        assert_eq!(report.operation, Operation::Modified);
+        assert_eq!(report.changed, ["name", "hnsw"]);
+
+        let report = dry_run(
+            chroma_modify_collection(request(json!({
+                "collection_name": "notes",
+                "new_metadata": {"owner": "docs"},
+                "dry_run": true,
+            })))
+            .await,
+        );
+        assert_eq!(report.changed, ["metadata"]);
+    }
+
+    #[tokio::test]
+    async fn writes_are_validated_like_dry_runs() {
+        for dry_run in [true, false] {
+            let duplicate = chroma_add_documents(request(json!({
+                "collection_name": "notes",
+                "documents": ["a", "b"],
+                "ids": ["x", "x"],
+                "dry_run": dry_run,
+            })))
+            .await;
+            assert_eq!(duplicate.unwrap_err().to_string(), "Duplicate id 'x' in the 'ids' list.");
+
+            let nested = chroma_add_documents(request(json!({
+                "collection_name": "notes",
+                "documents": ["a"],
+                "metadatas": [{"tags": ["a"]}],
+                "dry_run": dry_run,
+            })))
+            .await;
+            assert_eq!(nested.unwrap_err().to_string(), "Metadata 0 field 'tags' must be a string, number or boolean.");
+        }
+    }
+
+    fn preview<T: std::fmt::Debug>(outcome: Result<Outcome<T>>) -> DeletePreview {
+        match outcome.unwrap() {
+            Outcome::Preview(preview) => preview,
+            other => panic!("expected a delete preview, got {:?}", other),
+        }
+    }
+
+    /// A collection in the in-memory backend holding `count` records from
+    /// the source "old".
+    fn old_records(name: &str, count: usize) -> Collection {
+        let client = get_client();
+        client.create_collection(name, None, None).unwrap();
+        let collection = client.get_collection(name).unwrap();
+        let ids: Vec<String> = (0..count).map(|index| format!("id{}", index)).collect();
+        let metadatas = ids.iter().map(|_| json!({"source": "old"})).collect();
+        collection.add(ids.clone(), Some(metadatas), ids).unwrap();
+        collection
+    }
+
+    #[tokio::test]
+    async fn delete_tokens_only_confirm_the_previewed_ids() {
+        old_records("delete_token_ids", 24);
+        let ids = |range: std::ops::Range<usize>| range.map(|index| format!("id{}", index)).collect::<Vec<_>>();
+        let first = preview(
+            chroma_delete_documents(request(json!({"collection_name": "delete_token_ids", "ids": ids(0..12)}))).await,
+        );
+
+        let other_ids = chroma_delete_documents(request(json!({
+            "collection_name": "delete_token_ids",
+            "ids": ids(12..24),
+            "confirmation_token": first.confirmation.confirmation_token,
+        })))
+        .await;
+        assert!(other_ids.unwrap_err().to_string().contains("different call"));
+    }
+
+    #[tokio::test]
+    async fn filtered_delete_tokens_are_refused_when_the_matches_change() {
+        let collection = old_records("delete_token_filter", 12);
+        let arguments = json!({
+            "collection_name": "delete_token_filter",
+            "where_filter": {"source": "old"},
+            "max_affected": usize::MAX,
+        });
+        let first = preview(chroma_delete_documents(request(arguments.clone())).await);
+        assert_eq!(first.count, 12);
+
+        collection.add(vec!["late".to_string()], Some(vec![json!({"source": "old"})]), vec!["late".to_string()]).unwrap();
+        let mut confirmed = arguments;
+        confirmed["confirmation_token"] = json!(first.confirmation.confirmation_token);
+        let changed = chroma_delete_documents(request(confirmed)).await;
+        assert!(changed.unwrap_err().to_string().contains("changed since"));
+        assert_eq!(collection.count().unwrap(), 13);
+    }
+
+    #[test]
+    fn patch_changes_only_when_a_key_differs() {
+        let patch = |value: Value| value.as_object().unwrap().clone();
+        let metadata = json!({"source": "old", "stale": true});
+
+        assert!(!patch_changes(Some(&metadata), &patch(json!({"stale": true}))));
+        assert!(!patch_changes(Some(&metadata), &patch(json!({"owner": null}))));
+        assert!(patch_changes(Some(&metadata), &patch(json!({"stale": false}))));
+        assert!(patch_changes(Some(&metadata), &patch(json!({"source": null}))));
+        assert!(patch_changes(None, &patch(json!({"stale": true}))));
+        assert!(!patch_changes(None, &patch(json!({"stale": null}))));
+    }
+
+    #[tokio::test]
+    async fn patch_sets_and_unsets_keys_on_matching_documents() {
+        let collection = old_records("patch_merge", 3);
+        collection.update(vec!["id0".to_string()], None, Some(vec![json!({"stale": true, "owner": "docs"})]), None).unwrap();
+
+        let patch = |dry_run| {
+            request(json!({
+                "collection_name": "patch_merge",
+                "where_filter": {"source": "old"},
+                "set": {"stale": true},
+                "unset": ["owner"],
+                "max_affected": 3,
+                "dry_run": dry_run,
+            }))
+        };
+        assert_eq!(dry_run(chroma_patch_metadata(patch(true)).await).count, 3);
+        let Outcome::Done(change) = chroma_patch_metadata(patch(false)).await.unwrap() else {
+            panic!("expected the patch to run");
+        };
+        assert_eq!(change.count, 3);
+
+        let page = collection.get(None, None, None, vec!["metadatas".to_string()], None, None).unwrap();
+        for metadata in page["metadatas"].as_array().unwrap() {
+            assert_eq!(*metadata, json!({"source": "old", "stale": true}));
+        }
+        let Outcome::Done(again) = chroma_patch_metadata(patch(false)).await.unwrap() else {
+            panic!("expected the patch to run");
+        };
+        assert_eq!(again.count, 0);
+    }
+
+    #[tokio::test]
+    async fn patch_refuses_to_exceed_max_affected() {
+        let collection = old_records("patch_cap", 3);
+        let over = chroma_patch_metadata(request(json!({
+            "collection_name": "patch_cap",
+            "where_filter": {"source": "old"},
+            "set": {"stale": true},
+            "max_affected": 2,
+        })))
+        .await;
+
+        assert!(over.unwrap_err().to_string().contains("more than max_affected (2)"));
+        let page = collection.get(None, None, None, vec!["metadatas".to_string()], None, None).unwrap();
+        assert!(page["metadatas"].as_array().unwrap().iter().all(|metadata| metadata.get("stale").is_none()));
+    }
+
+    type SchemaCheck = (&'static str, fn(&str));
+
+    #[test]
+    fn tool_schemas_match_request_structs() {
+        let checks: &[SchemaCheck] = &[
+            ("chroma_list_collections", assert_schema_matches::<ListCollectionsRequest>),
+            ("chroma_create_collection", assert_schema_matches::<CreateCollectionRequest>),
+            ("chroma_peek_collection", assert_schema_matches::<PeekCollectionRequest>),
+            ("chroma_get_collection_info", assert_schema_matches::<GetCollectionInfoRequest>),
+            ("chroma_get_collection_count", assert_schema_matches::<GetCollectionCountRequest>),
+            ("chroma_modify_collection", assert_schema_matches::<ModifyCollectionRequest>),
+            ("chroma_delete_collection", assert_schema_matches::<DeleteCollectionRequest>),
+            ("chroma_add_documents", assert_schema_matches::<AddDocumentsRequest>),
+            ("chroma_query_documents", assert_schema_matches::<QueryDocumentsRequest>),
+            ("chroma_get_documents", assert_schema_matches::<GetDocumentsRequest>),
+            ("chroma_update_documents", assert_schema_matches::<UpdateDocumentsRequest>),
+            ("chroma_patch_metadata", assert_schema_matches::<PatchMetadataRequest>),
+            ("chroma_delete_documents", assert_schema_matches::<DeleteDocumentsRequest>),
+            ("chroma_ingest_path", assert_schema_matches::<IngestPathRequest>),
+            ("chroma_export_collection", assert_schema_matches::<ExportCollectionRequest>),
+            ("chroma_import_collection", assert_schema_matches::<ImportCollectionRequest>),
+            ("chroma_clone_collection", assert_schema_matches::<CloneCollectionRequest>),
+            ("chroma_list_trash", assert_schema_matches::<ListTrashRequest>),
+            ("chroma_restore", assert_schema_matches::<RestoreRequest>),
+            ("chroma_purge", assert_schema_matches::<PurgeRequest>),
+            ("chroma_audit_query", assert_schema_matches::<AuditQueryRequest>),
+            ("process_thought", assert_schema_matches::<ThoughtData>),
+        ];
+
+        let registry = tool_registry();
+        let defined: BTreeSet<&str> = registry.iter().map(|tool| tool.name).collect();
+        let checked: BTreeSet<&str> = checks.iter().map(|(name, _)| *name).collect();
+        assert_eq!(defined, checked, "every tool needs a schema check");
+
+        for (name, check) in checks {
+            check(name);
+        }
+    }
+
+    /// Adds `count` documents to a new collection `name` and returns the
+    /// progress notifications sent while doing so under `token`.
+    async fn add_progress(name: &str, count: usize, token: Option<Value>) -> Vec<Value> {
+        get_client().create_collection(name, None, None).unwrap();
+        let documents: Vec<String> = (0..count).map(|index| format!("document {}", index)).collect();
+        let (notifier, mut outgoing) = tokio::sync::mpsc::unbounded_channel();
+        let add = chroma_add_documents(request(json!({"collection_name": name, "documents": documents})));
+        let added = progress::with_notifier(notifier, progress::with_progress(token, add)).await;
+        assert!(matches!(added.unwrap(), Outcome::Done(change) if change.count == count));
+
+        let mut sent = Vec::new();
+        while let Ok(mcp_spec::protocol::JsonRpcMessage::Notification(notification)) = outgoing.try_recv() {
+            sent.push(notification.params.unwrap());
+        }
+        sent
+    }
+
+    #[tokio::test]
+    async fn bulk_adds_report_progress_per_batch_when_asked() {
+        let sent = add_progress("progress_add", 2 * ADD_BATCH_SIZE + 50, Some(json!("bulk"))).await;
+        let progress: Vec<(&Value, &Value, &Value)> =
+            sent.iter().map(|params| (&params["progressToken"], &params["progress"], &params["total"])).collect();
+        assert_eq!(
+            progress,
+            [
+                (&json!("bulk"), &json!(100), &json!(250)),
+                (&json!("bulk"), &json!(200), &json!(250)),
+                (&json!("bulk"), &json!(250), &json!(250)),
+            ]
+        );
+        assert!(sent.iter().all(|params| params["message"] == "adding documents"));
+
+        assert!(add_progress("progress_add_silent", 250, None).await.is_empty());
+    }
+
+    #[test]
+    fn calls_name_every_collection_they_touch() {
+        let registry = tool_registry();
+        let collections = |tool: &str, arguments: Value| registry.get(tool).unwrap().collections(&arguments);
+
+        let clone = json!({"source_collection": "notes", "target_collection": "hidden_copy"});
+        assert_eq!(collections("chroma_clone_collection", clone), ["notes", "hidden_copy"]);
+        let rename = json!({"collection_name": "notes", "new_name": "archive"});
+        assert_eq!(collections("chroma_modify_collection", rename), ["notes", "archive"]);
+        let restore = json!({"trash_id": "missing", "collection_name": "notes"});
+        assert_eq!(collections("chroma_restore", restore), ["notes"]);
+        assert!(collections("chroma_list_collections", json!({})).is_empty());
+    }
 }
@@ -0,0 +1,896 @@
+use anyhow::{Context, Result};
+use axum::extract::{Query, Request, State};
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use futures::stream::{self, Stream, StreamExt};
+use mcp_server::router::RouterService;
+use mcp_spec::protocol::{ErrorData, JsonRpcError, JsonRpcMessage, JsonRpcRequest, INTERNAL_ERROR, PARSE_ERROR};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+use tower_service::Service;
+
+use crate::audit::{self, ClientIdentity, SharedClient};
+use crate::cancel::{self, InFlight};
+use crate::progress::{self, Notifier};
+use crate::secret::{self, Secret};
+
+pub const MCP_PATH: &str = "/mcp";
+pub const SSE_PATH: &str = "/sse";
+pub const SSE_MESSAGE_PATH: &str = "/message";
+
+const MCP_SESSION_ID: HeaderName = HeaderName::from_static("mcp-session-id");
+const CANCELLED_NOTIFICATION: &str = "notifications/cancelled";
+/// Streamable HTTP sessions without a request for this long are dropped.
+const HTTP_SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+/// Most streamable HTTP sessions kept; the longest idle is dropped first.
+const MAX_HTTP_SESSIONS: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum TransportKind {
+    /// Newline-delimited JSON-RPC over stdin and stdout
+    Stdio,
+    /// Server-sent events stream with a separate POST endpoint for messages
+    Sse,
+    /// Streamable HTTP: JSON-RPC messages POSTed to a single endpoint
+    Http,
+    /// Newline-delimited JSON-RPC over connections to a Unix domain socket
+    Unix,
+}
+
+#[derive(Debug, Clone)]
+pub struct HttpOptions {
+    pub bind: SocketAddr,
+    pub auth_token: Option<Secret>,
+    pub cors_origins: Vec<String>,
+}
+
+#[cfg(unix)]
+#[derive(Debug, Clone)]
+pub struct SocketOptions {
+    pub path: PathBuf,
+    /// Permission bits applied to the socket file, e.g. `0o600`
+    pub mode: u32,
+}
+
+type SseSessions = Arc<Mutex<HashMap<String, Session>>>;
+type HttpSessions = Arc<Mutex<HashMap<String, HttpSession>>>;
+
+/// A streamable HTTP session started by `initialize`.
+#[derive(Clone)]
+struct HttpSession {
+    in_flight: InFlight,
+    client: SharedClient,
+    last_seen: Instant,
+}
+
+struct AppState<R> {
+    router: R,
+    sessions: SseSessions,
+    http_sessions: HttpSessions,
+}
+
+impl<R: Clone> Clone for AppState<R> {
+    fn clone(&self) -> Self {
+        Self {
+            router: self.router.clone(),
+            sessions: self.sessions.clone(),
+            http_sessions: self.http_sessions.clone(),
+        }
+    }
+}
+
+/// Serves `router` over HTTP until the process is stopped, using the
+/// streamable HTTP or the SSE transport.
+pub async fn serve<R>(router: R, kind: TransportKind, options: HttpOptions) -> Result<()>
+where
+    R: mcp_server::Router + Clone + Send + Sync + 'static,
+{
+    let app = app(router, kind, &options)?;
+    if options.auth_token.is_none() && !options.bind.ip().is_loopback() {
+        tracing::warn!("Serving on {} without --auth-token; any client that can reach it has full access", options.bind);
+    }
+
+    let listener = tokio::net::TcpListener::bind(options.bind)
+        .await
+        .with_context(|| format!("Failed to bind {}", options.bind))?;
+    tracing::info!("Serving MCP over {:?} on http://{}", kind, listener.local_addr()?);
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+/// The routes of the HTTP transport `kind`, behind bearer auth and CORS.
+fn app<R>(router: R, kind: TransportKind, options: &HttpOptions) -> Result<Router>
+where
+    R: mcp_server::Router + Clone + Send + Sync + 'static,
+{
+    let state = AppState {
+        router,
+        sessions: SseSessions::default(),
+        http_sessions: HttpSessions::default(),
+    };
+
+    let routes = match kind {
+        TransportKind::Http => Router::new().route(
+            MCP_PATH,
+            post(handle_http_message::<R>).delete(handle_http_delete::<R>),
+        ),
+        TransportKind::Sse => Router::new()
+            .route(SSE_PATH, get(handle_sse_connect::<R>))
+            .route(SSE_MESSAGE_PATH, post(handle_sse_message::<R>)),
+        TransportKind::Stdio | TransportKind::Unix => unreachable!("{:?} is served by serve_lines", kind),
+    };
+
+    let token = options.auth_token.clone().map(Arc::new);
+    Ok(routes
+        .with_state(state)
+        .layer(middleware::from_fn(move |request: Request, next: Next| {
+            let token = token.clone();
+            async move { authorize(token.as_deref().map(Secret::expose), request, next).await }
+        }))
+        .layer(cors_layer(&options.cors_origins)?))
+}
+
+fn cors_layer(origins: &[String]) -> Result<CorsLayer> {
+    let layer = CorsLayer::new()
+        .allow_methods([Method::GET, Method::POST, Method::DELETE, Method::OPTIONS])
+        .allow_headers([
+            header::AUTHORIZATION,
+            header::CONTENT_TYPE,
+            HeaderName::from_static("mcp-protocol-version"),
+            MCP_SESSION_ID,
+        ])
+        .expose_headers([MCP_SESSION_ID]);
+
+    if origins.iter().any(|origin| origin == "*") {
+        return Ok(layer.allow_origin(Any));
+    }
+    let origins = origins
+        .iter()
+        .map(|origin| HeaderValue::from_str(origin).with_context(|| format!("Invalid CORS origin: {}", origin)))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(layer.allow_origin(AllowOrigin::list(origins)))
+}
+
+async fn authorize(token: Option<&str>, request: Request, next: Next) -> Response {
+    let Some(token) = token else {
+        return next.run(request).await;
+    };
+    if request.method() == Method::OPTIONS {
+        return next.run(request).await;
+    }
+
+    let presented = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match presented {
+        Some(presented) if constant_time_eq(presented.as_bytes(), token.as_bytes()) => next.run(request).await,
+        _ => (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Bearer")],
+            "Missing or invalid bearer token",
+        )
+            .into_response(),
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// One client session: the channel its outgoing messages go through, the
+/// requests it has in flight and who the client is.
+#[derive(Clone)]
+struct Session {
+    notifier: Notifier,
+    in_flight: InFlight,
+    client: SharedClient,
+}
+
+impl Session {
+    fn new(notifier: Notifier, in_flight: InFlight, client: SharedClient) -> Self {
+        Self {
+            notifier,
+            in_flight,
+            client,
+        }
+    }
+
+    /// Handles one incoming message. Requests run on their own task, so that
+    /// a cancellation can arrive while they execute, and reply through the
+    /// session's channel.
+    fn receive<R>(&self, router: &R, body: Value)
+    where
+        R: mcp_server::Router + Clone + Send + Sync + 'static,
+    {
+        match serde_json::from_value::<JsonRpcMessage>(body) {
+            Ok(JsonRpcMessage::Request(request)) => {
+                let session = self.clone();
+                let router = router.clone();
+                tokio::spawn(async move {
+                    let reply = session.handle(router, request).await;
+                    let _ = session.notifier.send(reply);
+                });
+            }
+            Ok(JsonRpcMessage::Notification(notification)) if notification.method == CANCELLED_NOTIFICATION => {
+                self.cancel(notification.params.as_ref());
+            }
+            Ok(_) => {}
+            Err(e) => {
+                let _ = self.notifier.send(error_message(None, PARSE_ERROR, secret::scrub(&e.to_string())));
+            }
+        }
+    }
+
+    /// Runs `request` through the router with this session's notifier and
+    /// client identity, and a cancellation token registered under the request
+    /// id.
+    async fn handle<R>(&self, router: R, request: JsonRpcRequest) -> JsonRpcMessage
+    where
+        R: mcp_server::Router + Clone + Send + Sync + 'static,
+    {
+        let id = request.id;
+        let client = {
+            let mut client = self.client.lock().unwrap();
+            if request.method == "initialize" {
+                client.initialize(request.params.as_ref());
+            }
+            client.clone()
+        };
+        let token = id.map(|id| self.in_flight.start(id)).unwrap_or_default();
+        let call = audit::with_client(client, cancel::with_token(token, call(router, request)));
+        let reply = progress::with_notifier(self.notifier.clone(), call).await;
+        if let Some(id) = id {
+            self.in_flight.finish(id);
+        }
+        reply
+    }
+
+    fn cancel(&self, params: Option<&Value>) {
+        let Some(id) = params.and_then(|params| params.get("requestId")).and_then(Value::as_u64) else {
+            return;
+        };
+        let reason = params.and_then(|params| params.get("reason")).and_then(Value::as_str);
+        if self.in_flight.cancel(id) {
+            tracing::info!(request_id = id, reason, "Cancelling request");
+        }
+    }
+}
+
+/// Serves one session of newline-delimited JSON-RPC read from `reader` until
+/// it reaches end of file. Responses and the notifications sent while a
+/// request runs share one writer.
+pub async fn serve_lines<R, I, O>(router: R, reader: I, writer: O, client: SharedClient) -> Result<()>
+where
+    R: mcp_server::Router + Clone + Send + Sync + 'static,
+    I: AsyncRead + Unpin,
+    O: AsyncWrite + Unpin + Send + 'static,
+{
+    let (notifier, mut outgoing) = mpsc::unbounded_channel::<JsonRpcMessage>();
+    let write_task = tokio::spawn(async move {
+        let mut writer = writer;
+        while let Some(message) = outgoing.recv().await {
+            let mut line = serde_json::to_vec(&message)?;
+            line.push(b'\n');
+            writer.write_all(&line).await?;
+            writer.flush().await?;
+        }
+        Ok::<_, anyhow::Error>(())
+    });
+
+    let session = Session::new(notifier, InFlight::default(), client);
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<Value>(&line) {
+            Ok(body) => session.receive(&router, body),
+            Err(e) => {
+                let _ = session.notifier.send(error_message(None, PARSE_ERROR, secret::scrub(&e.to_string())));
+            }
+        }
+    }
+
+    // Requests still running keep their clones of the channel; the writer
+    // finishes once the last of them has replied.
+    drop(session);
+    write_task.await?
+}
+
+async fn call<R>(router: R, request: JsonRpcRequest) -> JsonRpcMessage
+where
+    R: mcp_server::Router + Clone + Send + Sync + 'static,
+{
+    let id = request.id;
+    tracing::info!(request_id = ?id, method = %request.method, "Received request");
+    match RouterService(router).call(request).await {
+        Ok(response) => JsonRpcMessage::Response(response),
+        Err(e) => {
+            let message = secret::scrub(&e.to_string());
+            tracing::error!(error = %message, "Request processing failed");
+            error_message(id, INTERNAL_ERROR, message)
+        }
+    }
+}
+
+fn error_message(id: Option<u64>, code: i32, message: String) -> JsonRpcMessage {
+    JsonRpcMessage::Error(JsonRpcError {
+        jsonrpc: "2.0".to_string(),
+        id,
+        error: ErrorData {
+            code,
+            message,
+            data: None,
+        },
+    })
+}
+
+/// The streamable HTTP session named by the `Mcp-Session-Id` header, or a
+/// fresh one for requests outside a session; `None` if the named session is
+/// unknown or has expired.
+fn http_session<R>(state: &AppState<R>, headers: &HeaderMap) -> Option<HttpSession> {
+    let Some(id) = headers.get(MCP_SESSION_ID) else {
+        return Some(HttpSession {
+            in_flight: InFlight::default(),
+            client: ClientIdentity::shared("http", None),
+            last_seen: Instant::now(),
+        });
+    };
+    let mut sessions = state.http_sessions.lock().unwrap();
+    id.to_str()
+        .ok()
+        .and_then(|id| find_http_session(&mut sessions, id, Instant::now()))
+}
+
+/// Looks up the session `id` as of `now`, dropping it if it has gone idle.
+fn find_http_session(sessions: &mut HashMap<String, HttpSession>, id: &str, now: Instant) -> Option<HttpSession> {
+    let session = sessions.get_mut(id)?;
+    if is_idle(session, now) {
+        sessions.remove(id);
+        return None;
+    }
+    session.last_seen = now;
+    Some(session.clone())
+}
+
+/// Whether `session` has had no request, and none running, for
+/// `HTTP_SESSION_IDLE_TIMEOUT` as of `now`.
+fn is_idle(session: &HttpSession, now: Instant) -> bool {
+    session.in_flight.is_empty() && now.saturating_duration_since(session.last_seen) >= HTTP_SESSION_IDLE_TIMEOUT
+}
+
+/// Answers with a single JSON body, or with an event stream carrying the
+/// request's notifications before its response when the client accepts one.
+/// Dropping the connection cancels the request.
+async fn handle_http_message<R>(
+    State(state): State<AppState<R>>,
+    headers: HeaderMap,
+    Json(body): Json<Value>,
+) -> Response
+where
+    R: mcp_server::Router + Clone + Send + Sync + 'static,
+{
+    let accepts_stream = headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .any(|value| value.contains("text/event-stream"));
+    // A 404 tells the client to initialize a new session.
+    let Some(http_session) = http_session(&state, &headers) else {
+        return (StatusCode::NOT_FOUND, "Unknown or expired session; initialize a new one").into_response();
+    };
+    let (notifier, mut outgoing) = mpsc::unbounded_channel();
+    let session = Session::new(notifier, http_session.in_flight, http_session.client);
+
+    let request = match serde_json::from_value::<JsonRpcMessage>(body) {
+        Ok(JsonRpcMessage::Request(request)) => request,
+        Ok(JsonRpcMessage::Notification(notification)) => {
+            if notification.method == CANCELLED_NOTIFICATION {
+                session.cancel(notification.params.as_ref());
+            }
+            return StatusCode::ACCEPTED.into_response();
+        }
+        Ok(_) => return StatusCode::ACCEPTED.into_response(),
+        Err(e) => return Json(error_message(None, PARSE_ERROR, secret::scrub(&e.to_string()))).into_response(),
+    };
+
+    let new_session = (request.method == "initialize").then(|| (state.http_sessions.clone(), session.client.clone()));
+    let id = request.id;
+    let in_flight = session.in_flight.clone();
+    let task = {
+        let session = session.clone();
+        tokio::spawn(async move {
+            let reply = session.handle(state.router, request).await;
+            let _ = session.notifier.send(reply);
+        })
+    };
+    drop(session);
+    let cancel_on_drop = CancelOnDrop { in_flight, id };
+
+    if !accepts_stream {
+        let _ = task.await;
+        drop(cancel_on_drop);
+        // The reply is sent last; notifications queued before it have nowhere
+        // to go.
+        let mut reply = None;
+        while let Ok(message) = outgoing.try_recv() {
+            reply = Some(message);
+        }
+        return match reply {
+            Some(reply) => with_session_header(Json(reply).into_response(), new_session),
+            None => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        };
+    }
+
+    let events = stream::unfold((outgoing, cancel_on_drop), |(mut outgoing, guard)| async move {
+        let message = outgoing.recv().await?;
+        Some((Ok::<_, Infallible>(message_event(&message)), (outgoing, guard)))
+    });
+    with_session_header(Sse::new(events).into_response(), new_session)
+}
+
+/// Cancels an HTTP request whose client went away before it completed.
+struct CancelOnDrop {
+    in_flight: InFlight,
+    id: Option<u64>,
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if let Some(id) = self.id
+            && self.in_flight.cancel(id)
+        {
+            tracing::info!(request_id = id, "Client disconnected; cancelling request");
+        }
+    }
+}
+
+/// Starts a streamable HTTP session in `sessions` on the response to
+/// `initialize`, for the client that sent it.
+fn with_session_header(mut response: Response, new_session: Option<(HttpSessions, SharedClient)>) -> Response {
+    if let Some((sessions, client)) = new_session {
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        if let Ok(value) = HeaderValue::from_str(&id) {
+            response.headers_mut().insert(MCP_SESSION_ID, value);
+            client.lock().unwrap().session = Some(id.clone());
+            let session = HttpSession {
+                in_flight: InFlight::default(),
+                client,
+                last_seen: Instant::now(),
+            };
+            insert_http_session(&mut sessions.lock().unwrap(), id, session, Instant::now());
+        }
+    }
+    response
+}
+
+/// Adds `session`, first dropping the sessions idle as of `now` and, when
+/// `MAX_HTTP_SESSIONS` remain, the one idle longest.
+fn insert_http_session(sessions: &mut HashMap<String, HttpSession>, id: String, session: HttpSession, now: Instant) {
+    sessions.retain(|_, session| !is_idle(session, now));
+    if sessions.len() >= MAX_HTTP_SESSIONS
+        && let Some(oldest) = sessions
+            .iter()
+            .min_by_key(|(_, session)| session.last_seen)
+            .map(|(id, _)| id.clone())
+        && let Some(evicted) = sessions.remove(&oldest)
+    {
+        tracing::info!(session = %oldest, "Too many HTTP sessions; dropping the longest idle one");
+        evicted.in_flight.cancel_all();
+    }
+    sessions.insert(id, session);
+}
+
+async fn handle_http_delete<R>(State(state): State<AppState<R>>, headers: HeaderMap) -> StatusCode {
+    let id = headers.get(MCP_SESSION_ID).and_then(|value| value.to_str().ok());
+    match id.and_then(|id| state.http_sessions.lock().unwrap().remove(id)) {
+        Some(session) => {
+            session.in_flight.cancel_all();
+            StatusCode::NO_CONTENT
+        }
+        None => StatusCode::NOT_FOUND,
+    }
+}
+
+fn message_event(message: &JsonRpcMessage) -> Event {
+    Event::default()
+        .event("message")
+        .data(serde_json::to_string(message).unwrap_or_default())
+}
+
+/// Removes an SSE session, cancelling its running requests, once its event
+/// stream is dropped by the client.
+struct SessionGuard {
+    id: String,
+    sessions: SseSessions,
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        if let Some(session) = self.sessions.lock().unwrap().remove(&self.id) {
+            session.in_flight.cancel_all();
+        }
+        tracing::info!(session_id = %self.id, "SSE session closed");
+    }
+}
+
+async fn handle_sse_connect<R>(
+    State(state): State<AppState<R>>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let id = uuid::Uuid::new_v4().simple().to_string();
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let session = Session::new(sender, InFlight::default(), ClientIdentity::shared("sse", Some(id.clone())));
+    state.sessions.lock().unwrap().insert(id.clone(), session);
+    tracing::info!(session_id = %id, "SSE session opened");
+
+    let endpoint = Event::default()
+        .event("endpoint")
+        .data(format!("{}?sessionId={}", SSE_MESSAGE_PATH, id));
+    let guard = SessionGuard {
+        id,
+        sessions: state.sessions,
+    };
+    let messages = stream::unfold((receiver, guard), |(mut receiver, guard)| async move {
+        let message = receiver.recv().await?;
+        Some((Ok(message_event(&message)), (receiver, guard)))
+    });
+
+    Sse::new(stream::once(async move { Ok(endpoint) }).chain(messages)).keep_alive(KeepAlive::default())
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SessionQuery {
+    session_id: String,
+}
+
+async fn handle_sse_message<R>(
+    State(state): State<AppState<R>>,
+    Query(query): Query<SessionQuery>,
+    Json(body): Json<Value>,
+) -> Response
+where
+    R: mcp_server::Router + Clone + Send + Sync + 'static,
+{
+    let Some(session) = state.sessions.lock().unwrap().get(&query.session_id).cloned() else {
+        return (StatusCode::NOT_FOUND, "Unknown SSE session").into_response();
+    };
+
+    session.receive(&state.router, body);
+    StatusCode::ACCEPTED.into_response()
+}
+
+/// Serves `router` on a Unix domain socket until interrupted. Every connection
+/// runs its own server session; all of them share the router's registry and
+/// the backend client.
+#[cfg(unix)]
+pub async fn serve_unix<R>(router: R, options: SocketOptions) -> Result<()>
+where
+    R: mcp_server::Router + Clone + Send + Sync + 'static,
+{
+    remove_stale_socket(&options.path).await?;
+    let listener = bind_private(&options.path, options.mode)?;
+    let _cleanup = SocketCleanup(options.path.clone());
+    tracing::info!("Serving MCP on unix socket {} (mode {:o})", options.path.display(), options.mode);
+
+    let mut next_session = 0u64;
+    loop {
+        let (stream, _) = tokio::select! {
+            accepted = listener.accept() => accepted?,
+            _ = tokio::signal::ctrl_c() => break,
+        };
+        next_session += 1;
+        let session = next_session;
+        let router = router.clone();
+        tokio::spawn(async move {
+            tracing::info!(session, "Unix socket session opened");
+            let (reader, writer) = stream.into_split();
+            let client = ClientIdentity::shared("unix", Some(session.to_string()));
+            if let Err(e) = serve_lines(router, reader, writer, client).await {
+                tracing::warn!(session, error = %secret::scrub(&e.to_string()), "Unix socket session failed");
+            }
+            tracing::info!(session, "Unix socket session closed");
+        });
+    }
+
+    tracing::info!("Shutting down unix socket transport");
+    Ok(())
+}
+
+/// Binds a socket at `path` that is never reachable with looser permissions
+/// than `mode`: it is bound inside a fresh 0700 directory next to `path`,
+/// restricted there, then renamed into place.
+#[cfg(unix)]
+fn bind_private(path: &Path, mode: u32) -> Result<tokio::net::UnixListener> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+    let parent = path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    // Kept short: socket paths are limited to about 100 bytes.
+    let staging = parent.join(format!(".mcp-{}", std::process::id()));
+    std::fs::DirBuilder::new()
+        .mode(0o700)
+        .create(&staging)
+        .with_context(|| format!("Failed to create {}", staging.display()))?;
+
+    let staged = staging.join("s");
+    let bound = tokio::net::UnixListener::bind(&staged)
+        .with_context(|| format!("Failed to bind {}", path.display()))
+        .and_then(|listener| {
+            std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(mode))
+                .with_context(|| format!("Failed to set permissions on {}", path.display()))?;
+            std::fs::rename(&staged, path).with_context(|| format!("Failed to bind {}", path.display()))?;
+            Ok(listener)
+        });
+    let _ = std::fs::remove_dir_all(&staging);
+    bound
+}
+
+/// Removes a socket file left behind by a server that is no longer running,
+/// refusing to touch live sockets or files that are not sockets.
+#[cfg(unix)]
+async fn remove_stale_socket(path: &Path) -> Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+
+    let Ok(metadata) = std::fs::symlink_metadata(path) else {
+        return Ok(());
+    };
+    if !metadata.file_type().is_socket() {
+        anyhow::bail!("{} exists and is not a socket", path.display());
+    }
+    if tokio::net::UnixStream::connect(path).await.is_ok() {
+        anyhow::bail!("{} is already in use by a running server", path.display());
+    }
+    std::fs::remove_file(path).with_context(|| format!("Failed to remove stale socket {}", path.display()))
+}
+
+#[cfg(unix)]
+struct SocketCleanup(PathBuf);
+
+#[cfg(unix)]
+impl Drop for SocketCleanup {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_spec::content::Content;
+    use mcp_spec::handler::{PromptError, ResourceError, ToolError};
+    use mcp_spec::prompt::Prompt;
+    use mcp_spec::protocol::ServerCapabilities;
+    use mcp_spec::resource::Resource;
+    use mcp_spec::tool::Tool;
+    use serde_json::json;
+    use std::future::Future;
+    use std::pin::Pin;
+
+    /// Signalled once the `batches` tool has committed its first batch.
+    static BATCHES_STARTED: tokio::sync::Notify = tokio::sync::Notify::const_new();
+
+    /// A router with no resources or prompts and a single unlisted tool,
+    /// `batches`, which commits batches until it is cancelled, for driving
+    /// the transport.
+    #[derive(Clone)]
+    struct TestRouter;
+
+    impl mcp_server::Router for TestRouter {
+        fn name(&self) -> String {
+            "test".to_string()
+        }
+
+        fn instructions(&self) -> String {
+            String::new()
+        }
+
+        fn capabilities(&self) -> ServerCapabilities {
+            mcp_server::router::CapabilitiesBuilder::new().with_tools(false).build()
+        }
+
+        fn list_tools(&self) -> Vec<Tool> {
+            Vec::new()
+        }
+
+        fn call_tool(
+            &self,
+            tool_name: &str,
+            _arguments: Value,
+        ) -> Pin<Box<dyn Future<Output = Result<Vec<Content>, ToolError>> + Send + 'static>> {
+            if tool_name != "batches" {
+                let error = ToolError::NotFound(tool_name.to_string());
+                return Box::pin(async move { Err(error) });
+            }
+            Box::pin(async move {
+                let batches = cancel::blocking(|| {
+                    let mut committed = 0;
+                    loop {
+                        cancel::check()
+                            .map_err(|e| cancel::partial(e, || format!("Cancelled with {} batches committed", committed)))?;
+                        committed += 1;
+                        if committed == 1 {
+                            BATCHES_STARTED.notify_one();
+                        }
+                        std::thread::sleep(Duration::from_millis(5));
+                    }
+                });
+                cancel::run(batches).await.map_err(|e| ToolError::ExecutionError(e.to_string()))
+            })
+        }
+
+        fn list_resources(&self) -> Vec<Resource> {
+            Vec::new()
+        }
+
+        fn read_resource(&self, uri: &str) -> Pin<Box<dyn Future<Output = Result<String, ResourceError>> + Send + 'static>> {
+            let error = ResourceError::NotFound(uri.to_string());
+            Box::pin(async move { Err(error) })
+        }
+
+        fn list_prompts(&self) -> Vec<Prompt> {
+            Vec::new()
+        }
+
+        fn get_prompt(&self, prompt_name: &str) -> Pin<Box<dyn Future<Output = Result<String, PromptError>> + Send + 'static>> {
+            let error = PromptError::NotFound(prompt_name.to_string());
+            Box::pin(async move { Err(error) })
+        }
+    }
+
+    #[tokio::test]
+    async fn cancelled_notifications_stop_the_call_and_report_committed_work() {
+        let (client, server) = tokio::io::duplex(4096);
+        let (reader, writer) = tokio::io::split(server);
+        tokio::spawn(serve_lines(TestRouter, reader, writer, ClientIdentity::shared("stdio", None)));
+        let (replies, mut requests) = tokio::io::split(client);
+        let mut replies = BufReader::new(replies).lines();
+
+        let call = json!({"jsonrpc": "2.0", "id": 3, "method": "tools/call", "params": {"name": "batches"}});
+        requests.write_all(format!("{}\n", call).as_bytes()).await.unwrap();
+        BATCHES_STARTED.notified().await;
+        let cancelled = json!({
+            "jsonrpc": "2.0",
+            "method": CANCELLED_NOTIFICATION,
+            "params": {"requestId": 3, "reason": "user pressed stop"},
+        });
+        requests.write_all(format!("{}\n", cancelled).as_bytes()).await.unwrap();
+
+        let reply = tokio::time::timeout(Duration::from_secs(5), replies.next_line()).await.unwrap().unwrap().unwrap();
+        let reply: Value = serde_json::from_str(&reply).unwrap();
+        assert_eq!(reply["id"], 3);
+        assert_eq!(reply["result"]["isError"], true);
+        let text = reply["result"]["content"][0]["text"].as_str().unwrap();
+        assert!(text.contains("Cancelled with "), "{}", text);
+    }
+
+    /// Serves the streamable HTTP transport on a free loopback port and
+    /// returns the URL of its endpoint.
+    async fn serve_http(auth_token: Option<&str>) -> String {
+        let options = HttpOptions {
+            bind: "127.0.0.1:0".parse().unwrap(),
+            auth_token: auth_token.map(Secret::new),
+            cors_origins: Vec::new(),
+        };
+        let app = app(TestRouter, TransportKind::Http, &options).unwrap();
+        let listener = tokio::net::TcpListener::bind(options.bind).await.unwrap();
+        let url = format!("http://{}{}", listener.local_addr().unwrap(), MCP_PATH);
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        url
+    }
+
+    fn http() -> reqwest::Client {
+        reqwest::Client::builder().no_proxy().build().unwrap()
+    }
+
+    fn request(id: u64, method: &str) -> Value {
+        json!({"jsonrpc": "2.0", "id": id, "method": method, "params": {}})
+    }
+
+    fn session(id: &str) -> HttpSession {
+        HttpSession {
+            in_flight: InFlight::default(),
+            client: ClientIdentity::shared("http", Some(id.to_string())),
+            last_seen: Instant::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn sessions_start_on_initialize_and_end_on_delete() {
+        let url = serve_http(None).await;
+        let client = http();
+
+        let initialized = client.post(&url).json(&request(1, "initialize")).send().await.unwrap();
+        assert_eq!(initialized.status(), StatusCode::OK);
+        let id = initialized.headers()[MCP_SESSION_ID.as_str()].to_str().unwrap().to_string();
+
+        let in_session = client.post(&url).header(MCP_SESSION_ID.as_str(), &id).json(&request(2, "tools/list"));
+        assert_eq!(in_session.send().await.unwrap().status(), StatusCode::OK);
+
+        let deleted = client.delete(&url).header(MCP_SESSION_ID.as_str(), &id).send().await.unwrap();
+        assert_eq!(deleted.status(), StatusCode::NO_CONTENT);
+        let after_delete = client.post(&url).header(MCP_SESSION_ID.as_str(), &id).json(&request(3, "tools/list"));
+        assert_eq!(after_delete.send().await.unwrap().status(), StatusCode::NOT_FOUND);
+        let deleted_again = client.delete(&url).header(MCP_SESSION_ID.as_str(), &id).send().await.unwrap();
+        assert_eq!(deleted_again.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn unknown_sessions_are_not_found_rather_than_restarted() {
+        let url = serve_http(None).await;
+        let client = http();
+
+        let unknown = client.post(&url).header(MCP_SESSION_ID.as_str(), "unknown").json(&request(1, "initialize"));
+        let response = unknown.send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert!(response.headers().get(MCP_SESSION_ID.as_str()).is_none());
+
+        let sessionless = client.post(&url).json(&request(2, "tools/list")).send().await.unwrap();
+        assert_eq!(sessionless.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn requests_need_the_bearer_token() {
+        let url = serve_http(Some("s3cret-token")).await;
+        let client = http();
+
+        let missing = client.post(&url).json(&request(1, "tools/list")).send().await.unwrap();
+        assert_eq!(missing.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(missing.headers()[header::WWW_AUTHENTICATE], "Bearer");
+        let wrong = client.post(&url).bearer_auth("s3cret-tokeN").json(&request(2, "tools/list"));
+        assert_eq!(wrong.send().await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        let right = client.post(&url).bearer_auth("s3cret-token").json(&request(3, "tools/list"));
+        assert_eq!(right.send().await.unwrap().status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn idle_sessions_expire_unless_a_request_is_running() {
+        let mut sessions = HashMap::new();
+        let now = Instant::now();
+        sessions.insert("idle".to_string(), session("idle"));
+        let busy = session("busy");
+        let _running = busy.in_flight.start(1);
+        sessions.insert("busy".to_string(), busy);
+
+        let later = now + HTTP_SESSION_IDLE_TIMEOUT;
+        assert!(find_http_session(&mut sessions, "idle", now).is_some());
+        assert!(find_http_session(&mut sessions, "idle", later).is_none());
+        assert!(!sessions.contains_key("idle"));
+        assert!(find_http_session(&mut sessions, "busy", later).is_some());
+    }
+
+    #[test]
+    fn the_session_cap_drops_the_longest_idle_session() {
+        let mut sessions = HashMap::new();
+        let now = Instant::now();
+        for index in 0..MAX_HTTP_SESSIONS {
+            let mut session = session(&index.to_string());
+            session.last_seen = now + Duration::from_millis(index as u64);
+            sessions.insert(index.to_string(), session);
+        }
+
+        let later = now + Duration::from_secs(1);
+        insert_http_session(&mut sessions, "new".to_string(), session("new"), later);
+        assert_eq!(sessions.len(), MAX_HTTP_SESSIONS);
+        assert!(!sessions.contains_key("0"));
+        assert!(sessions.contains_key("1") && sessions.contains_key("new"));
+
+        let expired = later + Duration::from_secs(1) + HTTP_SESSION_IDLE_TIMEOUT;
+        insert_http_session(&mut sessions, "fresh".to_string(), session("fresh"), expired);
+        assert_eq!(sessions.keys().collect::<Vec<_>>(), ["fresh"]);
+    }
+}
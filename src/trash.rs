@@ -0,0 +1,491 @@
+use crate::client::{ChromaClient, Collection};
+use crate::dump::{self, ConflictPolicy, ImportOptions};
+use crate::registry::ToolOutput;
+use crate::secret;
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const DEFAULT_RETENTION_HOURS: u64 = 7 * 24;
+
+static GLOBAL_OPTIONS: OnceLock<TrashOptions> = OnceLock::new();
+
+#[derive(Debug, Clone)]
+pub struct TrashOptions {
+    pub dir: PathBuf,
+    pub retention: Duration,
+    pub allow_hard_delete: bool,
+}
+
+impl Default for TrashOptions {
+    fn default() -> Self {
+        Self {
+            dir: default_dir(),
+            retention: Duration::from_secs(DEFAULT_RETENTION_HOURS * 3600),
+            allow_hard_delete: false,
+        }
+    }
+}
+
+/// The per-user directory the trash is kept in unless `--trash-dir` says
+/// otherwise: `mcp-chroma/trash` under `$XDG_DATA_HOME`, `%LOCALAPPDATA%` or
+/// `~/.local/share`, in that order.
+pub fn default_dir() -> PathBuf {
+    let var = |name| std::env::var_os(name).filter(|value| !value.is_empty()).map(PathBuf::from);
+    var("XDG_DATA_HOME")
+        .or_else(|| var("LOCALAPPDATA"))
+        .or_else(|| var("HOME").map(|home| home.join(".local").join("share")))
+        .unwrap_or_else(std::env::temp_dir)
+        .join("mcp-chroma")
+        .join("trash")
+}
+
+pub fn configure(options: TrashOptions) {
+    if GLOBAL_OPTIONS.set(options).is_err() {
+        tracing::warn!("Trash is already configured");
+    }
+}
+
+fn options() -> &'static TrashOptions {
+    GLOBAL_OPTIONS.get_or_init(unconfigured)
+}
+
+#[cfg(not(test))]
+fn unconfigured() -> TrashOptions {
+    TrashOptions::default()
+}
+
+/// Unit tests keep their trash in a directory of their own under the system
+/// temp dir.
+#[cfg(test)]
+fn unconfigured() -> TrashOptions {
+    TrashOptions {
+        dir: std::env::temp_dir().join(format!("mcp-chroma-trash-{}", std::process::id())),
+        ..TrashOptions::default()
+    }
+}
+
+/// Whether the resolved `path` lies inside the trash directory.
+pub fn contains(path: &Path) -> bool {
+    std::fs::canonicalize(&options().dir).is_ok_and(|dir| path.starts_with(dir))
+}
+
+/// Fails unless deleting without the trash was enabled with
+/// `--allow-hard-delete`.
+pub fn check_hard_delete() -> Result<()> {
+    if !options().allow_hard_delete {
+        bail!("Permanent deletes are disabled; start the server with --allow-hard-delete to enable them");
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrashKind {
+    Collection,
+    Documents,
+}
+
+/// A deleted collection or set of records, kept as a dump next to this
+/// description until it is restored, purged or expires.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashEntry {
+    pub trash_id: String,
+    pub kind: TrashKind,
+    pub collection_name: String,
+    pub records: usize,
+    /// Ids of the deleted records, for document deletes
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ids: Vec<String>,
+    /// Unix time of the delete, in seconds
+    pub deleted_at: u64,
+    /// Unix time after which the entry is purged, in seconds
+    pub expires_at: u64,
+}
+
+impl TrashEntry {
+    fn new(kind: TrashKind, collection_name: &str) -> Self {
+        let deleted_at = unix_now();
+        Self {
+            trash_id: uuid::Uuid::new_v4().simple().to_string()[..12].to_string(),
+            kind,
+            collection_name: collection_name.to_string(),
+            records: 0,
+            ids: Vec::new(),
+            deleted_at,
+            expires_at: deleted_at + options().retention.as_secs(),
+        }
+    }
+
+    fn entry_path(&self) -> PathBuf {
+        options().dir.join(format!("{}.json", self.trash_id))
+    }
+
+    fn dump_path(&self) -> PathBuf {
+        options().dir.join(format!("{}.jsonl", self.trash_id))
+    }
+
+    /// Where the entry is moved while it is being restored, which takes it
+    /// out of the listing.
+    fn restoring_path(&self) -> PathBuf {
+        options().dir.join(format!("{}.restoring", self.trash_id))
+    }
+
+    fn save(&self) -> Result<()> {
+        std::fs::write(self.entry_path(), serde_json::to_vec_pretty(self)?)
+            .map_err(|e| anyhow!("Cannot write trash entry '{}': {}", self.entry_path().display(), e))
+    }
+
+    fn remove(&self) -> Result<()> {
+        for path in [self.dump_path(), self.entry_path()] {
+            match std::fs::remove_file(&path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                    bail!("Cannot remove '{}': {}", path.display(), e)
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the dump with `write`, records the entry, then runs `delete`;
+    /// the entry is dropped again if either step fails.
+    fn commit(
+        mut self,
+        write: impl FnOnce(&Path) -> Result<usize>,
+        delete: impl FnOnce() -> Result<()>,
+    ) -> Result<Self> {
+        create_private_dir(&options().dir)
+            .map_err(|e| anyhow!("Cannot create trash directory '{}': {}", options().dir.display(), e))?;
+        let result = write(&self.dump_path()).and_then(|records| {
+            self.records = records;
+            self.save()?;
+            delete()
+        });
+        if let Err(e) = result {
+            let _ = self.remove();
+            return Err(e);
+        }
+
+        tracing::info!(
+            trash_id = %self.trash_id,
+            collection = %self.collection_name,
+            "Moved {} records to the trash",
+            self.records
+        );
+        purge_expired();
+        Ok(self)
+    }
+}
+
+impl ToolOutput for TrashEntry {
+    fn summary(&self) -> String {
+        let what = match self.kind {
+            TrashKind::Collection => format!("collection '{}'", self.collection_name),
+            TrashKind::Documents => format!("{} records of '{}'", self.records, self.collection_name),
+        };
+        format!("Trash entry {}: {}, deleted at {}", self.trash_id, what, self.deleted_at)
+    }
+}
+
+/// Creates `dir` and its missing parents readable by the current user only,
+/// since the trash holds copies of deleted records.
+fn create_private_dir(dir: &Path) -> std::io::Result<()> {
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(dir)
+}
+
+/// Moves the collection `name` to the trash, then deletes it.
+pub fn trash_collection(client: &ChromaClient, name: &str) -> Result<TrashEntry> {
+    let collection = client.get_collection(name)?;
+    TrashEntry::new(TrashKind::Collection, name).commit(
+        |path| Ok(dump::export_collection(&collection, path, true, dump::DEFAULT_PAGE_SIZE, false)?.records),
+        || client.delete_collection(name),
+    )
+}
+
+/// Moves the records `ids` of `collection` to the trash, then deletes them.
+pub fn trash_documents(collection: &Collection, ids: Vec<String>) -> Result<TrashEntry> {
+    let mut entry = TrashEntry::new(TrashKind::Documents, &collection.name);
+    entry.ids = ids.clone();
+    entry.commit(
+        |path| dump::export_records(collection, ids.clone(), path),
+        || collection.delete(ids.clone()),
+    )
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TrashList {
+    pub entries: Vec<TrashEntry>,
+    pub count: usize,
+}
+
+impl ToolOutput for TrashList {
+    fn summary(&self) -> String {
+        let mut summary = format!("{} trash entries", self.count);
+        for entry in &self.entries {
+            summary.push('\n');
+            summary.push_str(&entry.summary());
+        }
+        summary
+    }
+}
+
+/// Lists the trash, newest first, after purging expired entries.
+pub fn list() -> Result<Vec<TrashEntry>> {
+    purge_expired();
+    let mut entries = read_entries()?;
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.deleted_at));
+    Ok(entries)
+}
+
+pub fn entry(trash_id: &str) -> Result<TrashEntry> {
+    list()?
+        .into_iter()
+        .find(|entry| entry.trash_id == trash_id)
+        .ok_or_else(|| anyhow!("Trash entry '{}' not found", trash_id))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RestoreReport {
+    pub trash_id: String,
+    pub kind: TrashKind,
+    pub collection_name: String,
+    pub records_restored: usize,
+    pub records_skipped: usize,
+}
+
+impl ToolOutput for RestoreReport {
+    fn summary(&self) -> String {
+        format!(
+            "Restored {} records into '{}' from trash entry {} ({} skipped)",
+            self.records_restored, self.collection_name, self.trash_id, self.records_skipped
+        )
+    }
+}
+
+/// Restores `entry` into `collection_name`, or into the collection it was
+/// deleted from, and removes it from the trash. The entry is taken out of the
+/// trash before the import, so that it cannot be restored twice, and put
+/// back if the import fails.
+pub fn restore(
+    client: &ChromaClient,
+    entry: &TrashEntry,
+    collection_name: Option<String>,
+    on_conflict: ConflictPolicy,
+) -> Result<RestoreReport> {
+    let collection_name = collection_name.unwrap_or_else(|| entry.collection_name.clone());
+    if entry.kind == TrashKind::Collection && client.list_collections(None, None)?.contains(&collection_name) {
+        bail!(
+            "Collection '{}' already exists; pass collection_name to restore it under another name",
+            collection_name
+        );
+    }
+
+    std::fs::rename(entry.entry_path(), entry.restoring_path()).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => anyhow!("Trash entry '{}' not found", entry.trash_id),
+        _ => anyhow!("Cannot restore trash entry '{}': {}", entry.trash_id, e),
+    })?;
+    let imported = dump::import_collection(
+        client,
+        &ImportOptions {
+            input_path: entry.dump_path(),
+            collection_name: Some(collection_name.clone()),
+            start_line: 1,
+            on_conflict,
+            batch_size: dump::DEFAULT_IMPORT_BATCH_SIZE,
+        },
+    );
+    let report = match imported {
+        Ok(report) => report,
+        Err(e) => {
+            if let Err(put_back) = std::fs::rename(entry.restoring_path(), entry.entry_path()) {
+                tracing::warn!(trash_id = %entry.trash_id, "Cannot put back trash entry: {}", put_back);
+            }
+            return Err(e);
+        }
+    };
+    for path in [entry.dump_path(), entry.restoring_path()] {
+        if let Err(e) = std::fs::remove_file(&path) {
+            tracing::warn!(trash_id = %entry.trash_id, "Cannot remove '{}' after restoring: {}", path.display(), e);
+        }
+    }
+
+    Ok(RestoreReport {
+        trash_id: entry.trash_id.clone(),
+        kind: entry.kind,
+        collection_name,
+        records_restored: report.records_written,
+        records_skipped: report.records_skipped,
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PurgeReport {
+    pub purged: Vec<String>,
+    pub records: usize,
+}
+
+impl ToolOutput for PurgeReport {
+    fn summary(&self) -> String {
+        format!(
+            "Permanently deleted {} trash entries holding {} records",
+            self.purged.len(),
+            self.records
+        )
+    }
+}
+
+/// Permanently deletes `entries` from the trash.
+pub fn purge(entries: &[TrashEntry]) -> Result<PurgeReport> {
+    let mut report = PurgeReport {
+        purged: Vec::new(),
+        records: 0,
+    };
+    for entry in entries {
+        entry.remove()?;
+        report.purged.push(entry.trash_id.clone());
+        report.records += entry.records;
+    }
+    Ok(report)
+}
+
+/// Deletes entries past their retention; failures are logged, not returned.
+fn purge_expired() {
+    let now = unix_now();
+    let entries = match read_entries() {
+        Ok(entries) => entries,
+        Err(e) => {
+            tracing::warn!("Cannot read the trash: {}", secret::scrub(&e.to_string()));
+            return;
+        }
+    };
+    for entry in entries.iter().filter(|entry| entry.expires_at <= now) {
+        match entry.remove() {
+            Ok(()) => tracing::info!(trash_id = %entry.trash_id, "Purged expired trash entry"),
+            Err(e) => tracing::warn!(trash_id = %entry.trash_id, "Cannot purge expired trash entry: {}", secret::scrub(&e.to_string())),
+        }
+    }
+}
+
+fn read_entries() -> Result<Vec<TrashEntry>> {
+    let dir = &options().dir;
+    let listing = match std::fs::read_dir(dir) {
+        Ok(listing) => listing,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => bail!("Cannot read trash directory '{}': {}", dir.display(), e),
+    };
+
+    let mut entries = Vec::new();
+    for file in listing {
+        let path = file?.path();
+        if path.extension().is_none_or(|extension| extension != "json") {
+            continue;
+        }
+        let entry = std::fs::read(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|bytes| Ok(serde_json::from_slice(&bytes)?));
+        match entry {
+            Ok(entry) => entries.push(entry),
+            Err(e) => tracing::warn!("Skipping unreadable trash entry '{}': {}", path.display(), secret::scrub(&e.to_string())),
+        }
+    }
+    Ok(entries)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::get_client;
+    use serde_json::json;
+
+    /// A collection in the in-memory backend with the records `ids`.
+    fn collection(name: &str, ids: &[&str]) -> Collection {
+        let client = get_client();
+        client.create_collection(name, None, None).unwrap();
+        let collection = client.get_collection(name).unwrap();
+        let ids: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+        let metadatas = ids.iter().map(|id| json!({"id": id})).collect();
+        collection.add(ids.clone(), Some(metadatas), ids).unwrap();
+        collection
+    }
+
+    fn listed(entry: &TrashEntry) -> bool {
+        list().unwrap().iter().any(|listed| listed.trash_id == entry.trash_id)
+    }
+
+    #[test]
+    fn trashed_documents_can_be_restored_once() {
+        let collection = collection("trash_documents", &["a", "b", "c"]);
+        let entry = trash_documents(&collection, vec!["a".to_string(), "b".to_string()]).unwrap();
+        assert_eq!((entry.records, collection.count().unwrap()), (2, 1));
+        assert!(listed(&entry));
+
+        let report = restore(&get_client(), &entry, None, ConflictPolicy::Skip).unwrap();
+        assert_eq!((report.records_restored, report.collection_name.as_str()), (2, "trash_documents"));
+        assert_eq!(collection.count().unwrap(), 3);
+        assert!(!listed(&entry));
+        assert!(!entry.dump_path().exists() && !entry.restoring_path().exists());
+
+        let again = restore(&get_client(), &entry, None, ConflictPolicy::Skip).unwrap_err();
+        assert_eq!(again.to_string(), format!("Trash entry '{}' not found", entry.trash_id));
+    }
+
+    #[test]
+    fn failed_restores_leave_the_entry_in_the_trash() {
+        let collection = collection("trash_conflict", &["a", "b"]);
+        let entry = trash_documents(&collection, vec!["a".to_string()]).unwrap();
+        collection.add(vec!["new a".to_string()], None, vec!["a".to_string()]).unwrap();
+
+        let failed = restore(&get_client(), &entry, None, ConflictPolicy::Fail).unwrap_err();
+        assert!(failed.to_string().contains("Record 'a' already exists"));
+        assert!(listed(&entry));
+        restore(&get_client(), &entry, None, ConflictPolicy::Overwrite).unwrap();
+        assert!(!listed(&entry));
+    }
+
+    #[test]
+    fn restoring_a_collection_refuses_to_replace_an_existing_one() {
+        collection("trash_collection", &["a", "b"]);
+        let client = get_client();
+        let entry = trash_collection(&client, "trash_collection").unwrap();
+        assert!(!client.list_collections(None, None).unwrap().contains(&"trash_collection".to_string()));
+        collection("trash_collection", &[]);
+
+        let refused = restore(&client, &entry, None, ConflictPolicy::Skip).unwrap_err();
+        assert!(refused.to_string().contains("already exists"));
+        assert!(listed(&entry));
+
+        let report = restore(&client, &entry, Some("trash_collection_restored".to_string()), ConflictPolicy::Skip).unwrap();
+        assert_eq!(report.records_restored, 2);
+        assert_eq!(client.get_collection("trash_collection_restored").unwrap().count().unwrap(), 2);
+    }
+
+    #[test]
+    fn purged_and_expired_entries_are_removed() {
+        let collection = collection("trash_purge", &["a", "b", "c"]);
+        let purged = trash_documents(&collection, vec!["a".to_string()]).unwrap();
+        let report = purge(std::slice::from_ref(&purged)).unwrap();
+        assert_eq!((report.purged, report.records), (vec![purged.trash_id.clone()], 1));
+        assert!(!listed(&purged) && !purged.dump_path().exists());
+
+        let mut expired = trash_documents(&collection, vec!["b".to_string()]).unwrap();
+        let kept = trash_documents(&collection, vec!["c".to_string()]).unwrap();
+        expired.expires_at = unix_now() - 1;
+        expired.save().unwrap();
+        assert!(!listed(&expired) && !expired.dump_path().exists());
+        assert!(listed(&kept));
+    }
+}